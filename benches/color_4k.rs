@@ -0,0 +1,136 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use twilight::image::{convert_color, ColorFormat, ImageBuf};
+use twilight::util::AsUsize;
+
+const WIDTH: u32 = 3840;
+const HEIGHT: u32 = 2160;
+
+/// Fraction bits of the fixed point coefficients, as in the converter
+const SHIFT: i32 = 16;
+
+/// Gradients with a grid of sharp lines, somewhere between a photo and a desktop
+fn synthetic_frame() -> ImageBuf {
+    let mut img = ImageBuf::alloc(WIDTH, HEIGHT, None, ColorFormat::Bgra8888);
+    let stride = img.stride.as_usize();
+
+    for y in 0..HEIGHT {
+        let row = &mut img.data[y.as_usize() * stride..][..WIDTH.as_usize() * 4];
+
+        for (x, pixel) in (0..WIDTH).zip(row.chunks_exact_mut(4)) {
+            let line = x % 97 == 0 || y % 61 == 0;
+            let bgra = if line {
+                [0x10, 0x10, 0x10, 0xFF]
+            } else {
+                [
+                    (x * 255 / WIDTH) as u8,
+                    (y * 255 / HEIGHT) as u8,
+                    ((x + y) % 256) as u8,
+                    0xFF,
+                ]
+            };
+            pixel.copy_from_slice(&bgra);
+        }
+    }
+
+    img
+}
+
+fn fixed(x: f64) -> i32 {
+    (x * (1 << SHIFT) as f64).round() as i32
+}
+
+/// BT.601 limited range of a BGRA pixel, one at a time
+fn scalar_yuv(coef: &[[i32; 3]; 3], [b, g, r, _]: [u8; 4]) -> [u8; 3] {
+    let offsets = [16, 128, 128];
+    std::array::from_fn(|i| {
+        let [cr, cg, cb] = coef[i];
+        let sum = r as i32 * cr + g as i32 * cg + b as i32 * cb;
+        ((sum + (offsets[i] << SHIFT) + (1 << (SHIFT - 1))) >> SHIFT).clamp(0, 255) as u8
+    })
+}
+
+/// Plain loop over the pixels, as the baseline of the SIMD path
+fn scalar_bgra_to_i420(src: &ImageBuf, dst: &mut ImageBuf) {
+    let (kr, kb) = (0.299, 0.114);
+    let kg = 1.0 - kr - kb;
+    let (y_range, c_range) = (219.0 / 255.0, 224.0 / 255.0);
+    let (u_scale, v_scale) = (c_range / (2.0 * (1.0 - kb)), c_range / (2.0 * (1.0 - kr)));
+    let coef = [
+        [kr * y_range, kg * y_range, kb * y_range],
+        [-kr * u_scale, -kg * u_scale, (1.0 - kb) * u_scale],
+        [(1.0 - kr) * v_scale, -kg * v_scale, -kb * v_scale],
+    ]
+    .map(|x| x.map(fixed));
+
+    let (width, height) = (src.width.as_usize(), src.height.as_usize());
+    let (src_stride, dst_stride) = (src.stride.as_usize(), dst.stride.as_usize());
+    let pixel = |x: usize, y: usize| -> [u8; 4] {
+        src.data[y * src_stride + x * 4..][..4].try_into().unwrap()
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            dst.data[y * dst_stride + x] = scalar_yuv(&coef, pixel(x, y))[0];
+        }
+    }
+
+    let chroma_stride = dst_stride.div_ceil(2);
+    let chroma_rows = height.div_ceil(2);
+    let u_plane = dst_stride * height;
+    let v_plane = u_plane + chroma_stride * chroma_rows;
+
+    for cy in 0..chroma_rows {
+        for cx in 0..width.div_ceil(2) {
+            let mut sum = [0u32; 4];
+            let mut count = 0;
+            for y in (cy * 2)..(cy * 2 + 2).min(height) {
+                for x in (cx * 2)..(cx * 2 + 2).min(width) {
+                    for (s, p) in sum.iter_mut().zip(pixel(x, y)) {
+                        *s += p as u32;
+                    }
+                    count += 1;
+                }
+            }
+
+            let average = sum.map(|x| ((x + count / 2) / count) as u8);
+            let [_, u, v] = scalar_yuv(&coef, average);
+            dst.data[u_plane + cy * chroma_stride + cx] = u;
+            dst.data[v_plane + cy * chroma_stride + cx] = v;
+        }
+    }
+}
+
+fn rgb_to_yuv(c: &mut Criterion) {
+    let src = synthetic_frame();
+    let mut dst = ImageBuf::alloc(WIDTH, HEIGHT, None, ColorFormat::I420);
+
+    let mut group = c.benchmark_group("color_4k_bgra_to_i420");
+    group.throughput(Throughput::Elements(1));
+    group.sample_size(20);
+
+    group.bench_function("simd", |b| b.iter(|| convert_color(&src, &mut dst)));
+    group.bench_function("scalar", |b| b.iter(|| scalar_bgra_to_i420(&src, &mut dst)));
+
+    group.finish();
+}
+
+fn yuv_to_rgb(c: &mut Criterion) {
+    let mut yuv = ImageBuf::alloc(WIDTH, HEIGHT, None, ColorFormat::I420);
+    convert_color(&synthetic_frame(), &mut yuv);
+
+    let mut group = c.benchmark_group("color_4k_i420_to_rgb");
+    group.throughput(Throughput::Elements(1));
+    group.sample_size(20);
+
+    for format in [ColorFormat::Bgra8888, ColorFormat::Rgb24] {
+        let mut dst = ImageBuf::alloc(WIDTH, HEIGHT, None, format);
+        group.bench_function(format!("{format:?}"), |b| {
+            b.iter(|| convert_color(&yuv, &mut dst))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, rgb_to_yuv, yuv_to_rgb);
+criterion_main!(benches);
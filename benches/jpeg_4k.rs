@@ -0,0 +1,90 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use twilight::image::{ColorFormat, ImageBuf, Rect};
+use twilight::util::{AsUsize, FramePool};
+use twilight::video::decoder::jpeg::JpegDecoder;
+use twilight::video::decoder::DecoderStage;
+use twilight::video::encoder::jpeg::{encode_tiles, strips, STRIP_HEIGHT};
+use twilight::video::encoder::JpegParams;
+
+const WIDTH: u32 = 3840;
+const HEIGHT: u32 = 2160;
+
+const PARAMS: JpegParams = JpegParams {
+    quality: 90,
+    yuv444: false,
+};
+
+/// Gradients with a grid of sharp lines, somewhere between a photo and a desktop
+fn synthetic_frame() -> ImageBuf {
+    let mut img = ImageBuf::alloc(WIDTH, HEIGHT, None, ColorFormat::Bgra8888);
+    let stride = img.stride.as_usize();
+
+    for y in 0..HEIGHT {
+        let row = &mut img.data[y.as_usize() * stride..][..WIDTH.as_usize() * 4];
+
+        for (x, pixel) in (0..WIDTH).zip(row.chunks_exact_mut(4)) {
+            let line = x % 97 == 0 || y % 61 == 0;
+            let bgra = if line {
+                [0x10, 0x10, 0x10, 0xFF]
+            } else {
+                [
+                    (x * 255 / WIDTH) as u8,
+                    (y * 255 / HEIGHT) as u8,
+                    ((x + y) % 256) as u8,
+                    0xFF,
+                ]
+            };
+            pixel.copy_from_slice(&bgra);
+        }
+    }
+
+    img
+}
+
+fn layouts() -> [(&'static str, Vec<Rect>); 2] {
+    [
+        ("whole", vec![Rect::new(0, 0, WIDTH, HEIGHT)]),
+        ("strips", strips(WIDTH, HEIGHT, STRIP_HEIGHT)),
+    ]
+}
+
+fn encode(c: &mut Criterion) {
+    let img = synthetic_frame();
+    // reused across iterations, as by the encoder stage
+    let pool = FramePool::default();
+
+    let mut group = c.benchmark_group("jpeg_4k_encode");
+    group.throughput(Throughput::Elements(1));
+    group.sample_size(20);
+
+    for (name, rects) in layouts() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &rects, |b, rects| {
+            b.iter(|| encode_tiles(&img, rects, PARAMS, &pool).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    let img = synthetic_frame();
+    let mut decoder = JpegDecoder::new(WIDTH, HEIGHT).unwrap();
+
+    let mut group = c.benchmark_group("jpeg_4k_decode");
+    group.throughput(Throughput::Elements(1));
+    group.sample_size(20);
+
+    for (name, rects) in layouts() {
+        let encoded = encode_tiles(&img, &rects, PARAMS, &FramePool::default()).unwrap();
+        let tiles: Vec<&[u8]> = encoded.iter().map(|x| &x[..]).collect();
+
+        group.bench_with_input(BenchmarkId::from_parameter(name), &tiles, |b, tiles| {
+            b.iter(|| decoder.decode_tiles(tiles).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, encode, decode);
+criterion_main!(benches);
@@ -1,3 +1,4 @@
 //TODO: Implement audio streaming and remove this
 #![allow(unused)]
+#[cfg(windows)]
 mod capture_wasapi;
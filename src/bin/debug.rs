@@ -3,18 +3,29 @@ use std::str::FromStr;
 use tokio::runtime::Runtime;
 use tokio::task::LocalSet;
 use twilight::client::ClientLaunchArgs;
+use twilight::network::dto::auth::AuthType;
+use twilight::server::normal_defaults;
 
 fn main() {
+    #[cfg(windows)]
     twilight::platform::win32::init_dpi();
     env_logger::init();
 
     let runtime = Runtime::new().expect("starting tokio runtime");
     let rt = runtime.handle().clone();
 
+    // client and server run together, so skip the auth for convenience
+    let mut config = normal_defaults();
+    config.auth.username = true;
+
     std::thread::spawn(move || {
         let local = LocalSet::new();
 
-        local.spawn_local(async { twilight::server::serve().await.expect("launching server") });
+        local.spawn_local(async move {
+            twilight::server::serve(config)
+                .await
+                .expect("launching server")
+        });
         rt.block_on(local);
     });
 
@@ -24,6 +35,16 @@ fn main() {
         rt,
         ClientLaunchArgs {
             url: FromStr::from_str("twilightc://localhost/twilight").unwrap(),
+            record: None,
+            monitor: None,
+            codec: None,
+            max_resolution: None,
+            max_fps: None,
+            insecure: false,
+            known_hosts: None,
+            auth: Some(AuthType::Username),
+            username: Some("debug".into()),
+            token: None,
         },
     );
 }
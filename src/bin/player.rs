@@ -0,0 +1,23 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use tokio::runtime::Runtime;
+
+/// Play back a session recorded by the client with `--record`.
+#[derive(Parser, Debug)]
+#[command(version, long_about = None)]
+struct PlayerArgs {
+    /// Path to the recording
+    path: PathBuf,
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = PlayerArgs::parse();
+
+    let runtime = Runtime::new().expect("starting tokio runtime");
+    let rt = runtime.handle().clone();
+
+    twilight::viewer::launch_replay(rt, args.path);
+}
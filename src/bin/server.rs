@@ -1,12 +1,56 @@
+use clap::Parser;
 use tokio::task::LocalSet;
+use twilight::server::{
+    generate_token, hash_password, hash_token, normal_defaults, ServerConfig, ServerLaunchArgs,
+};
 
 #[tokio::main]
 async fn main() {
+    #[cfg(windows)]
     twilight::platform::win32::init_dpi();
     env_logger::init();
 
+    let args = ServerLaunchArgs::parse();
+
+    if args.hash_password {
+        let password = rpassword::prompt_password("Password: ").expect("reading password");
+        println!("{}", hash_password(&password).expect("hashing password"));
+        return;
+    }
+
+    if let Some(format) = args.print_default_config {
+        let config = normal_defaults()
+            .to_string(format)
+            .expect("serializing config");
+        println!("{config}");
+        return;
+    }
+
+    if args.generate_token {
+        let token = generate_token();
+        println!("token: {token}");
+        println!("hash: {}", hash_token(&token));
+        return;
+    }
+
+    let mut config = match &args.config {
+        Some(path) => match ServerConfig::load(path) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        },
+        None => normal_defaults(),
+    };
+    args.apply(&mut config);
+
     let local = LocalSet::new();
 
-    local.spawn_local(async { twilight::server::serve().await.expect("launching server") });
+    local.spawn_local(async move {
+        twilight::server::serve(config)
+            .await
+            .expect("launching server")
+    });
     local.await;
 }
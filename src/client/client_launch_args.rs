@@ -1,12 +1,17 @@
+use std::path::PathBuf;
+
 use clap::Parser;
 
+use crate::network::dto::auth::AuthType;
+use crate::network::dto::video::{DesktopCodec, Resolution};
+
 use super::server_connection::Origin;
 
 const ABOUT: &str = "A CLI interface to Twilight Remote Desktop. The form of \
 arguments may change at any time during the alpha version.";
 
 /// Arguments that are local to one connection and not persisted.
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(version, about = ABOUT, long_about = None)]
 pub struct ClientLaunchArgs {
     /// The URL to connect to. Example: twilight://127.0.0.1:1234/base/path
@@ -14,7 +19,7 @@ pub struct ClientLaunchArgs {
     /// If no scheme is given, it defaults to twilight.
     /// If no port is given, the default value varies by the scheme.
     /// If no base path (no slash at all), it defaults to "/twilight".
-    /// End with a slash to use empty base path.
+    /// Trailing slash is ignored, so end with a slash to use empty base path.
     ///
     /// Available schemes: http, https, twilight, twilightc
     ///
@@ -24,4 +29,62 @@ pub struct ClientLaunchArgs {
     /// Current default value is for ease of debugging
     #[clap(default_value = "twilightc://localhost/twilight")]
     pub url: Origin,
+
+    /// Record the received desktop stream into the given file.
+    ///
+    /// The recording can be played back later using the player binary.
+    #[clap(long)]
+    pub record: Option<PathBuf>,
+
+    /// The monitor to view, given as an id or a name reported by the server.
+    ///
+    /// Defaults to the first monitor.
+    #[clap(long)]
+    pub monitor: Option<String>,
+
+    /// The codec to encode the desktop with, such as lossless for text and CAD work.
+    ///
+    /// Defaults to the codec configured on the server.
+    #[clap(long, value_enum)]
+    pub codec: Option<DesktopCodec>,
+
+    /// Have the server downscale the desktop to fit in the resolution, such as 1920x1080.
+    ///
+    /// Saves bandwidth when the window is smaller than the desktop. Never scaled up.
+    #[clap(long)]
+    pub max_resolution: Option<Resolution>,
+
+    /// Have the server send at most this many frames per second.
+    ///
+    /// Defaults to the frame rate configured on the server, which is never exceeded.
+    #[clap(long)]
+    pub max_fps: Option<u32>,
+
+    /// Accept any server certificate, including self-signed ones.
+    ///
+    /// This makes TLS vulnerable to active attackers. Use only for testing.
+    #[clap(long)]
+    pub insecure: bool,
+
+    /// The file to store pinned server certificates.
+    ///
+    /// Defaults to `twilight/known_hosts` in the config directory of the platform.
+    #[clap(long)]
+    pub known_hosts: Option<PathBuf>,
+
+    /// How to authenticate to the server.
+    ///
+    /// Defaults to the first type the server offers.
+    #[clap(long, value_enum)]
+    pub auth: Option<AuthType>,
+
+    /// Username for password and username auth. Asked if not given.
+    #[clap(long)]
+    pub username: Option<String>,
+
+    /// Pre-shared token for token auth. Asked if not given.
+    ///
+    /// Note that other users on this machine may see the command line.
+    #[clap(long)]
+    pub token: Option<String>,
 }
@@ -0,0 +1,76 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+
+use crate::image::{decode_png, ColorFormat, ImageBuf};
+use crate::schema::video::{self, Coord2f, VideoCodec};
+use crate::util::{CursorShape, LruCache, CURSOR_CACHE_SIZE};
+
+/// Cursor shapes received so far, for the server to refer to by id.
+/// Of the same size as the server side tracking, which relies on it.
+#[derive(Debug)]
+pub struct CursorCache {
+    shapes: LruCache<u64, CursorShape>,
+}
+
+impl CursorCache {
+    pub fn new() -> Self {
+        CursorCache {
+            shapes: LruCache::new(CURSOR_CACHE_SIZE),
+        }
+    }
+
+    /// Decodes the shape, or looks it up if the image is left out.
+    /// Fails if the shape is not known, in which case the server should be asked to resend it.
+    pub fn resolve(&mut self, msg: &video::CursorShape) -> Result<CursorShape> {
+        let id = msg.id();
+
+        let image = match msg.image() {
+            Some(x) => x,
+            None => {
+                return self
+                    .shapes
+                    .get(&id)
+                    .filter(|_| id != 0)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("unknown cursor shape {id:016x}"));
+            }
+        };
+
+        let res = msg
+            .resolution()
+            .context("cursor shape without resolution")?;
+        let image = match msg.codec() {
+            VideoCodec::Png => decode_png(image.bytes())?,
+            // raw pixels, labelled as Jpeg by older servers
+            VideoCodec::Bgra8888 | VideoCodec::Jpeg => ImageBuf::new(
+                res.width(),
+                res.height(),
+                res.width() * 4,
+                ColorFormat::Bgra8888,
+                image.bytes().to_vec().into(),
+            ),
+            other => bail!("unsupported cursor codec {other:?}"),
+        };
+        ensure!(
+            (image.width, image.height) == (res.width(), res.height())
+                && image.data.len() >= image.color_format.buffer_len(image.stride, image.height),
+            "cursor shape is not of the advertised resolution"
+        );
+
+        let hotspot = msg
+            .hotspot()
+            .cloned()
+            .unwrap_or_else(|| Coord2f::new(0.0, 0.0));
+        let shape = CursorShape {
+            image,
+            xor: msg.xor(),
+            hotspot_x: hotspot.x(),
+            hotspot_y: hotspot.y(),
+        };
+
+        if id != 0 {
+            self.shapes.insert(id, shape.clone());
+        }
+
+        Ok(shape)
+    }
+}
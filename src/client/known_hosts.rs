@@ -0,0 +1,91 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::network::server_auth::{format_fingerprint, parse_fingerprint, Fingerprint};
+
+/// Certificate fingerprints pinned by the user, one `<host:port> <fingerprint>` per line.
+#[derive(Debug)]
+pub struct KnownHosts {
+    path: Option<PathBuf>,
+    entries: Vec<(String, Fingerprint)>,
+}
+
+impl KnownHosts {
+    /// Default location of the known hosts file, if the platform has a config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|x| x.join("twilight").join("known_hosts"))
+    }
+
+    /// A missing file is treated as empty.
+    /// Without a path, pinned fingerprints only last until the process exits.
+    pub fn load(path: Option<PathBuf>) -> Result<Self> {
+        let content = match &path {
+            Some(path) => match fs::read_to_string(path) {
+                Ok(x) => x,
+                Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+                Err(e) => {
+                    return Err(e).with_context(|| format!("unable to read {}", path.display()))
+                }
+            },
+            None => String::new(),
+        };
+
+        let mut entries = Vec::new();
+
+        for (idx, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let entry = line
+                .split_once(' ')
+                .and_then(|(host, fp)| Some((host.to_string(), parse_fingerprint(fp.trim())?)));
+
+            match entry {
+                Some(x) => entries.push(x),
+                None => log::warn!("Ignoring invalid known hosts entry at line {}", idx + 1),
+            }
+        }
+
+        Ok(Self { path, entries })
+    }
+
+    pub fn get(&self, authority: &str) -> Option<&Fingerprint> {
+        self.entries
+            .iter()
+            .find(|(host, _)| host == authority)
+            .map(|(_, fp)| fp)
+    }
+
+    /// Pin the fingerprint and save the file.
+    pub fn insert(&mut self, authority: &str, fingerprint: Fingerprint) -> Result<()> {
+        self.entries.retain(|(host, _)| host != authority);
+        self.entries.push((authority.to_string(), fingerprint));
+
+        match &self.path {
+            Some(path) => self.save(path),
+            None => Ok(()),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let mut content = String::new();
+        for (host, fp) in &self.entries {
+            content.push_str(host);
+            content.push(' ');
+            content.push_str(&format_fingerprint(fp));
+            content.push('\n');
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("unable to create {}", parent.display()))?;
+        }
+
+        fs::write(path, content).with_context(|| format!("unable to write {}", path.display()))
+    }
+}
@@ -1,7 +1,11 @@
 mod client_launch_args;
+mod cursor_cache;
+mod known_hosts;
 pub mod native_server_connection;
 mod server_connection;
+mod session_recording;
+mod tls;
 mod twilight_client;
 
 pub use client_launch_args::ClientLaunchArgs;
-pub use twilight_client::{TwilightClient, TwilightClientEvent};
+pub use twilight_client::{EventCb, TwilightClient, TwilightClientEvent};
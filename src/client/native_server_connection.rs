@@ -9,8 +9,10 @@ use http_body_util::Empty;
 use hyper::{header, Method, Request, StatusCode};
 use parking_lot::RwLock;
 use rustc_hash::FxHashMap;
+use rustls::pki_types::ServerName;
 use tokio::net::TcpStream;
 use tokio::sync::mpsc::error::TrySendError;
+use tokio_rustls::TlsConnector;
 
 use crate::client::server_connection::{FetchResponse, MessageRead, Origin, ServerConnection};
 
@@ -21,29 +23,38 @@ pub struct NativeServerConnection {
     origin: Origin,
     auth: Option<String>,
     client: reqwest::Client,
+    tls: Arc<rustls::ClientConfig>,
     stream_read: Arc<RwLock<FxHashMap<u16, tokio::sync::mpsc::Sender<Bytes>>>>,
     stream_write: Option<Arc<tokio::sync::mpsc::Sender<Frame<'static>>>>,
 }
 
 impl NativeServerConnection {
-    pub async fn new(origin: Origin) -> Result<Self> {
-        let client = reqwest::Client::builder();
+    /// tls: Used unless the origin is cleartext
+    pub async fn new(origin: Origin, tls: Arc<rustls::ClientConfig>) -> Result<Self> {
+        let client = reqwest::Client::builder().use_preconfigured_tls((*tls).clone());
 
         Ok(NativeServerConnection {
             origin,
             auth: Default::default(),
             client: client.build()?,
+            tls,
             stream_read: Arc::new(RwLock::new(Default::default())),
             stream_write: None,
         })
     }
 
     fn get_url(&self, path: &str) -> String {
+        let scheme = if self.origin.cleartext {
+            "http"
+        } else {
+            "https"
+        };
+
         if self.origin.path.is_empty() {
-            format!("http://{}:{}{path}", self.origin.host, self.origin.port)
+            format!("{scheme}://{}:{}{path}", self.origin.host, self.origin.port)
         } else {
             format!(
-                "http://{}:{}{}{path}",
+                "{scheme}://{}:{}{}{path}",
                 self.origin.host, self.origin.port, self.origin.path
             )
         }
@@ -127,10 +138,17 @@ impl ServerConnection for NativeServerConnection {
 
 impl NativeServerConnection {
     async fn open_conn(&mut self) -> Result<()> {
-        let stream = TcpStream::connect((self.origin.host.as_str(), self.origin.port)).await?;
+        // IPv6 address is enclosed in brackets in URL
+        let host = self
+            .origin
+            .host
+            .trim_start_matches('[')
+            .trim_end_matches(']');
+
+        let stream = TcpStream::connect((host, self.origin.port)).await?;
 
         let mut url = self.get_url("/stream/v1?auth=");
-        url.push_str(self.auth.as_ref().map(|x| x.as_str()).unwrap_or(""));
+        url.push_str(self.auth.as_deref().unwrap_or(""));
 
         let key = handshake::generate_key();
 
@@ -144,7 +162,16 @@ impl NativeServerConnection {
             .header("Sec-WebSocket-Version", "13")
             .body(Empty::<Bytes>::new())?;
 
-        let (mut ws, _) = handshake::client(&SpawnExecutor, req, stream).await?;
+        let (mut ws, _) = if self.origin.cleartext {
+            handshake::client(&SpawnExecutor, req, stream).await?
+        } else {
+            let server_name = ServerName::try_from(host.to_string())?;
+            let stream = TlsConnector::from(Arc::clone(&self.tls))
+                .connect(server_name, stream)
+                .await?;
+
+            handshake::client(&SpawnExecutor, req, stream).await?
+        };
 
         // Copied from https://github.com/denoland/fastwebsockets/issues/76
         ws.set_auto_pong(false);
@@ -41,26 +41,31 @@ impl FromStr for Origin {
         };
 
         // Perhaps we should remove default path thing
-        let path = if s.ends_with("/") {
-            "/"
+        let path = if s.ends_with('/') {
+            url.path().trim_end_matches('/')
         } else {
-            let path = url.path();
-            if path == "/" {
-                "/twilight"
-            } else {
-                path
+            match url.path() {
+                "" | "/" => "/twilight",
+                path => path,
             }
         };
 
         Ok(Self {
             cleartext,
             host: url.host_str().context("URL must contain a host")?.into(),
-            port: url.port().unwrap_or(default_port).into(),
+            port: url.port().unwrap_or(default_port),
             path: path.into(),
         })
     }
 }
 
+impl Origin {
+    /// The `host:port` part, which identifies the server certificate.
+    pub fn authority(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
 #[allow(unused)]
 pub trait ServerConnection: Send + Debug {
     type FetchResponseImpl: FetchResponse;
@@ -122,3 +127,44 @@ pub trait MessageWrite: Send + Sync + Debug + 'static {
     /// Send message via websocket
     async fn write(&mut self, data: Bytes) -> Result<()>;
 }
+
+/// Reads from two streams of the same connection, whichever has a message first.
+#[derive(Debug)]
+pub struct MergedMessageRead<A, B> {
+    first: A,
+    second: B,
+    ch: u16,
+}
+
+impl<A: MessageRead, B: MessageRead> MergedMessageRead<A, B> {
+    /// If both have messages, ones from `first` are read first.
+    pub fn new(first: A, second: B) -> Self {
+        let ch = first.channel();
+        Self { first, second, ch }
+    }
+}
+
+impl<A: MessageRead, B: MessageRead> MessageRead for MergedMessageRead<A, B> {
+    fn is_open(&self) -> bool {
+        self.first.is_open() && self.second.is_open()
+    }
+
+    /// Channel of the message read last
+    fn channel(&self) -> u16 {
+        self.ch
+    }
+
+    async fn read(&mut self) -> Result<Option<Bytes>> {
+        let first_ch = self.first.channel();
+        let second_ch = self.second.channel();
+
+        let (ch, msg) = tokio::select! {
+            biased;
+            x = self.first.read() => (first_ch, x),
+            x = self.second.read() => (second_ch, x),
+        };
+
+        self.ch = ch;
+        msg
+    }
+}
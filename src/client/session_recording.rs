@@ -0,0 +1,195 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{ensure, Context, Result};
+use hyper::body::Bytes;
+use tokio::io::{AsyncReadExt, BufReader};
+
+use crate::client::server_connection::MessageRead;
+use crate::network::dto::video::MonitorInfo;
+
+/*
+A session recording is laid out as follows. Every number is little endian.
+
+header:
+    [8 bytes] MAGIC
+    [u32] length of the JSON, followed by MonitorInfo serialized as JSON
+
+followed by any number of records:
+    [u64] microseconds since the recording started
+    [u16] channel
+    [u32] message length, followed by the message as received from the server
+
+Messages of the control channel are recorded as well, so the decoder can be set up on replay.
+ */
+
+const MAGIC: &[u8; 8] = b"TWLSESS1";
+
+/// Largest JSON header, far more than any MonitorInfo takes
+const MAX_HEADER_LEN: u32 = 64 << 10;
+
+/// Largest message, as accepted by the websocket of the client
+const MAX_MESSAGE_LEN: u32 = 64 << 20;
+
+/// Writes every message received through a channel into a file.
+pub struct SessionRecorder {
+    file: BufWriter<File>,
+    start: Instant,
+}
+
+impl SessionRecorder {
+    pub fn create(path: &Path, monitor: &MonitorInfo) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("unable to create recording file {path:?}"))?;
+        let mut file = BufWriter::new(file);
+
+        let header = serde_json::to_vec(monitor)?;
+        let header_len: u32 = header.len().try_into().context("header too large")?;
+
+        file.write_all(MAGIC)?;
+        file.write_all(&header_len.to_le_bytes())?;
+        file.write_all(&header)?;
+
+        Ok(Self {
+            file,
+            start: Instant::now(),
+        })
+    }
+
+    pub fn write(&mut self, ch: u16, msg: &[u8]) -> Result<()> {
+        let timestamp: u64 = self
+            .start
+            .elapsed()
+            .as_micros()
+            .try_into()
+            .context("recording too long")?;
+        let len: u32 = msg.len().try_into().context("message too large")?;
+
+        self.file.write_all(&timestamp.to_le_bytes())?;
+        self.file.write_all(&ch.to_le_bytes())?;
+        self.file.write_all(&len.to_le_bytes())?;
+        self.file.write_all(msg)?;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+impl Debug for SessionRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionRecorder")
+            .field("start", &self.start)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Plays back a session recording at the recorded pace.
+pub struct RecordedMessageRead {
+    file: BufReader<tokio::fs::File>,
+    monitor: MonitorInfo,
+    ch: u16,
+    is_open: bool,
+    start: tokio::time::Instant,
+}
+
+impl RecordedMessageRead {
+    pub async fn open(path: &Path) -> Result<Self> {
+        let file = tokio::fs::File::open(path)
+            .await
+            .with_context(|| format!("unable to open recording file {path:?}"))?;
+        let mut file = BufReader::new(file);
+
+        let mut magic = [0u8; 8];
+        file.read_exact(&mut magic)
+            .await
+            .context("unable to read recording header")?;
+        ensure!(&magic == MAGIC, "not a session recording");
+
+        let header_len = file.read_u32_le().await?;
+        ensure!(
+            header_len <= MAX_HEADER_LEN,
+            "recording header of {header_len} bytes is too large"
+        );
+        let mut header = vec![0u8; header_len as usize];
+        file.read_exact(&mut header).await?;
+        let monitor: MonitorInfo = serde_json::from_slice(&header)?;
+
+        Ok(Self {
+            file,
+            monitor,
+            ch: 0,
+            is_open: true,
+            start: tokio::time::Instant::now(),
+        })
+    }
+
+    /// Monitor the session was recorded from
+    pub fn monitor(&self) -> &MonitorInfo {
+        &self.monitor
+    }
+
+    async fn read_record(&mut self) -> Result<Option<(Duration, u16, Bytes)>> {
+        let timestamp = match self.file.read_u64_le().await {
+            Ok(x) => x,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let ch = self.file.read_u16_le().await?;
+
+        let len = self.file.read_u32_le().await?;
+        ensure!(
+            len <= MAX_MESSAGE_LEN,
+            "recorded message of {len} bytes is too large"
+        );
+
+        let mut msg = vec![0u8; len as usize];
+        self.file
+            .read_exact(&mut msg)
+            .await
+            .context("recording ended in the middle of a message")?;
+
+        Ok(Some((Duration::from_micros(timestamp), ch, msg.into())))
+    }
+}
+
+impl MessageRead for RecordedMessageRead {
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    fn channel(&self) -> u16 {
+        self.ch
+    }
+
+    async fn read(&mut self) -> Result<Option<Bytes>> {
+        let (timestamp, ch, msg) = match self.read_record().await? {
+            Some(x) => x,
+            None => {
+                self.is_open = false;
+                return Ok(None);
+            }
+        };
+
+        self.ch = ch;
+        tokio::time::sleep_until(self.start + timestamp).await;
+
+        Ok(Some(msg))
+    }
+}
+
+impl Debug for RecordedMessageRead {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordedMessageRead")
+            .field("monitor", &self.monitor)
+            .field("ch", &self.ch)
+            .finish_non_exhaustive()
+    }
+}
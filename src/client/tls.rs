@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, RootCertStore, SignatureScheme};
+
+use crate::network::server_auth::{fingerprint, format_fingerprint, Fingerprint};
+
+/// Build rustls config used for both fetches and the stream.
+pub fn client_tls_config(
+    verifier: Arc<dyn ServerCertVerifier>,
+) -> Result<Arc<rustls::ClientConfig>> {
+    let config = rustls::ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(verifier)
+        .with_no_client_auth();
+
+    Ok(Arc::new(config))
+}
+
+/// Accept any certificate. Only useful for testing against self-signed certificates.
+pub fn accept_any_cert() -> Arc<dyn ServerCertVerifier> {
+    Arc::new(AcceptAnyCert(provider()))
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// Trust on first use.
+///
+/// A pinned certificate is accepted as is, and any other certificate is refused.
+/// Without a pin, the first certificate seen is accepted for this connection,
+/// but it stays untrusted unless a public CA signed it or `mark_trusted` is called.
+#[derive(Debug)]
+pub struct CertPinning {
+    provider: Arc<CryptoProvider>,
+    webpki: Arc<WebPkiServerVerifier>,
+    state: Mutex<PinningState>,
+}
+
+#[derive(Debug)]
+struct PinningState {
+    expected: Option<Fingerprint>,
+    trusted: bool,
+}
+
+impl CertPinning {
+    pub fn new(pinned: Option<Fingerprint>) -> Result<Arc<Self>> {
+        let provider = provider();
+
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.into(),
+        };
+        let webpki =
+            WebPkiServerVerifier::builder_with_provider(Arc::new(roots), Arc::clone(&provider))
+                .build()?;
+
+        Ok(Arc::new(Self {
+            provider,
+            webpki,
+            state: Mutex::new(PinningState {
+                trusted: pinned.is_some(),
+                expected: pinned,
+            }),
+        }))
+    }
+
+    /// Fingerprint of the server certificate, if it is not trusted yet.
+    pub fn untrusted_fingerprint(&self) -> Option<Fingerprint> {
+        let state = self.state.lock();
+        if state.trusted {
+            None
+        } else {
+            state.expected
+        }
+    }
+
+    /// Trust the certificate seen so far.
+    pub fn mark_trusted(&self) {
+        self.state.lock().trusted = true;
+    }
+}
+
+impl ServerCertVerifier for CertPinning {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let seen = fingerprint(end_entity);
+
+        let mut state = self.state.lock();
+        match state.expected {
+            Some(expected) if expected == seen => {}
+            Some(expected) => {
+                return Err(rustls::Error::General(format!(
+                    "Server certificate has changed from {} to {}! \
+                    If this is expected, remove the server from known hosts file.",
+                    format_fingerprint(&expected),
+                    format_fingerprint(&seen)
+                )));
+            }
+            None => {
+                state.expected = Some(seen);
+                state.trusted = self
+                    .webpki
+                    .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+                    .is_ok();
+            }
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+/// Accepts any certificate, but still checks the handshake is signed by it.
+#[derive(Debug)]
+struct AcceptAnyCert(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
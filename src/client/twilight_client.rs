@@ -1,21 +1,49 @@
+use crate::client::cursor_cache::CursorCache;
+use crate::client::known_hosts::KnownHosts;
 use crate::client::native_server_connection::NativeServerConnection;
-use crate::client::server_connection::{FetchResponse, MessageRead, ServerConnection};
+use crate::client::server_connection::{
+    FetchResponse, MergedMessageRead, MessageRead, MessageWrite, ServerConnection,
+};
+use crate::client::session_recording::{RecordedMessageRead, SessionRecorder};
+use crate::client::tls::{accept_any_cert, client_tls_config, CertPinning};
 use crate::client::ClientLaunchArgs;
-use crate::image::{ColorFormat, ImageBuf};
-use crate::network::dto::auth::AuthSuccessResponse;
+use crate::image::{ImageBuf, Rect};
+use crate::input::InputEvent;
+use crate::network::dto::auth::{
+    AuthServerCommitResponse, AuthServerPinRequest, AuthServerRevealResponse, AuthSuccessResponse,
+    AuthType, AuthTypesResponse, PasswordAuthRequest, TokenAuthRequest,
+};
 use crate::network::dto::channel::OpenChannelResponse;
-use crate::network::dto::video::{DesktopInfo, MonitorInfo, StartCapture};
-use crate::schema::video::{Coord2f, Coord2u, VideoCodec, VideoFrame};
+use crate::network::dto::input::StartInput;
+use crate::network::dto::video::{DesktopInfo, MonitorInfo, Resolution, StartCapture};
+use crate::network::server_auth::{self, commitment, format_fingerprint, Fingerprint, Nonce};
+use crate::network::CONTROL_CHANNEL;
+use crate::schema::control::{ControlFrame, ControlFrameArgs, ControlPacket};
+use crate::schema::video::{
+    AckVideoFrame, AckVideoFrameArgs, ChromaSubsampling, Coord2u, RequestKeyframe,
+    RequestKeyframeArgs, VideoCodec, VideoFrame,
+};
 use crate::schema::{parse_msg, parse_msg_payload};
-use crate::util::{CursorShape, CursorState, DesktopUpdate, Micros};
-use crate::util::{ThreadManager, Timings};
+use crate::util::{CursorState, DesktopUpdate, Micros};
+use crate::util::{FramePool, ThreadManager, Timings};
+#[cfg(feature = "av1")]
+use crate::video::decoder::av1::Av1Decoder;
+use crate::video::decoder::h264::H264Decoder;
+use crate::video::decoder::hybrid::HybridDecoder;
 use crate::video::decoder::jpeg::JpegDecoder;
+use crate::video::decoder::lossless::LosslessDecoder;
 use crate::video::decoder::DecoderStage;
-use anyhow::{anyhow, Result};
+use crate::video::encoder::EncodedTile;
+use anyhow::{anyhow, bail, ensure, Result};
+use flatbuffers::FlatBufferBuilder;
 use hyper::body::Bytes;
-use hyper::Method;
+use hyper::{Method, StatusCode};
+use rustls::client::danger::ServerCertVerifier;
+use std::io::Write;
+use std::path::PathBuf;
 use std::rc::Rc;
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, watch};
 use tokio::task::JoinHandle;
 
@@ -26,7 +54,7 @@ pub enum TwilightClientEvent {
     Closed(Result<()>),
 }
 
-type EventCb = Rc<dyn Fn(TwilightClientEvent)>;
+pub type EventCb = Rc<dyn Fn(TwilightClientEvent)>;
 
 /// Represents connection to a single server.
 pub struct TwilightClient {
@@ -35,17 +63,50 @@ pub struct TwilightClient {
 }
 
 impl TwilightClient {
-    pub fn new(callback: EventCb, args: ClientLaunchArgs) -> Self {
+    /// Events received from `input` are forwarded to the server.
+    pub fn new(
+        callback: EventCb,
+        args: ClientLaunchArgs,
+        input: mpsc::UnboundedReceiver<InputEvent>,
+    ) -> Self {
         let (tx, rx) = watch::channel(false);
 
-        if !args.url.cleartext {
-            panic!("Only cleartext transport is supported for now");
+        let worker = tokio::task::spawn_local(async move {
+            let result = match connect(&args).await {
+                Ok((c, pinning)) => worker(c, pinning, rx, Rc::clone(&callback), input, args).await,
+                Err(e) => Err(e),
+            };
+            callback(TwilightClientEvent::Closed(result));
+        });
+
+        TwilightClient {
+            shutdown: tx,
+            _worker: worker,
         }
-        let origin = args.url.clone();
+    }
+
+    /// Play back a session recorded with `ClientLaunchArgs::record`.
+    pub fn replay(callback: EventCb, path: PathBuf) -> Self {
+        let (tx, rx) = watch::channel(false);
 
         let worker = tokio::task::spawn_local(async move {
-            let result = match NativeServerConnection::new(origin).await {
-                Ok(c) => worker(c, rx, Rc::clone(&callback)).await,
+            let result = match RecordedMessageRead::open(&path).await {
+                Ok(stream) => {
+                    let monitor = stream.monitor().clone();
+                    // recording does not respond to control requests
+                    let (control_tx, _) = mpsc::unbounded_channel();
+                    let (scale_tx, _) = watch::channel(1.0);
+                    receive_desktop(
+                        stream,
+                        monitor,
+                        rx,
+                        Rc::clone(&callback),
+                        None,
+                        control_tx,
+                        scale_tx,
+                    )
+                    .await
+                }
                 Err(e) => Err(e),
             };
             callback(TwilightClientEvent::Closed(result));
@@ -62,63 +123,442 @@ impl TwilightClient {
     }
 }
 
+/// Minimum interval between keyframe requests, so a burst of broken frames asks only once.
+const KEYFRAME_REQUEST_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Input of encoded frames and output of decoded images
+type DecoderPipeline = (
+    mpsc::Sender<DesktopUpdate<VideoPayload>>,
+    mpsc::Receiver<DesktopUpdate<ImageBuf>>,
+);
+
+/// Frames which fail to decode are dropped, and a keyframe is requested through `control_tx`.
 fn decoder_pipeline(
+    stream: u16,
     w: u32,
     h: u32,
     codec: VideoCodec,
+    control_tx: mpsc::UnboundedSender<ControlRequest>,
     thread_manager: &mut ThreadManager,
-) -> (
-    mpsc::Sender<DesktopUpdate<Bytes>>,
-    mpsc::Receiver<DesktopUpdate<ImageBuf>>,
-) {
-    assert_eq!(codec, VideoCodec::Jpeg);
-
-    let (data_tx, mut data_rx) = mpsc::channel::<DesktopUpdate<Bytes>>(1);
+) -> Result<DecoderPipeline> {
+    let mut decoder: Box<dyn DecoderStage> = match codec {
+        VideoCodec::Jpeg => Box::new(JpegDecoder::new(w, h)?),
+        VideoCodec::H264 => Box::new(H264Decoder::new(w, h)?),
+        #[cfg(feature = "av1")]
+        VideoCodec::Av1 => Box::new(Av1Decoder::new(w, h)?),
+        VideoCodec::ZstdDelta => Box::new(LosslessDecoder::new(w, h)?),
+        VideoCodec::Hybrid => Box::new(HybridDecoder::new(w, h)?),
+        _ => bail!("unsupported video codec {codec:?}"),
+    };
+
+    let (data_tx, mut data_rx) = mpsc::channel::<DesktopUpdate<VideoPayload>>(1);
     let (img_tx, img_rx) = mpsc::channel(1);
 
     thread_manager
         .spawn_named("decoder_pipeline", move || {
-            let mut decoder = JpegDecoder::new(w, h)?;
+            let mut framebuffer: Option<ImageBuf> = None;
+            let mut last_keyframe_request: Option<Instant> = None;
+            // for the frames handed to the viewer, returning once displayed
+            let pool = FramePool::default();
+
             while let Some(mut update) = data_rx.blocking_recv() {
                 update.timings.decode_begin = update.timings.elapsed_since_recv().unwrap();
 
-                let mut update = update.and_then_desktop(|x| decoder.decode(&x))?;
+                let (mut update, payload) = update.split();
+                let img = match decode_payload(decoder.as_mut(), &mut framebuffer, payload, &pool) {
+                    Ok(Some(x)) => x,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        log::warn!("Dropping frame which failed to decode: {e:?}");
+                        framebuffer = None;
+
+                        if last_keyframe_request
+                            .is_none_or(|x| x.elapsed() >= KEYFRAME_REQUEST_INTERVAL)
+                        {
+                            last_keyframe_request = Some(Instant::now());
+                            // fails only if nobody can deliver the request
+                            let _ = control_tx.send(ControlRequest::Keyframe { stream });
+                        }
+                        continue;
+                    }
+                };
                 update.timings.decode_end = update.timings.elapsed_since_recv().unwrap();
+                let update = update.with_desktop(img);
 
                 img_tx
                     .blocking_send(update)
                     .map_err(|_| anyhow!("img_rx closed"))?;
             }
+
+            log::debug!("Frame pool of decoder: {:?}", pool.stats());
             Ok(())
         })
         .unwrap();
 
-    (data_tx, img_rx)
+    Ok((data_tx, img_rx))
+}
+
+/// Encoded frame as received on the video channel
+struct VideoPayload {
+    /// None if `data` is the whole frame
+    tiles: Option<Vec<EncodedTile>>,
+    data: Bytes,
+}
+
+/// Decode a frame or patch the framebuffer with its tiles.
+/// Returns None if the payload has no new picture to display.
+fn decode_payload(
+    decoder: &mut dyn DecoderStage,
+    framebuffer: &mut Option<ImageBuf>,
+    payload: VideoPayload,
+    pool: &FramePool,
+) -> Result<Option<ImageBuf>> {
+    let (w, h) = decoder.resolution();
+
+    let tiles = match payload.tiles {
+        Some(x) => x,
+        None => {
+            let Some(img) = decoder.decode_frame(&payload.data)? else {
+                return Ok(None);
+            };
+
+            // sent before the stream was re-announced
+            if (img.width, img.height) != (w, h) {
+                log::debug!(
+                    "Dropping {}x{} frame of the previous stream",
+                    img.width,
+                    img.height
+                );
+                return Ok(None);
+            }
+
+            *framebuffer = Some(img.copied());
+            return Ok(Some(img));
+        }
+    };
+
+    let mut data = Vec::with_capacity(tiles.len());
+    let mut offset = 0;
+    for tile in &tiles {
+        let end = offset + tile.len as usize;
+        ensure!(end <= payload.data.len(), "tile exceeds the frame payload");
+        ensure!(
+            Rect::new(0, 0, w, h).contains(&tile.rect),
+            "tile {:?} out of the frame",
+            tile.rect
+        );
+
+        data.push(&payload.data[offset..end]);
+        offset = end;
+    }
+
+    // tiles do not overlap, so they cover the whole frame if their areas add up to it
+    let covered: u64 = tiles
+        .iter()
+        .map(|x| x.rect.width as u64 * x.rect.height as u64)
+        .sum();
+    if framebuffer.is_none() && covered < w as u64 * h as u64 {
+        log::debug!("Dropping tiles without a preceding full frame");
+        return Ok(None);
+    }
+
+    let images = decoder.decode_tiles(&data)?;
+
+    if framebuffer.is_none() {
+        let color_format = match images.first() {
+            Some(x) => x.color_format,
+            None => return Ok(None),
+        };
+        *framebuffer = Some(ImageBuf::alloc(w, h, None, color_format));
+    }
+    let framebuffer = framebuffer.as_mut().expect("allocated above");
+
+    for (tile, img) in tiles.iter().zip(images) {
+        ensure!(
+            (img.width, img.height) == (tile.rect.width, tile.rect.height),
+            "tile size does not match its placement"
+        );
+
+        img.copy_to(framebuffer, tile.rect.x, tile.rect.y);
+    }
+
+    Ok(Some(framebuffer.copied_in(pool)))
+}
+
+/// Decoder pipeline of a video stream, built upon `NotifyVideoStart`.
+struct VideoDecoder {
+    stream: u16,
+    data_tx: mpsc::Sender<DesktopUpdate<VideoPayload>>,
+    forward: JoinHandle<()>,
+    thread_manager: ThreadManager,
+}
+
+impl VideoDecoder {
+    fn start(
+        stream: u16,
+        resolution: &Resolution,
+        codec: VideoCodec,
+        callback: &EventCb,
+        control_tx: &mpsc::UnboundedSender<ControlRequest>,
+    ) -> Result<Self> {
+        let mut thread_manager = ThreadManager::new();
+        let (data_tx, mut img_rx) = decoder_pipeline(
+            stream,
+            resolution.width,
+            resolution.height,
+            codec,
+            control_tx.clone(),
+            &mut thread_manager,
+        )?;
+
+        let callback = Rc::clone(callback);
+        let forward = tokio::task::spawn_local(async move {
+            while let Some(img) = img_rx.recv().await {
+                callback(TwilightClientEvent::NextFrame(img));
+            }
+        });
+
+        Ok(Self {
+            stream,
+            data_tx,
+            forward,
+            thread_manager,
+        })
+    }
+
+    /// Decode remaining frames and wait for the decoder thread to finish.
+    async fn stop(self) -> Result<()> {
+        drop(self.data_tx);
+        self.forward.await?;
+        self.thread_manager.join_all();
+
+        Ok(())
+    }
+}
+
+/// Pick the monitor matching `wanted` by id or name, or the first one if nothing is wanted.
+fn select_monitor<'a>(
+    monitors: &'a [MonitorInfo],
+    wanted: Option<&str>,
+) -> Result<&'a MonitorInfo> {
+    let monitor = match wanted {
+        Some(wanted) => monitors
+            .iter()
+            .find(|m| m.id == wanted || m.name == wanted)
+            .ok_or_else(|| anyhow!("Monitor {wanted} not found in server!"))?,
+        None => monitors
+            .first()
+            .ok_or_else(|| anyhow!("No monitor available in server!"))?,
+    };
+
+    Ok(monitor)
+}
+
+/// Returns the pinning state too, if the server certificate needs to be checked.
+async fn connect(
+    args: &ClientLaunchArgs,
+) -> Result<(NativeServerConnection, Option<Arc<CertPinning>>)> {
+    let (verifier, pinning) = if args.url.cleartext {
+        (accept_any_cert(), None)
+    } else if args.insecure {
+        log::warn!("Server certificate will not be verified");
+        (accept_any_cert(), None)
+    } else {
+        let known_hosts = KnownHosts::load(known_hosts_path(args))?;
+        let pinning = CertPinning::new(known_hosts.get(&args.url.authority()).copied())?;
+        let verifier: Arc<dyn ServerCertVerifier> = pinning.clone();
+        (verifier, Some(pinning))
+    };
+
+    let tls = client_tls_config(verifier)?;
+    let conn = NativeServerConnection::new(args.url.clone(), tls).await?;
+
+    Ok((conn, pinning))
+}
+
+fn known_hosts_path(args: &ClientLaunchArgs) -> Option<PathBuf> {
+    args.known_hosts.clone().or_else(KnownHosts::default_path)
+}
+
+/// Authenticate the server by a PIN unless its certificate is already trusted.
+async fn authenticate_server(
+    conn: &mut impl ServerConnection,
+    pinning: &CertPinning,
+    args: &ClientLaunchArgs,
+) -> Result<()> {
+    const PATH: &str = "/auth-server?type=pin";
+
+    // Certificate is only known after the first request
+    let req = serde_json::to_vec(&AuthServerPinRequest::Commit)?;
+    let res = conn.fetch(Method::POST, PATH, req.into()).await?;
+
+    let fingerprint = match pinning.untrusted_fingerprint() {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+
+    if res.status() == StatusCode::TOO_MANY_REQUESTS {
+        bail!("Another client is authenticating the server. Try again in a minute");
+    }
+    if !res.status().is_success() {
+        bail!("Server auth status is not ok ({})", res.status().as_u16());
+    }
+
+    let committed: AuthServerCommitResponse = serde_json::from_slice(&res.body().await?)?;
+
+    let client_nonce: Nonce = rand::random();
+    let req = serde_json::to_vec(&AuthServerPinRequest::Reveal {
+        id: committed.id,
+        client_nonce: hex::encode(client_nonce),
+    })?;
+    let res = conn.fetch(Method::POST, PATH, req.into()).await?;
+
+    if !res.status().is_success() {
+        bail!("Server auth status is not ok ({})", res.status().as_u16());
+    }
+
+    let revealed: AuthServerRevealResponse = serde_json::from_slice(&res.body().await?)?;
+
+    let mut server_nonce = Nonce::default();
+    hex::decode_to_slice(&revealed.server_nonce, &mut server_nonce)?;
+
+    let mut expected_commitment = [0; 32];
+    hex::decode_to_slice(&committed.commitment, &mut expected_commitment)?;
+
+    if commitment(&fingerprint, &server_nonce) != expected_commitment {
+        bail!("Server nonce does not match its commitment. The connection may be intercepted!");
+    }
+
+    let pin = server_auth::pin(&fingerprint, &client_nonce, &server_nonce);
+    let entered = prompt_pin(&fingerprint).await?;
+
+    if entered.trim() != pin {
+        bail!("PIN does not match. The server could not be authenticated");
+    }
+
+    pinning.mark_trusted();
+
+    let mut known_hosts = KnownHosts::load(known_hosts_path(args))?;
+    known_hosts.insert(&args.url.authority(), fingerprint)?;
+    log::info!(
+        "Pinned certificate {} for {}",
+        format_fingerprint(&fingerprint),
+        args.url.authority()
+    );
+
+    Ok(())
+}
+
+async fn prompt_pin(fingerprint: &Fingerprint) -> Result<String> {
+    println!(
+        "Server certificate {} is not trusted yet.",
+        format_fingerprint(fingerprint)
+    );
+
+    prompt_line("Press Enter on the server console, then enter the PIN it displays: ").await
+}
+
+async fn prompt_line(prompt: &'static str) -> Result<String> {
+    tokio::task::spawn_blocking(move || {
+        print!("{prompt}");
+        std::io::stdout().flush()?;
+
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+
+        Ok(line.trim().to_string())
+    })
+    .await?
+}
+
+/// Authenticate the client and return the token for privileged endpoints.
+async fn authenticate(conn: &mut impl ServerConnection, args: &ClientLaunchArgs) -> Result<String> {
+    let auth_type = match args.auth {
+        Some(x) => x,
+        None => {
+            let res = conn.fetch(Method::GET, "/auth", Bytes::new()).await?;
+            if !res.status().is_success() {
+                bail!("Auth list status is not ok ({})", res.status().as_u16());
+            }
+
+            let res: AuthTypesResponse = serde_json::from_slice(&res.body().await?)?;
+            *res.types
+                .first()
+                .ok_or_else(|| anyhow!("Server does not accept any auth type"))?
+        }
+    };
+
+    let body: Bytes = match auth_type {
+        AuthType::Password => {
+            let username = match &args.username {
+                Some(x) => x.clone(),
+                None => prompt_line("Username: ").await?,
+            };
+            let password =
+                tokio::task::spawn_blocking(|| rpassword::prompt_password("Password: ")).await??;
+
+            serde_json::to_vec(&PasswordAuthRequest { username, password })?.into()
+        }
+        AuthType::Token => {
+            let token = match &args.token {
+                Some(x) => x.clone(),
+                None => {
+                    tokio::task::spawn_blocking(|| rpassword::prompt_password("Token: ")).await??
+                }
+            };
+
+            serde_json::to_vec(&TokenAuthRequest { token })?.into()
+        }
+        AuthType::Username => match &args.username {
+            Some(x) => x.clone().into(),
+            None => prompt_line("Username: ").await?.into(),
+        },
+    };
+
+    let path = format!("/auth/{}", auth_type.as_str());
+    let res = conn.fetch(Method::POST, &path, body).await?;
+
+    if !res.status().is_success() {
+        let status = res.status().as_u16();
+        let body = res.body().await.unwrap_or_default();
+        bail!(
+            "Auth status is not ok ({status}) {}",
+            String::from_utf8_lossy(&body)
+        );
+    }
+
+    let res: AuthSuccessResponse = serde_json::from_slice(&res.body().await?)?;
+
+    Ok(res.token)
 }
 
 async fn worker(
     mut conn: impl ServerConnection,
+    pinning: Option<Arc<CertPinning>>,
     mut shutdown: watch::Receiver<bool>,
     callback: EventCb,
+    input: mpsc::UnboundedReceiver<InputEvent>,
+    args: ClientLaunchArgs,
 ) -> Result<()> {
-    let mut thread_manager = ThreadManager::new();
+    if let Some(pinning) = pinning {
+        let res = authenticate_server(&mut conn, &pinning, &args);
 
-    let res = conn.fetch(Method::POST, "/auth/username", b"testuser"[..].into());
+        tokio::select! {
+            biased;
+            _ = shutdown.changed() => return Ok(()),
+            x = res => x
+        }?;
+    }
 
-    let res = tokio::select! {
+    let res = authenticate(&mut conn, &args);
+
+    let token = tokio::select! {
         biased;
         _ = shutdown.changed() => return Ok(()),
         x = res => x
     }?;
 
-    if !res.status().is_success() {
-        return Err(anyhow!("Auth status is not ok ({})", res.status().as_u16()));
-    }
-
-    let res = res.body().await?;
-    let res: AuthSuccessResponse = serde_json::from_slice(&res)?;
-
-    conn.set_auth(res.token);
+    conn.set_auth(token);
 
     let res = conn.fetch(Method::GET, "/capture/desktop", Bytes::new());
 
@@ -138,20 +578,20 @@ async fn worker(
     let res = res.body().await?;
     let res: DesktopInfo = serde_json::from_slice(&res)?;
 
-    if res.monitor.is_empty() {
-        return Err(anyhow!("No monitor available in server!"));
-    }
-
-    let monitor = &res.monitor[0];
+    let monitor = select_monitor(&res.monitor, args.monitor.as_deref())?;
     log::info!("Connecting to monitor {:?}", monitor);
 
     let ch = open_channel(&mut conn).await?;
     log::info!("Using channel {ch}");
-    let mut stream = conn.stream_read(ch).await?;
+    let control = conn.stream_read(CONTROL_CHANNEL).await?;
+    let stream = MergedMessageRead::new(control, conn.stream_read(ch).await?);
 
     let payload = serde_json::to_string(&StartCapture {
         ch,
         id: monitor.id.clone(),
+        codec: args.codec,
+        target_resolution: args.max_resolution.clone(),
+        max_fps: args.max_fps,
     })?;
 
     let res = conn
@@ -168,22 +608,155 @@ async fn worker(
         ));
     }
 
-    let width = monitor.resolution.width;
-    let height = monitor.resolution.height;
-    let desktop_codec = VideoCodec::Jpeg;
-    callback(TwilightClientEvent::Connected(monitor.clone()));
+    let input_ch = open_channel(&mut conn).await?;
+    log::info!("Using channel {input_ch} for input");
+    let input_stream = conn.stream_write(input_ch).await?;
+
+    let payload = serde_json::to_string(&StartInput {
+        ch: input_ch,
+        id: Some(monitor.id.clone()),
+    })?;
 
-    let (data_tx, mut img_rx) = decoder_pipeline(width, height, desktop_codec, &mut thread_manager);
+    let res = conn
+        .fetch(Method::POST, "/input/desktop", payload.into())
+        .await?;
 
-    let callback_inner = Rc::clone(&callback);
-    let decoder = tokio::task::spawn_local(async move {
-        let callback = callback_inner;
+    if !res.status().is_success() {
+        return Err(anyhow!(
+            "failed to start input (status={})",
+            res.status().as_u16()
+        ));
+    }
+
+    let recorder = match args.record {
+        Some(path) => Some(SessionRecorder::create(&path, monitor)?),
+        None => None,
+    };
+
+    // scale of the video announced by the server, to map pointer positions back
+    let (scale_tx, scale_rx) = watch::channel(1.0);
+    let input_sender = tokio::task::spawn_local(async move {
+        if let Err(e) = send_input(input_stream, input, scale_rx).await {
+            log::error!("Stopped forwarding input due to error: {e:?}");
+        }
+    });
 
-        while let Some(img) = img_rx.recv().await {
-            callback(TwilightClientEvent::NextFrame(img));
+    let control_stream = conn.stream_write(CONTROL_CHANNEL).await?;
+    let (control_tx, control_rx) = mpsc::unbounded_channel();
+    let control_sender = tokio::task::spawn_local(async move {
+        if let Err(e) = send_control(control_stream, control_rx).await {
+            log::error!("Stopped sending control requests due to error: {e:?}");
         }
     });
 
+    let result = receive_desktop(
+        stream,
+        monitor.clone(),
+        shutdown,
+        callback,
+        recorder,
+        control_tx,
+        scale_tx,
+    )
+    .await;
+
+    input_sender.abort();
+    control_sender.abort();
+    result
+}
+
+/// Forward input events to the server until the viewer stops sending them.
+/// Pointer positions in the video are mapped back to the desktop by `scale`.
+async fn send_input(
+    mut stream: impl MessageWrite,
+    mut input: mpsc::UnboundedReceiver<InputEvent>,
+    scale: watch::Receiver<f32>,
+) -> Result<()> {
+    let mut builder = FlatBufferBuilder::with_capacity(256);
+
+    while let Some(mut event) = input.recv().await {
+        if let InputEvent::PointerMove { x, y } = &mut event {
+            let scale = *scale.borrow();
+            *x = (*x as f32 / scale).round() as u32;
+            *y = (*y as f32 / scale).round() as u32;
+        }
+
+        builder.reset();
+        let msg = event.build(&mut builder);
+        builder.finish_size_prefixed(msg, None);
+
+        stream
+            .write(Bytes::copy_from_slice(builder.finished_data()))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Message to the server through the control channel
+#[derive(Debug, Clone, Copy)]
+enum ControlRequest {
+    Keyframe { stream: u16 },
+    Ack { stream: u16, sequence: u32 },
+}
+
+/// Forward requests to the server until every sender is dropped.
+async fn send_control(
+    mut stream: impl MessageWrite,
+    mut requests: mpsc::UnboundedReceiver<ControlRequest>,
+) -> Result<()> {
+    let mut builder = FlatBufferBuilder::with_capacity(64);
+
+    while let Some(request) = requests.recv().await {
+        builder.reset();
+
+        let (data_type, data) = match request {
+            ControlRequest::Keyframe { stream } => {
+                log::debug!("Requesting keyframe on channel {stream}");
+                let data = RequestKeyframe::create(&mut builder, &RequestKeyframeArgs { stream });
+                (ControlPacket::video_RequestKeyframe, data.as_union_value())
+            }
+            ControlRequest::Ack { stream, sequence } => {
+                let data =
+                    AckVideoFrame::create(&mut builder, &AckVideoFrameArgs { stream, sequence });
+                (ControlPacket::video_AckVideoFrame, data.as_union_value())
+            }
+        };
+
+        let msg = ControlFrame::create(
+            &mut builder,
+            &ControlFrameArgs {
+                data_type,
+                data: Some(data),
+            },
+        );
+        builder.finish_size_prefixed(msg, None);
+
+        stream
+            .write(Bytes::copy_from_slice(builder.finished_data()))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Decode and deliver desktop updates until the stream closes.
+///
+/// The stream carries both the control channel and the video channel.
+/// Decoder is built and torn down as the server announces the video stream.
+async fn receive_desktop(
+    mut stream: impl MessageRead,
+    monitor: MonitorInfo,
+    mut shutdown: watch::Receiver<bool>,
+    callback: EventCb,
+    mut recorder: Option<SessionRecorder>,
+    control_tx: mpsc::UnboundedSender<ControlRequest>,
+    scale: watch::Sender<f32>,
+) -> Result<()> {
+    let mut decoder: Option<VideoDecoder> = None;
+    let mut last_quality: Option<(u8, ChromaSubsampling)> = None;
+    let mut cursor_cache = CursorCache::new();
+
     loop {
         let msg = tokio::select! {
             biased;
@@ -197,9 +770,67 @@ async fn worker(
             None => break,
         };
 
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.write(stream.channel(), &msg)?;
+        }
+
+        if stream.channel() == CONTROL_CHANNEL {
+            let frame: ControlFrame = parse_msg(&msg)?;
+            recv_control(
+                &frame,
+                &monitor,
+                &callback,
+                &control_tx,
+                &scale,
+                &mut decoder,
+            )
+            .await?;
+            continue;
+        }
+
+        let data_tx = match &decoder {
+            Some(x) if x.stream == stream.channel() => &x.data_tx,
+            _ => {
+                log::warn!(
+                    "Ignoring video frame of channel {} which is not started",
+                    stream.channel()
+                );
+                continue;
+            }
+        };
+
         let frame: VideoFrame = parse_msg(&msg)?;
         let payload = parse_msg_payload(&msg);
 
+        // fails only if nobody can deliver the request
+        let _ = control_tx.send(ControlRequest::Ack {
+            stream: stream.channel(),
+            sequence: frame.sequence(),
+        });
+
+        if last_quality != Some((frame.quality(), frame.subsampling())) {
+            log::debug!(
+                "Video quality {} {:?}",
+                frame.quality(),
+                frame.subsampling()
+            );
+            last_quality = Some((frame.quality(), frame.subsampling()));
+        }
+
+        let cursor_shape = frame.cursor_update().and_then(|x| x.shape()).and_then(|x| {
+            match cursor_cache.resolve(&x) {
+                Ok(x) => Some(x),
+                Err(e) => {
+                    // the server sends it again along with the keyframe
+                    log::warn!("Cursor shape unavailable, requesting it again: {e:?}");
+                    let _ = control_tx.send(ControlRequest::Keyframe {
+                        stream: stream.channel(),
+                    });
+                    None
+                }
+            }
+        });
+
         let update = DesktopUpdate {
             cursor: frame.cursor_update().map(|x| {
                 let pos = x.pos().cloned().unwrap_or_else(|| Coord2u::new(0, 0));
@@ -207,26 +838,7 @@ async fn worker(
                     visible: x.visible(),
                     pos_x: pos.x(),
                     pos_y: pos.y(),
-                    shape: x.shape().and_then(|s| {
-                        let res = s.resolution()?;
-                        let img = s.image()?;
-                        let hotspot = s
-                            .hotspot()
-                            .cloned()
-                            .unwrap_or_else(|| Coord2f::new(0.0, 0.0));
-                        Some(CursorShape {
-                            image: ImageBuf::new(
-                                res.width(),
-                                res.height(),
-                                res.width() * 4,
-                                ColorFormat::Bgra8888,
-                                img.iter().collect(),
-                            ),
-                            xor: s.xor(),
-                            hotspot_x: hotspot.x(),
-                            hotspot_y: hotspot.y(),
-                        })
-                    }),
+                    shape: cursor_shape,
                 }
             }),
             timings: frame
@@ -239,16 +851,98 @@ async fn worker(
                     ..Default::default()
                 })
                 .unwrap_or_default(),
-            desktop: payload,
+            damage: None,
+            scale: *scale.borrow(),
+            desktop: VideoPayload {
+                tiles: frame.tiles().map(|tiles| {
+                    tiles
+                        .iter()
+                        .map(|x| EncodedTile {
+                            rect: Rect::new(x.x(), x.y(), x.width(), x.height()),
+                            len: x.bytes(),
+                        })
+                        .collect()
+                }),
+                data: payload,
+            },
         };
 
         data_tx.send(update).await?;
     }
 
-    drop(data_tx);
-    decoder.await?;
+    if let Some(decoder) = decoder {
+        decoder.stop().await?;
+    }
+
+    if let Some(recorder) = recorder {
+        recorder.finish()?;
+    }
 
-    thread_manager.join_all();
+    Ok(())
+}
+
+async fn recv_control(
+    frame: &ControlFrame<'_>,
+    monitor: &MonitorInfo,
+    callback: &EventCb,
+    control_tx: &mpsc::UnboundedSender<ControlRequest>,
+    scale: &watch::Sender<f32>,
+    decoder: &mut Option<VideoDecoder>,
+) -> Result<()> {
+    let invalid = || anyhow!("invalid control packet {:?}", frame.data_type());
+
+    match frame.data_type() {
+        ControlPacket::video_NotifyVideoStart => {
+            let x = frame
+                .data_as_video_notify_video_start()
+                .ok_or_else(invalid)?;
+            let size = x.resolution().ok_or_else(invalid)?;
+            let resolution = Resolution {
+                width: size.width(),
+                height: size.height(),
+            };
+
+            log::info!(
+                "Video started on channel {} ({}x{} {:?} scale={})",
+                x.stream(),
+                resolution.width,
+                resolution.height,
+                x.desktop_codec(),
+                x.scale()
+            );
+            scale.send_replace(x.scale());
+
+            if let Some(old) = decoder.take() {
+                old.stop().await?;
+            }
+
+            callback(TwilightClientEvent::Connected(MonitorInfo {
+                resolution: resolution.clone(),
+                ..monitor.clone()
+            }));
+
+            *decoder = Some(VideoDecoder::start(
+                x.stream(),
+                &resolution,
+                x.desktop_codec(),
+                callback,
+                control_tx,
+            )?);
+        }
+        ControlPacket::video_NotifyVideoStop => {
+            let x = frame
+                .data_as_video_notify_video_stop()
+                .ok_or_else(invalid)?;
+            log::info!("Video stopped on channel {}", x.stream());
+
+            if decoder.as_ref().is_some_and(|d| d.stream == x.stream()) {
+                if let Some(old) = decoder.take() {
+                    old.stop().await?;
+                }
+            }
+        }
+        other => log::debug!("Ignoring control packet {other:?}"),
+    }
 
     Ok(())
 }
@@ -1,8 +1,62 @@
 use crate::image::{ColorFormat, Image};
 use crate::util::AsUsize;
 use std::ops::{Deref, DerefMut};
+use wide::i32x8;
 
+/// Matrix to convert between RGB and YUV with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum YuvMatrix {
+    /// Standard definition, assumed by most software codecs
+    #[default]
+    Bt601,
+
+    /// High definition
+    Bt709,
+}
+
+/// Range of YUV values
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum YuvRange {
+    /// Luma from 16 to 235 and chroma from 16 to 240, as in broadcast video
+    #[default]
+    Limited,
+
+    /// Every value from 0 to 255, as in JPEG
+    Full,
+}
+
+/// How RGB is mapped to YUV. Defaults to BT.601 limited range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct YuvParams {
+    pub matrix: YuvMatrix,
+    pub range: YuvRange,
+}
+
+/// Pixels processed at once by the SIMD paths
+const LANES: usize = 8;
+
+/// Pixels of a row converted at once from RGB to 4:2:0, two for every lane of chroma
+const BLOCK: usize = LANES * 2;
+
+/// Fraction bits of the fixed point coefficients
+const SHIFT: i32 = 16;
+
+/// Convert with BT.601 limited range if either is YUV.
+/// See `convert_color_with`.
 pub fn convert_color<A, B>(src: &Image<A>, dst: &mut Image<B>)
+where
+    A: Deref<Target = [u8]>,
+    B: DerefMut<Target = [u8]>,
+{
+    convert_color_with(src, dst, YuvParams::default());
+}
+
+/// Convert between any of the color formats, using `params` if either is YUV.
+///
+/// Chroma of 4:2:0 formats is the average of each 2x2 block when converted from RGB,
+/// and is shared by the block when converted to RGB.
+/// Alpha is kept if both have it, and set opaque if only the destination has it.
+pub fn convert_color_with<A, B>(src: &Image<A>, dst: &mut Image<B>, params: YuvParams)
 where
     A: Deref<Target = [u8]>,
     B: DerefMut<Target = [u8]>,
@@ -13,100 +67,384 @@ where
     src.validate();
     dst.validate();
 
-    use ColorFormat::*;
+    if src.color_format == dst.color_format && src.stride == dst.stride {
+        let len = src.color_format.buffer_len(src.stride, src.height);
+        dst.data[..len].copy_from_slice(&src.data[..len]);
+        return;
+    }
+
+    let coef = Coefficients::new(params);
+
+    match (src.color_format.is_planar(), dst.color_format.is_planar()) {
+        (false, false) => convert_packed(src, dst),
+        (false, true) => rgb_to_yuv420(src, dst, &coef),
+        (true, false) => yuv420_to_rgb(src, dst, &coef),
+        (true, true) => convert_planar(src, dst),
+    }
+}
+
+/// Byte offsets of red, green and blue in a pixel, and of alpha if any
+fn channels(color_format: ColorFormat) -> ([usize; 3], Option<usize>) {
+    match color_format {
+        ColorFormat::Rgba8888 => ([0, 1, 2], Some(3)),
+        ColorFormat::Bgra8888 => ([2, 1, 0], Some(3)),
+        ColorFormat::Rgb24 => ([0, 1, 2], None),
+        other => panic!("{other:?} is not a packed RGB format"),
+    }
+}
+
+/// Fixed point coefficients of a `YuvParams`, scaled by `1 << SHIFT`
+#[derive(Debug)]
+struct Coefficients {
+    /// RGB to luma
+    y: [i32; 3],
+    /// RGB to blue difference
+    u: [i32; 3],
+    /// RGB to red difference
+    v: [i32; 3],
+    /// Offset of black in luma
+    y_offset: i32,
+
+    /// Luma to RGB
+    y_scale: i32,
+    /// Red difference to red
+    r_v: i32,
+    /// Blue and red differences to green
+    g_u: i32,
+    g_v: i32,
+    /// Blue difference to blue
+    b_u: i32,
+}
+
+impl Coefficients {
+    fn new(params: YuvParams) -> Self {
+        let (kr, kb) = match params.matrix {
+            YuvMatrix::Bt601 => (0.299, 0.114),
+            YuvMatrix::Bt709 => (0.2126, 0.0722),
+        };
+        let kg = 1.0 - kr - kb;
+
+        let (y_range, c_range, y_offset) = match params.range {
+            YuvRange::Limited => (219.0 / 255.0, 224.0 / 255.0, 16),
+            YuvRange::Full => (1.0, 1.0, 0),
+        };
+
+        let fixed = |x: f64| (x * (1 << SHIFT) as f64).round() as i32;
+        let u_scale = c_range / (2.0 * (1.0 - kb));
+        let v_scale = c_range / (2.0 * (1.0 - kr));
+
+        Coefficients {
+            y: [
+                fixed(kr * y_range),
+                fixed(kg * y_range),
+                fixed(kb * y_range),
+            ],
+            u: [
+                fixed(-kr * u_scale),
+                fixed(-kg * u_scale),
+                fixed((1.0 - kb) * u_scale),
+            ],
+            v: [
+                fixed((1.0 - kr) * v_scale),
+                fixed(-kg * v_scale),
+                fixed(-kb * v_scale),
+            ],
+            y_offset,
+
+            y_scale: fixed(1.0 / y_range),
+            r_v: fixed(2.0 * (1.0 - kr) / c_range),
+            g_u: fixed(-2.0 * kb * (1.0 - kb) / kg / c_range),
+            g_v: fixed(-2.0 * kr * (1.0 - kr) / kg / c_range),
+            b_u: fixed(2.0 * (1.0 - kb) / c_range),
+        }
+    }
+
+    /// Luma, blue and red differences of RGB lanes
+    fn rgb_to_yuv(&self, [r, g, b]: [i32x8; 3]) -> [i32x8; 3] {
+        let dot = |c: [i32; 3], offset: i32| {
+            let sum = r * i32x8::splat(c[0])
+                + g * i32x8::splat(c[1])
+                + b * i32x8::splat(c[2])
+                + i32x8::splat((offset << SHIFT) + (1 << (SHIFT - 1)));
+            clamp_u8(sum >> SHIFT)
+        };
+
+        [
+            dot(self.y, self.y_offset),
+            dot(self.u, 128),
+            dot(self.v, 128),
+        ]
+    }
+
+    /// RGB of luma, blue and red difference lanes
+    fn yuv_to_rgb(&self, [y, u, v]: [i32x8; 3]) -> [i32x8; 3] {
+        let y = (y - i32x8::splat(self.y_offset)) * i32x8::splat(self.y_scale)
+            + i32x8::splat(1 << (SHIFT - 1));
+        let u = u - i32x8::splat(128);
+        let v = v - i32x8::splat(128);
+
+        [
+            clamp_u8((y + v * i32x8::splat(self.r_v)) >> SHIFT),
+            clamp_u8((y + u * i32x8::splat(self.g_u) + v * i32x8::splat(self.g_v)) >> SHIFT),
+            clamp_u8((y + u * i32x8::splat(self.b_u)) >> SHIFT),
+        ]
+    }
+}
+
+fn clamp_u8(x: i32x8) -> i32x8 {
+    x.max(i32x8::splat(0)).min(i32x8::splat(255))
+}
+
+/// First bytes of `data` in an array, zero past its end
+fn load<const N: usize>(data: &[u8]) -> [u8; N] {
+    let mut out = [0; N];
+    out[..data.len()].copy_from_slice(data);
+    out
+}
+
+/// Red, green and blue lanes of the even and odd pixels of `row`, which holds up to `BLOCK`.
+/// Pixels past its end repeat the last one, so that 2x2 blocks clipped at the right edge
+/// average the pixels they have.
+fn pixel_pairs<const PIXEL: usize>(row: &[u8], rgb: [usize; 3]) -> [[i32x8; 3]; 2] {
+    let mut block: [u8; BLOCK * 4] = load(row);
+    let last = &row[row.len() - PIXEL..];
+    for px in block[row.len()..BLOCK * PIXEL].chunks_exact_mut(PIXEL) {
+        px.copy_from_slice(last);
+    }
+
+    [0, 1].map(|parity| {
+        rgb.map(|c| {
+            i32x8::from(std::array::from_fn::<i32, LANES, _>(|i| {
+                block[(i * 2 + parity) * PIXEL + c] as i32
+            }))
+        })
+    })
+}
+
+/// Chroma planes of a 4:2:0 image, following the luma plane
+struct ChromaLayout {
+    /// Offset of the first chroma plane
+    offset: usize,
+    /// Bytes per row of a chroma plane
+    stride: usize,
+    /// Rows of a chroma plane
+    rows: usize,
+    /// Distance between the planes of U and V, 1 if interleaved
+    v_offset: usize,
+    /// Distance between two chroma samples in a row
+    step: usize,
+}
+
+impl ChromaLayout {
+    fn of<D: Deref<Target = [u8]>>(img: &Image<D>) -> Self {
+        let stride = img.stride.as_usize();
+        let offset = stride * img.height.as_usize();
+        let rows = img.height.div_ceil(2).as_usize();
+
+        match img.color_format {
+            ColorFormat::Nv12 => ChromaLayout {
+                offset,
+                stride,
+                rows,
+                v_offset: 1,
+                step: 2,
+            },
+            ColorFormat::I420 => ChromaLayout {
+                offset,
+                stride: stride.div_ceil(2),
+                rows,
+                v_offset: stride.div_ceil(2) * rows,
+                step: 1,
+            },
+            other => panic!("{other:?} is not a planar format"),
+        }
+    }
 
-    if src.color_format == dst.color_format {
-        dst.data.copy_from_slice(&src.data);
-    } else if src.color_format == Bgra8888 && dst.color_format == Rgb24 {
-        convert_bgra8888_rgb24(src, dst);
-    } else if src.color_format == Rgb24 && dst.color_format == Bgra8888 {
-        convert_rgb24_bgra8888(src, dst);
-    } else {
-        unimplemented!(
-            "cannot convert {:?} to {:?}",
-            src.color_format,
-            dst.color_format
-        );
+    /// Index of U of the sample at (x, y), with V at `v_offset` from it
+    fn index(&self, x: usize, y: usize) -> usize {
+        self.offset + y * self.stride + x * self.step
     }
 }
 
-fn convert_bgra8888_rgb24<A, B>(src: &Image<A>, dst: &mut Image<B>)
+fn convert_packed<A, B>(src: &Image<A>, dst: &mut Image<B>)
 where
     A: Deref<Target = [u8]>,
     B: DerefMut<Target = [u8]>,
 {
-    assert_eq!(src.height, dst.height);
-    assert_eq!(src.width, dst.width);
-    assert_eq!(src.color_format, ColorFormat::Bgra8888);
-    assert_eq!(dst.color_format, ColorFormat::Rgb24);
+    let (src_rgb, src_alpha) = channels(src.color_format);
+    let (dst_rgb, dst_alpha) = channels(dst.color_format);
+    let src_pixel = src.color_format.pixel_stride().as_usize();
+    let dst_pixel = dst.color_format.pixel_stride().as_usize();
+    let src_stride = src.stride.as_usize();
+    let dst_stride = dst.stride.as_usize();
+    let width = src.width.as_usize();
 
-    let data_src = src.data.deref();
-    let data_dst = dst.data.deref_mut();
+    for y in 0..src.height.as_usize() {
+        let src_row = &src.data[y * src_stride..][..width * src_pixel];
+        let dst_row = &mut dst.data[y * dst_stride..][..width * dst_pixel];
 
-    let height = src.height.as_usize();
+        for (s, d) in src_row
+            .chunks_exact(src_pixel)
+            .zip(dst_row.chunks_exact_mut(dst_pixel))
+        {
+            for (&dc, &sc) in dst_rgb.iter().zip(&src_rgb) {
+                d[dc] = s[sc];
+            }
+            if let Some(a) = dst_alpha {
+                d[a] = src_alpha.map_or(255, |x| s[x]);
+            }
+        }
+    }
+}
+
+fn rgb_to_yuv420<A, B>(src: &Image<A>, dst: &mut Image<B>, coef: &Coefficients)
+where
+    A: Deref<Target = [u8]>,
+    B: DerefMut<Target = [u8]>,
+{
+    match src.color_format.pixel_stride() {
+        3 => rgb_to_yuv420_with::<3, A, B>(src, dst, coef),
+        4 => rgb_to_yuv420_with::<4, A, B>(src, dst, coef),
+        other => unreachable!("{other} bytes per RGB pixel"),
+    }
+}
+
+/// Two rows at a time, in blocks of `BLOCK` pixels: a lane of luma for every pixel of a row,
+/// and a lane of chroma for every 2x2 block of both
+fn rgb_to_yuv420_with<const PIXEL: usize, A, B>(
+    src: &Image<A>,
+    dst: &mut Image<B>,
+    coef: &Coefficients,
+) where
+    A: Deref<Target = [u8]>,
+    B: DerefMut<Target = [u8]>,
+{
+    let (rgb, _) = channels(src.color_format);
+    let src_stride = src.stride.as_usize();
+    let dst_stride = dst.stride.as_usize();
     let width = src.width.as_usize();
+    let height = src.height.as_usize();
+    let chroma = ChromaLayout::of(dst);
 
-    let src_stride = usize::checked_mul(width, 4).expect("source stride too large to fit in usize");
-    let dst_stride =
-        usize::checked_mul(width, 3).expect("destination stride too large to fit in usize");
+    for cy in 0..chroma.rows {
+        // the last row of an odd height stands in for the missing one below it
+        let ys = [cy * 2, (cy * 2 + 1).min(height - 1)];
+        let rows = if cy * 2 + 1 < height { 2 } else { 1 };
 
-    let src_expected_size = usize::checked_mul(height, src_stride)
-        .expect("source bytes count too large to fit in usize");
-    let dst_expected_size = usize::checked_mul(height, dst_stride)
-        .expect("destination bytes count too large to fit in usize");
+        for x in (0..width).step_by(BLOCK) {
+            let n = BLOCK.min(width - x);
+            let pairs = ys.map(|y| {
+                pixel_pairs::<PIXEL>(&src.data[y * src_stride + x * PIXEL..][..n * PIXEL], rgb)
+            });
 
-    assert!(src_expected_size <= data_src.len());
-    assert!(dst_expected_size <= data_dst.len());
+            for (&y, [even, odd]) in ys.iter().zip(&pairs).take(rows) {
+                let [even, odd] = [even, odd].map(|x| coef.rgb_to_yuv(*x)[0].to_array());
+                let luma: [u8; BLOCK] = std::array::from_fn(
+                    |i| if i % 2 == 0 { even[i / 2] } else { odd[i / 2] } as u8,
+                );
+                dst.data[y * dst_stride + x..][..n].copy_from_slice(&luma[..n]);
+            }
 
-    for i in 0..height {
-        for j in 0..width {
-            unsafe {
-                let b = *data_src.get_unchecked(i * src_stride + j * 4);
-                let g = *data_src.get_unchecked(i * src_stride + j * 4 + 1);
-                let r = *data_src.get_unchecked(i * src_stride + j * 4 + 2);
+            let [[top_even, top_odd], [bottom_even, bottom_odd]] = pairs;
+            let average = [0, 1, 2].map(|c| {
+                (top_even[c] + top_odd[c] + bottom_even[c] + bottom_odd[c] + i32x8::splat(2)) >> 2
+            });
+            let [_, u, v] = coef.rgb_to_yuv(average).map(|x| x.to_array());
 
-                *data_dst.get_unchecked_mut(i * dst_stride + j * 3) = r;
-                *data_dst.get_unchecked_mut(i * dst_stride + j * 3 + 1) = g;
-                *data_dst.get_unchecked_mut(i * dst_stride + j * 3 + 2) = b;
+            for i in 0..n.div_ceil(2) {
+                let index = chroma.index(x / 2 + i, cy);
+                dst.data[index] = u[i] as u8;
+                dst.data[index + chroma.v_offset] = v[i] as u8;
             }
         }
     }
 }
 
-fn convert_rgb24_bgra8888<A, B>(src: &Image<A>, dst: &mut Image<B>)
+fn yuv420_to_rgb<A, B>(src: &Image<A>, dst: &mut Image<B>, coef: &Coefficients)
 where
     A: Deref<Target = [u8]>,
     B: DerefMut<Target = [u8]>,
 {
-    assert_eq!(src.height, dst.height);
-    assert_eq!(src.width, dst.width);
-    assert_eq!(src.color_format, ColorFormat::Rgb24);
-    assert_eq!(dst.color_format, ColorFormat::Bgra8888);
-
-    let data_src = src.data.deref();
-    let data_dst = dst.data.deref_mut();
+    match dst.color_format.pixel_stride() {
+        3 => yuv420_to_rgb_with::<3, A, B>(src, dst, coef),
+        4 => yuv420_to_rgb_with::<4, A, B>(src, dst, coef),
+        other => unreachable!("{other} bytes per RGB pixel"),
+    }
+}
 
-    let height = src.height.as_usize();
+fn yuv420_to_rgb_with<const PIXEL: usize, A, B>(
+    src: &Image<A>,
+    dst: &mut Image<B>,
+    coef: &Coefficients,
+) where
+    A: Deref<Target = [u8]>,
+    B: DerefMut<Target = [u8]>,
+{
+    let (rgb, _) = channels(dst.color_format);
+    let src_stride = src.stride.as_usize();
+    let dst_stride = dst.stride.as_usize();
     let width = src.width.as_usize();
-    let src_stride = usize::checked_mul(width, 3).expect("source stride too large to fit in usize");
-    let dst_stride =
-        usize::checked_mul(width, 4).expect("destination stride too large to fit in usize");
-
-    assert!(height * src_stride <= data_src.len());
-    assert!(height * dst_stride <= data_dst.len());
-
-    for i in 0..height {
-        for j in 0..width {
-            unsafe {
-                let r = *data_src.get_unchecked(i * src_stride + j * 3);
-                let g = *data_src.get_unchecked(i * src_stride + j * 3 + 1);
-                let b = *data_src.get_unchecked(i * src_stride + j * 3 + 2);
-
-                *data_dst.get_unchecked_mut(i * dst_stride + j * 4) = b;
-                *data_dst.get_unchecked_mut(i * dst_stride + j * 4 + 1) = g;
-                *data_dst.get_unchecked_mut(i * dst_stride + j * 4 + 2) = r;
-                *data_dst.get_unchecked_mut(i * dst_stride + j * 4 + 3) = 255;
+    let chroma = ChromaLayout::of(src);
+
+    for y in 0..src.height.as_usize() {
+        for x in (0..width).step_by(LANES) {
+            let n = LANES.min(width - x);
+            let luma: [u8; LANES] = load(&src.data[y * src_stride + x..][..n]);
+
+            // a sample for every two pixels, every `step` bytes
+            let first = chroma.index(x / 2, y / 2);
+            let span = (n.div_ceil(2) - 1) * chroma.step + 1;
+            let u: [u8; LANES] = load(&src.data[first..][..span]);
+            let v: [u8; LANES] = load(&src.data[first + chroma.v_offset..][..span]);
+
+            let chroma_lanes = |samples: [u8; LANES]| {
+                i32x8::from(std::array::from_fn::<i32, LANES, _>(|i| {
+                    samples[i / 2 * chroma.step] as i32
+                }))
+            };
+            let out = coef
+                .yuv_to_rgb([
+                    i32x8::from(luma.map(i32::from)),
+                    chroma_lanes(u),
+                    chroma_lanes(v),
+                ])
+                .map(|x| x.to_array());
+
+            // alpha, if any, is left opaque
+            let mut pixels = [255u8; LANES * 4];
+            for (i, px) in pixels.chunks_exact_mut(PIXEL).take(LANES).enumerate() {
+                for (&c, lane) in rgb.iter().zip(&out) {
+                    px[c] = lane[i] as u8;
+                }
             }
+            dst.data[y * dst_stride + x * PIXEL..][..n * PIXEL]
+                .copy_from_slice(&pixels[..n * PIXEL]);
+        }
+    }
+}
+
+fn convert_planar<A, B>(src: &Image<A>, dst: &mut Image<B>)
+where
+    A: Deref<Target = [u8]>,
+    B: DerefMut<Target = [u8]>,
+{
+    let src_stride = src.stride.as_usize();
+    let dst_stride = dst.stride.as_usize();
+    let width = src.width.as_usize();
+
+    for y in 0..src.height.as_usize() {
+        dst.data[y * dst_stride..][..width].copy_from_slice(&src.data[y * src_stride..][..width]);
+    }
+
+    let src_chroma = ChromaLayout::of(src);
+    let dst_chroma = ChromaLayout::of(dst);
+
+    for cy in 0..src_chroma.rows {
+        for cx in 0..width.div_ceil(2) {
+            let s = src_chroma.index(cx, cy);
+            let d = dst_chroma.index(cx, cy);
+            dst.data[d] = src.data[s];
+            dst.data[d + dst_chroma.v_offset] = src.data[s + src_chroma.v_offset];
         }
     }
 }
@@ -1,4 +1,5 @@
 use crate::schema::video::VideoCodec;
+use crate::util::AsUsize;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ColorFormat {
@@ -12,7 +13,11 @@ pub enum ColorFormat {
     Rgb24,
 
     // A typical YUV420 format
+    // Y plane, followed by a plane of interleaved U and V at half resolution
     Nv12,
+
+    // Y plane, followed by planes of U and V at half resolution with half the stride
+    I420,
 }
 
 impl ColorFormat {
@@ -38,6 +43,25 @@ impl ColorFormat {
             ColorFormat::Bgra8888 => 4,
             ColorFormat::Rgb24 => 3,
             ColorFormat::Nv12 => 1,
+            ColorFormat::I420 => 1,
+        }
+    }
+
+    /// YUV 4:2:0 formats, where `stride` is the stride of the luma plane
+    pub fn is_planar(self) -> bool {
+        matches!(self, ColorFormat::Nv12 | ColorFormat::I420)
+    }
+
+    /// Bytes taken by an image of `height` rows of `stride`, including every plane.
+    /// Chroma planes of odd sizes are rounded up.
+    pub fn buffer_len(self, stride: u32, height: u32) -> usize {
+        let luma = stride.as_usize() * height.as_usize();
+        let chroma_rows = height.div_ceil(2).as_usize();
+
+        match self {
+            ColorFormat::Nv12 => luma + stride.as_usize() * chroma_rows,
+            ColorFormat::I420 => luma + 2 * stride.div_ceil(2).as_usize() * chroma_rows,
+            _ => luma,
         }
     }
 }
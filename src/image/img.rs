@@ -1,5 +1,5 @@
-use crate::image::ColorFormat;
-use crate::util::AsUsize;
+use crate::image::{ColorFormat, Rect};
+use crate::util::{AsUsize, FramePool, PooledBuf};
 use std::fmt::{Debug, Formatter};
 use std::ops::Deref;
 
@@ -13,7 +13,8 @@ pub struct Image<D: Deref<Target = [u8]>> {
     pub data: D,
 }
 
-pub type ImageBuf = Image<Vec<u8>>;
+/// Owned image, whose buffer returns to its pool when dropped if allocated from one
+pub type ImageBuf = Image<PooledBuf>;
 
 impl<D> Debug for Image<D>
 where
@@ -46,43 +47,50 @@ where
 }
 
 fn align_to_multiple_of(x: u32, alignment: u32) -> u32 {
-    if x % alignment == 0 {
+    if x.is_multiple_of(alignment) {
         x
     } else {
         x + alignment - (x % alignment)
     }
 }
 
+fn default_stride(width: u32, color_format: ColorFormat) -> u32 {
+    let bytes_per_pixel = color_format.pixel_stride();
+
+    align_to_multiple_of(bytes_per_pixel * width, 4)
+}
+
 impl ImageBuf {
     pub fn alloc(width: u32, height: u32, stride: Option<u32>, color_format: ColorFormat) -> Self {
-        let stride = stride.unwrap_or_else(|| {
-            let bytes_per_pixel = match color_format {
-                ColorFormat::Rgba8888 => 4,
-                ColorFormat::Bgra8888 => 4,
-                ColorFormat::Rgb24 => 3,
-                ColorFormat::Nv12 => 1,
-            };
-
-            align_to_multiple_of(bytes_per_pixel * width, 4)
-        });
-
-        let bytes = match color_format {
-            ColorFormat::Rgba8888 => height * stride,
-            ColorFormat::Bgra8888 => height * stride,
-            ColorFormat::Rgb24 => height * stride,
-            ColorFormat::Nv12 => {
-                assert_eq!(height % 2, 0);
-                (height + height / 2) * stride
-            }
+        let stride = stride.unwrap_or_else(|| default_stride(width, color_format));
+        let bytes = color_format.buffer_len(stride, height);
+
+        ImageBuf {
+            width,
+            height,
+            stride,
+            color_format,
+            data: vec![0; bytes].into(),
         }
-        .as_usize();
+    }
+
+    /// Like `alloc`, but the pixels are left from the previous user of the buffer.
+    pub fn alloc_in(
+        pool: &FramePool,
+        width: u32,
+        height: u32,
+        stride: Option<u32>,
+        color_format: ColorFormat,
+    ) -> Self {
+        let stride = stride.unwrap_or_else(|| default_stride(width, color_format));
+        let bytes = color_format.buffer_len(stride, height);
 
         ImageBuf {
             width,
             height,
             stride,
             color_format,
-            data: vec![0; bytes],
+            data: pool.buffer(bytes),
         }
     }
 }
@@ -99,8 +107,8 @@ impl<D: Deref<Target = [u8]>> Image<D> {
     }
 
     pub fn is_aligned(&self) -> bool {
-        (self.data.as_ptr() as usize) % ALIGNMENT == 0
-            && self.stride.as_usize() % ALIGNMENT == 0
+        (self.data.as_ptr() as usize).is_multiple_of(ALIGNMENT)
+            && self.stride.as_usize().is_multiple_of(ALIGNMENT)
             && self.width * self.color_format.pixel_stride() <= self.stride
     }
 
@@ -116,38 +124,121 @@ impl<D: Deref<Target = [u8]>> Image<D> {
 
     #[must_use]
     pub fn copied(&self) -> ImageBuf {
+        self.with_data(Vec::from(self.data.deref()).into())
+    }
+
+    /// Like `copied`, into a buffer of `pool`.
+    #[must_use]
+    pub fn copied_in(&self, pool: &FramePool) -> ImageBuf {
+        let mut data = pool.with_capacity(self.data.len());
+        data.as_mut_vec().extend_from_slice(&self.data);
+        self.with_data(data)
+    }
+
+    fn with_data(&self, data: PooledBuf) -> ImageBuf {
         ImageBuf {
             width: self.width,
             height: self.height,
             stride: self.stride,
             color_format: self.color_format,
-            data: Vec::from(self.data.deref()),
+            data,
         }
     }
 
     pub fn copy_into(&self, target: &mut ImageBuf) {
-        if self.data.len() <= target.data.len() {
+        if self.data.len() <= target.data.capacity() {
             // fast path
             target.width = self.width;
             target.height = self.height;
             target.stride = self.stride;
             target.color_format = self.color_format;
 
-            target.data.truncate(self.data.len());
-            target.data.copy_from_slice(&self.data);
+            let data = target.data.as_mut_vec();
+            data.clear();
+            data.extend_from_slice(&self.data);
         } else {
             // slow path
             *target = self.copied();
         }
     }
 
-    pub fn validate(&self) {
-        let height = self.height.as_usize();
-        let _width: usize = self.width.as_usize();
+    /// Copy the area into a new, tightly packed image.
+    pub fn crop(&self, rect: &Rect) -> ImageBuf {
+        self.crop_into(rect, PooledBuf::default())
+    }
+
+    /// Like `crop`, into a buffer of `pool`.
+    pub fn crop_in(&self, pool: &FramePool, rect: &Rect) -> ImageBuf {
+        let line_len = (rect.width * self.color_format.pixel_stride()).as_usize();
+        self.crop_into(rect, pool.with_capacity(line_len * rect.height.as_usize()))
+    }
+
+    fn crop_into(&self, rect: &Rect, mut buf: PooledBuf) -> ImageBuf {
+        assert!(
+            !self.color_format.is_planar(),
+            "planar image cannot be cropped"
+        );
+        assert!(
+            Rect::new(0, 0, self.width, self.height).contains(rect),
+            "crop area out of bounds"
+        );
+
+        let pixel = self.color_format.pixel_stride();
+        let line_len = (rect.width * pixel).as_usize();
         let stride = self.stride.as_usize();
 
-        let total_size =
-            usize::checked_mul(height, stride).expect("total size does not fit into usize");
+        let data = buf.as_mut_vec();
+        data.clear();
+        data.reserve(line_len * rect.height.as_usize());
+        for i in rect.y..rect.bottom() {
+            let start = i.as_usize() * stride + (rect.x * pixel).as_usize();
+            data.extend_from_slice(&self.data[start..start + line_len]);
+        }
+
+        ImageBuf {
+            width: rect.width,
+            height: rect.height,
+            stride: rect.width * pixel,
+            color_format: self.color_format,
+            data: buf,
+        }
+    }
+
+    /// Overwrite the area of `target` at (x, y) with this image.
+    pub fn copy_to(&self, target: &mut ImageBuf, x: u32, y: u32) {
+        assert_eq!(
+            self.color_format, target.color_format,
+            "color format must match"
+        );
+        assert!(
+            !self.color_format.is_planar(),
+            "planar image cannot be copied"
+        );
+        assert!(
+            Rect::new(0, 0, target.width, target.height).contains(&Rect::new(
+                x,
+                y,
+                self.width,
+                self.height
+            )),
+            "copy area out of bounds"
+        );
+
+        let pixel = self.color_format.pixel_stride();
+        let line_len = (self.width * pixel).as_usize();
+        let src_stride = self.stride.as_usize();
+        let dst_stride = target.stride.as_usize();
+        let dst_x = (x * pixel).as_usize();
+
+        for i in 0..self.height.as_usize() {
+            let src = i * src_stride;
+            let dst = (y.as_usize() + i) * dst_stride + dst_x;
+            target.data[dst..dst + line_len].copy_from_slice(&self.data[src..src + line_len]);
+        }
+    }
+
+    pub fn validate(&self) {
+        let total_size = self.color_format.buffer_len(self.stride, self.height);
         assert!(
             total_size <= self.data.len(),
             "buffer is smaller than expected size"
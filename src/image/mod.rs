@@ -1,7 +1,13 @@
 mod color_converter;
 mod color_format;
 mod img;
+mod png_codec;
+mod rect;
+mod resize;
 
-pub use color_converter::convert_color;
+pub use color_converter::{convert_color, convert_color_with, YuvMatrix, YuvParams, YuvRange};
 pub use color_format::*;
 pub use img::*;
+pub use png_codec::{decode_png, encode_png};
+pub use rect::Rect;
+pub use resize::{resize, resize_in, ScaleFilter};
@@ -0,0 +1,72 @@
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::image::{convert_color, ColorFormat, Image, ImageBuf};
+
+/// Encode losslessly, keeping alpha as is.
+pub fn encode_png(img: Image<&[u8]>) -> Result<Vec<u8>> {
+    ensure!(
+        !img.color_format.is_planar(),
+        "unsupported color format {:?}",
+        img.color_format
+    );
+
+    let mut rgba = ImageBuf::alloc(
+        img.width,
+        img.height,
+        Some(img.width * 4),
+        ColorFormat::Rgba8888,
+    );
+    convert_color(&img, &mut rgba);
+
+    let mut output = Vec::new();
+    let mut encoder = png::Encoder::new(&mut output, img.width, img.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder
+        .write_header()
+        .context("failed to write PNG header")?;
+    writer.write_image_data(&rgba.data)?;
+    writer.finish()?;
+
+    Ok(output)
+}
+
+/// Decode into a tightly packed `Bgra8888` image.
+pub fn decode_png(data: &[u8]) -> Result<ImageBuf> {
+    let mut decoder = png::Decoder::new(data);
+    // palettes, transparency chunks and low bit depths into plain 8-bit RGB(A)
+    decoder.set_transformations(png::Transformations::EXPAND);
+
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+
+    ensure!(
+        info.bit_depth == png::BitDepth::Eight,
+        "unsupported PNG bit depth {:?}",
+        info.bit_depth
+    );
+    let color_format = match info.color_type {
+        png::ColorType::Rgba => ColorFormat::Rgba8888,
+        png::ColorType::Rgb => ColorFormat::Rgb24,
+        other => bail!("unsupported PNG color type {other:?}"),
+    };
+
+    let decoded = Image::new(
+        info.width,
+        info.height,
+        info.line_size.try_into()?,
+        color_format,
+        buf,
+    );
+    let mut img = ImageBuf::alloc(
+        info.width,
+        info.height,
+        Some(info.width * 4),
+        ColorFormat::Bgra8888,
+    );
+    convert_color(&decoded, &mut img);
+
+    Ok(img)
+}
@@ -0,0 +1,160 @@
+use std::ops::Deref;
+
+use serde::{Deserialize, Serialize};
+
+use crate::image::{Image, ImageBuf};
+use crate::util::{AsUsize, FramePool, PooledBuf};
+
+/// How pixels are sampled when resizing an image
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScaleFilter {
+    /// Average of the source pixels covered by each pixel. Sharpest for downscaling.
+    #[default]
+    Area,
+
+    /// Interpolated from the four nearest source pixels. Faster, but aliases below half size.
+    Bilinear,
+}
+
+/// Resize into a new, tightly packed image of the same color format.
+pub fn resize<D>(src: &Image<D>, width: u32, height: u32, filter: ScaleFilter) -> ImageBuf
+where
+    D: Deref<Target = [u8]>,
+{
+    resize_into(src, width, height, filter, PooledBuf::default())
+}
+
+/// Like `resize`, into a buffer of `pool`.
+pub fn resize_in<D>(
+    pool: &FramePool,
+    src: &Image<D>,
+    width: u32,
+    height: u32,
+    filter: ScaleFilter,
+) -> ImageBuf
+where
+    D: Deref<Target = [u8]>,
+{
+    let len = width.as_usize() * height.as_usize() * src.color_format.pixel_stride().as_usize();
+    resize_into(src, width, height, filter, pool.with_capacity(len))
+}
+
+fn resize_into<D>(
+    src: &Image<D>,
+    width: u32,
+    height: u32,
+    filter: ScaleFilter,
+    mut buf: PooledBuf,
+) -> ImageBuf
+where
+    D: Deref<Target = [u8]>,
+{
+    assert!(
+        !src.color_format.is_planar(),
+        "planar image cannot be resized"
+    );
+    assert!(
+        src.width > 0 && src.height > 0 && width > 0 && height > 0,
+        "image must not be empty"
+    );
+    src.validate();
+
+    let data = buf.as_mut_vec();
+    data.clear();
+    data.reserve(width.as_usize() * height.as_usize() * src.color_format.pixel_stride().as_usize());
+
+    match filter {
+        ScaleFilter::Area => resize_area(src, width, height, data),
+        ScaleFilter::Bilinear => resize_bilinear(src, width, height, data),
+    }
+
+    Image::new(
+        width,
+        height,
+        width * src.color_format.pixel_stride(),
+        src.color_format,
+        buf,
+    )
+}
+
+/// Source range [start, end) covered by every destination index, never empty.
+fn area_spans(src_len: u32, dst_len: u32) -> Vec<(usize, usize)> {
+    (0..dst_len as u64)
+        .map(|i| {
+            let start = (i * src_len as u64 / dst_len as u64) as usize;
+            let end = ((i + 1) * src_len as u64 / dst_len as u64) as usize;
+            (start, end.max(start + 1).min(src_len.as_usize()))
+        })
+        .collect()
+}
+
+fn resize_area<D>(src: &Image<D>, width: u32, height: u32, data: &mut Vec<u8>)
+where
+    D: Deref<Target = [u8]>,
+{
+    let pixel = src.color_format.pixel_stride().as_usize();
+    let stride = src.stride.as_usize();
+    let columns = area_spans(src.width, width);
+    let rows = area_spans(src.height, height);
+
+    let mut sum = vec![0u32; pixel];
+
+    for &(y0, y1) in &rows {
+        for &(x0, x1) in &columns {
+            sum.fill(0);
+
+            for y in y0..y1 {
+                let row = &src.data[y * stride + x0 * pixel..y * stride + x1 * pixel];
+                for px in row.chunks_exact(pixel) {
+                    for (s, &c) in sum.iter_mut().zip(px) {
+                        *s += c as u32;
+                    }
+                }
+            }
+
+            let count = ((y1 - y0) * (x1 - x0)) as u32;
+            data.extend(sum.iter().map(|s| ((s + count / 2) / count) as u8));
+        }
+    }
+}
+
+/// Two nearest source indices of every destination index, and the weight of the second
+/// in 1/256 units, sampling at pixel centers.
+fn bilinear_taps(src_len: u32, dst_len: u32) -> Vec<(usize, usize, u32)> {
+    let ratio = src_len as f64 / dst_len as f64;
+    let last = src_len.as_usize() - 1;
+
+    (0..dst_len)
+        .map(|i| {
+            let pos = ((i as f64 + 0.5) * ratio - 0.5).max(0.0);
+            let start = (pos.floor() as usize).min(last);
+            let weight = ((pos - start as f64) * 256.0).round() as u32;
+            (start, (start + 1).min(last), weight.min(256))
+        })
+        .collect()
+}
+
+fn resize_bilinear<D>(src: &Image<D>, width: u32, height: u32, data: &mut Vec<u8>)
+where
+    D: Deref<Target = [u8]>,
+{
+    let pixel = src.color_format.pixel_stride().as_usize();
+    let stride = src.stride.as_usize();
+    let columns = bilinear_taps(src.width, width);
+    let rows = bilinear_taps(src.height, height);
+
+    for &(y0, y1, wy) in &rows {
+        let top = &src.data[y0 * stride..];
+        let bottom = &src.data[y1 * stride..];
+
+        for &(x0, x1, wx) in &columns {
+            for c in 0..pixel {
+                let lerp = |row: &[u8]| {
+                    row[x0 * pixel + c] as u32 * (256 - wx) + row[x1 * pixel + c] as u32 * wx
+                };
+                let value = lerp(top) * (256 - wy) + lerp(bottom) * wy;
+                data.push(((value + (1 << 15)) >> 16) as u8);
+            }
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use anyhow::Result;
+
+use crate::input::InputEvent;
+
+use super::InputInjector;
+
+/// Discards every event.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopInjector;
+
+impl InputInjector for NoopInjector {
+    fn inject(&self, _event: &InputEvent, _monitor: Option<&str>) -> Result<()> {
+        Ok(())
+    }
+}
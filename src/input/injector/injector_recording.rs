@@ -0,0 +1,35 @@
+use anyhow::Result;
+use parking_lot::Mutex;
+
+use crate::input::InputEvent;
+
+use super::InputInjector;
+
+/// Keeps every event in memory instead of delivering it.
+#[derive(Debug, Default)]
+pub struct RecordingInjector {
+    events: Mutex<Vec<InputEvent>>,
+}
+
+impl RecordingInjector {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Events injected so far, in order.
+    pub fn events(&self) -> Vec<InputEvent> {
+        self.events.lock().clone()
+    }
+
+    /// Returns injected events and clears the record.
+    pub fn take(&self) -> Vec<InputEvent> {
+        std::mem::take(&mut *self.events.lock())
+    }
+}
+
+impl InputInjector for RecordingInjector {
+    fn inject(&self, event: &InputEvent, _monitor: Option<&str>) -> Result<()> {
+        self.events.lock().push(*event);
+        Ok(())
+    }
+}
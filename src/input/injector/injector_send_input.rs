@@ -0,0 +1,275 @@
+use anyhow::{anyhow, Result};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, KEYBDINPUT, KEYBD_EVENT_FLAGS,
+    KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE, MOUSEEVENTF_ABSOLUTE,
+    MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MIDDLEDOWN,
+    MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_MOVE, MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP,
+    MOUSEEVENTF_VIRTUALDESK, MOUSEEVENTF_WHEEL, MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, MOUSEINPUT,
+    MOUSE_EVENT_FLAGS, VIRTUAL_KEY,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetSystemMetrics, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN,
+};
+
+use crate::input::{InputEvent, MouseButton};
+use crate::video::capture::monitor_rect;
+
+use super::InputInjector;
+
+const XBUTTON1: u32 = 1;
+const XBUTTON2: u32 = 2;
+
+/// Injects input using the `SendInput` API.
+///
+/// Pointer position is relative to the monitor given along with each event.
+#[derive(Debug, Default)]
+pub struct SendInputInjector {}
+
+impl SendInputInjector {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl InputInjector for SendInputInjector {
+    fn inject(&self, event: &InputEvent, monitor: Option<&str>) -> Result<()> {
+        let input = match *event {
+            InputEvent::Key { usage, pressed } => {
+                let (scancode, extended) = match hid_to_scancode(usage) {
+                    Some(x) => x,
+                    None => {
+                        log::debug!("Ignoring key with unknown usage {usage:#04x}");
+                        return Ok(());
+                    }
+                };
+
+                let mut flags = KEYEVENTF_SCANCODE;
+                if extended {
+                    flags |= KEYEVENTF_EXTENDEDKEY;
+                }
+                if !pressed {
+                    flags |= KEYEVENTF_KEYUP;
+                }
+
+                keyboard_input(scancode, flags)
+            }
+            InputEvent::PointerMove { x, y } => {
+                // position on the virtual screen, which spans every monitor
+                let rect = monitor_rect(monitor.unwrap_or(""))?;
+                let x = rect.left as i64 + (x as i64).min((rect.right - rect.left - 1) as i64);
+                let y = rect.top as i64 + (y as i64).min((rect.bottom - rect.top - 1) as i64);
+
+                let (left, top, width, height) = unsafe {
+                    (
+                        GetSystemMetrics(SM_XVIRTUALSCREEN) as i64,
+                        GetSystemMetrics(SM_YVIRTUALSCREEN) as i64,
+                        GetSystemMetrics(SM_CXVIRTUALSCREEN).max(2) as i64,
+                        GetSystemMetrics(SM_CYVIRTUALSCREEN).max(2) as i64,
+                    )
+                };
+
+                // Absolute coordinates are normalized into 0..=65535 over the virtual screen
+                let dx = (x - left).clamp(0, width - 1) * 65535 / (width - 1);
+                let dy = (y - top).clamp(0, height - 1) * 65535 / (height - 1);
+
+                mouse_input(
+                    dx as i32,
+                    dy as i32,
+                    0,
+                    MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK,
+                )
+            }
+            InputEvent::PointerButton { button, pressed } => {
+                let (flags, data) = match (button, pressed) {
+                    (MouseButton::Left, true) => (MOUSEEVENTF_LEFTDOWN, 0),
+                    (MouseButton::Left, false) => (MOUSEEVENTF_LEFTUP, 0),
+                    (MouseButton::Right, true) => (MOUSEEVENTF_RIGHTDOWN, 0),
+                    (MouseButton::Right, false) => (MOUSEEVENTF_RIGHTUP, 0),
+                    (MouseButton::Middle, true) => (MOUSEEVENTF_MIDDLEDOWN, 0),
+                    (MouseButton::Middle, false) => (MOUSEEVENTF_MIDDLEUP, 0),
+                    (MouseButton::Back, true) => (MOUSEEVENTF_XDOWN, XBUTTON1),
+                    (MouseButton::Back, false) => (MOUSEEVENTF_XUP, XBUTTON1),
+                    (MouseButton::Forward, true) => (MOUSEEVENTF_XDOWN, XBUTTON2),
+                    (MouseButton::Forward, false) => (MOUSEEVENTF_XUP, XBUTTON2),
+                };
+
+                mouse_input(0, 0, data, flags)
+            }
+            InputEvent::PointerWheel { delta_x, delta_y } => {
+                if delta_y != 0 {
+                    send(&[mouse_input(0, 0, delta_y as u32, MOUSEEVENTF_WHEEL)])?;
+                }
+                if delta_x != 0 {
+                    send(&[mouse_input(0, 0, delta_x as u32, MOUSEEVENTF_HWHEEL)])?;
+                }
+                return Ok(());
+            }
+        };
+
+        send(&[input])
+    }
+}
+
+fn send(inputs: &[INPUT]) -> Result<()> {
+    let sent = unsafe { SendInput(inputs, std::mem::size_of::<INPUT>() as i32) };
+    if sent as usize != inputs.len() {
+        return Err(anyhow!(
+            "SendInput failed: {:?}",
+            windows::core::Error::from_win32()
+        ));
+    }
+
+    Ok(())
+}
+
+fn keyboard_input(scancode: u16, flags: KEYBD_EVENT_FLAGS) -> INPUT {
+    INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: VIRTUAL_KEY(0),
+                wScan: scancode,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    }
+}
+
+fn mouse_input(dx: i32, dy: i32, data: u32, flags: MOUSE_EVENT_FLAGS) -> INPUT {
+    INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                dx,
+                dy,
+                mouseData: data,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    }
+}
+
+/// Converts USB HID usage ID into scancode set 1.
+/// Returns (scancode, is_extended).
+fn hid_to_scancode(usage: u16) -> Option<(u16, bool)> {
+    let normal = |x| Some((x, false));
+    let extended = |x| Some((x, true));
+
+    match usage {
+        // letters
+        0x04 => normal(0x1E), // A
+        0x05 => normal(0x30), // B
+        0x06 => normal(0x2E), // C
+        0x07 => normal(0x20), // D
+        0x08 => normal(0x12), // E
+        0x09 => normal(0x21), // F
+        0x0A => normal(0x22), // G
+        0x0B => normal(0x23), // H
+        0x0C => normal(0x17), // I
+        0x0D => normal(0x24), // J
+        0x0E => normal(0x25), // K
+        0x0F => normal(0x26), // L
+        0x10 => normal(0x32), // M
+        0x11 => normal(0x31), // N
+        0x12 => normal(0x18), // O
+        0x13 => normal(0x19), // P
+        0x14 => normal(0x10), // Q
+        0x15 => normal(0x13), // R
+        0x16 => normal(0x1F), // S
+        0x17 => normal(0x14), // T
+        0x18 => normal(0x16), // U
+        0x19 => normal(0x2F), // V
+        0x1A => normal(0x11), // W
+        0x1B => normal(0x2D), // X
+        0x1C => normal(0x15), // Y
+        0x1D => normal(0x2C), // Z
+
+        // digits: 1..9 then 0
+        0x1E..=0x27 => normal(usage - 0x1E + 0x02),
+
+        0x28 => normal(0x1C), // Enter
+        0x29 => normal(0x01), // Escape
+        0x2A => normal(0x0E), // Backspace
+        0x2B => normal(0x0F), // Tab
+        0x2C => normal(0x39), // Space
+        0x2D => normal(0x0C), // -
+        0x2E => normal(0x0D), // =
+        0x2F => normal(0x1A), // [
+        0x30 => normal(0x1B), // ]
+        0x31 => normal(0x2B), // Backslash
+        0x32 => normal(0x2B), // Non-US #
+        0x33 => normal(0x27), // ;
+        0x34 => normal(0x28), // '
+        0x35 => normal(0x29), // `
+        0x36 => normal(0x33), // ,
+        0x37 => normal(0x34), // .
+        0x38 => normal(0x35), // /
+        0x39 => normal(0x3A), // Caps Lock
+
+        // F1..F10 are contiguous
+        0x3A..=0x43 => normal(usage - 0x3A + 0x3B),
+        0x44 => normal(0x57), // F11
+        0x45 => normal(0x58), // F12
+
+        0x46 => extended(0x37), // Print Screen
+        0x47 => normal(0x46),   // Scroll Lock
+        0x49 => extended(0x52), // Insert
+        0x4A => extended(0x47), // Home
+        0x4B => extended(0x49), // Page Up
+        0x4C => extended(0x53), // Delete
+        0x4D => extended(0x4F), // End
+        0x4E => extended(0x51), // Page Down
+        0x4F => extended(0x4D), // Right
+        0x50 => extended(0x4B), // Left
+        0x51 => extended(0x50), // Down
+        0x52 => extended(0x48), // Up
+
+        0x53 => normal(0x45),   // Num Lock
+        0x54 => extended(0x35), // Keypad /
+        0x55 => normal(0x37),   // Keypad *
+        0x56 => normal(0x4A),   // Keypad -
+        0x57 => normal(0x4E),   // Keypad +
+        0x58 => extended(0x1C), // Keypad Enter
+        0x59 => normal(0x4F),   // Keypad 1
+        0x5A => normal(0x50),   // Keypad 2
+        0x5B => normal(0x51),   // Keypad 3
+        0x5C => normal(0x4B),   // Keypad 4
+        0x5D => normal(0x4C),   // Keypad 5
+        0x5E => normal(0x4D),   // Keypad 6
+        0x5F => normal(0x47),   // Keypad 7
+        0x60 => normal(0x48),   // Keypad 8
+        0x61 => normal(0x49),   // Keypad 9
+        0x62 => normal(0x52),   // Keypad 0
+        0x63 => normal(0x53),   // Keypad .
+
+        0x64 => normal(0x56),   // Non-US Backslash
+        0x65 => extended(0x5D), // Application
+
+        // F13..F23 are contiguous
+        0x68..=0x72 => normal(usage - 0x68 + 0x64),
+        0x73 => normal(0x76), // F24
+
+        0x87 => normal(0x73), // International1 (Ro)
+        0x88 => normal(0x70), // International2 (Katakana/Hiragana)
+        0x89 => normal(0x7D), // International3 (Yen)
+        0x8A => normal(0x79), // International4 (Henkan)
+        0x8B => normal(0x7B), // International5 (Muhenkan)
+        0x90 => normal(0xF2), // Lang1 (Hangul)
+        0x91 => normal(0xF1), // Lang2 (Hanja)
+
+        0xE0 => normal(0x1D),   // Left Control
+        0xE1 => normal(0x2A),   // Left Shift
+        0xE2 => normal(0x38),   // Left Alt
+        0xE3 => extended(0x5B), // Left GUI
+        0xE4 => extended(0x1D), // Right Control
+        0xE5 => normal(0x36),   // Right Shift
+        0xE6 => extended(0x38), // Right Alt
+        0xE7 => extended(0x5C), // Right GUI
+
+        _ => None,
+    }
+}
@@ -0,0 +1,41 @@
+mod injector_noop;
+mod injector_recording;
+#[cfg(windows)]
+mod injector_send_input;
+
+use anyhow::Result;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::input::InputEvent;
+use crate::server::InputInjectionMethod;
+
+pub use injector_noop::NoopInjector;
+pub use injector_recording::RecordingInjector;
+#[cfg(windows)]
+pub use injector_send_input::SendInputInjector;
+
+/// Delivers input events received from a client to the operating system.
+pub trait InputInjector: Debug + Send + Sync {
+    /// Pointer positions are relative to `monitor`, one of the ids listed by the capture
+    /// factory, or to the primary monitor if None.
+    fn inject(&self, event: &InputEvent, monitor: Option<&str>) -> Result<()>;
+}
+
+// ensure object safety
+const _: Option<&dyn InputInjector> = None;
+
+/// Create the injector for the given method.
+/// Falls back to `NoopInjector` if the method is unavailable on this platform.
+pub fn create_injector(method: InputInjectionMethod) -> Arc<dyn InputInjector> {
+    match method {
+        InputInjectionMethod::Disabled => Arc::new(NoopInjector),
+        #[cfg(windows)]
+        InputInjectionMethod::SendInput => Arc::new(SendInputInjector::new()),
+        #[allow(unreachable_patterns)]
+        _ => {
+            log::warn!("Input injection method {method:?} is not available on this platform");
+            Arc::new(NoopInjector)
+        }
+    }
+}
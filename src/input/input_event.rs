@@ -0,0 +1,170 @@
+use anyhow::{anyhow, ensure, Result};
+use flatbuffers::{FlatBufferBuilder, WIPOffset};
+
+use crate::schema::input::{
+    InputFrame, InputFrameArgs, InputPacket, KeyEvent, KeyEventArgs, PointerButton,
+    PointerButtonArgs, PointerMove, PointerMoveArgs, PointerWheel, PointerWheelArgs,
+};
+use crate::schema::parse_msg;
+use crate::schema::video::Coord2u;
+
+/// Scroll amount of a single wheel notch
+pub const WHEEL_DELTA: i32 = 120;
+
+/// Input event sent from the client to the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    /// Key identified by its USB HID usage ID (keyboard usage page 0x07)
+    Key {
+        usage: u16,
+        pressed: bool,
+    },
+
+    /// Absolute pointer position in desktop coordinates
+    PointerMove {
+        x: u32,
+        y: u32,
+    },
+
+    PointerButton {
+        button: MouseButton,
+        pressed: bool,
+    },
+
+    /// Wheel movement. A single notch is `WHEEL_DELTA`.
+    /// Positive values scroll up and to the right.
+    PointerWheel {
+        delta_x: i32,
+        delta_y: i32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+}
+
+impl InputEvent {
+    pub fn build<'a>(&self, builder: &mut FlatBufferBuilder<'a>) -> WIPOffset<InputFrame<'a>> {
+        let (data_type, data) = match *self {
+            InputEvent::Key { usage, pressed } => (
+                InputPacket::KeyEvent,
+                KeyEvent::create(builder, &KeyEventArgs { usage, pressed }).as_union_value(),
+            ),
+            InputEvent::PointerMove { x, y } => (
+                InputPacket::PointerMove,
+                PointerMove::create(
+                    builder,
+                    &PointerMoveArgs {
+                        pos: Some(&Coord2u::new(x, y)),
+                    },
+                )
+                .as_union_value(),
+            ),
+            InputEvent::PointerButton { button, pressed } => (
+                InputPacket::PointerButton,
+                PointerButton::create(
+                    builder,
+                    &PointerButtonArgs {
+                        button: button.into(),
+                        pressed,
+                    },
+                )
+                .as_union_value(),
+            ),
+            InputEvent::PointerWheel { delta_x, delta_y } => (
+                InputPacket::PointerWheel,
+                PointerWheel::create(builder, &PointerWheelArgs { delta_x, delta_y })
+                    .as_union_value(),
+            ),
+        };
+
+        InputFrame::create(
+            builder,
+            &InputFrameArgs {
+                data_type,
+                data: Some(data),
+            },
+        )
+    }
+
+    /// Parse a size prefixed `InputFrame`.
+    pub fn parse(msg: &[u8]) -> Result<Self> {
+        ensure!(msg.len() >= 4, "input message too short");
+        let size = u32::from_le_bytes(msg[..4].try_into().expect("checked above")) as usize;
+        ensure!(4 + size <= msg.len(), "input message truncated");
+
+        let frame: InputFrame = parse_msg(msg)?;
+        Self::from_frame(&frame)
+    }
+
+    pub fn from_frame(frame: &InputFrame) -> Result<Self> {
+        let invalid = || anyhow!("invalid input packet {:?}", frame.data_type());
+
+        match frame.data_type() {
+            InputPacket::KeyEvent => {
+                let x = frame.data_as_key_event().ok_or_else(invalid)?;
+                Ok(InputEvent::Key {
+                    usage: x.usage(),
+                    pressed: x.pressed(),
+                })
+            }
+            InputPacket::PointerMove => {
+                let x = frame.data_as_pointer_move().ok_or_else(invalid)?;
+                let pos = x.pos().ok_or_else(invalid)?;
+                Ok(InputEvent::PointerMove {
+                    x: pos.x(),
+                    y: pos.y(),
+                })
+            }
+            InputPacket::PointerButton => {
+                let x = frame.data_as_pointer_button().ok_or_else(invalid)?;
+                Ok(InputEvent::PointerButton {
+                    button: x.button().try_into()?,
+                    pressed: x.pressed(),
+                })
+            }
+            InputPacket::PointerWheel => {
+                let x = frame.data_as_pointer_wheel().ok_or_else(invalid)?;
+                Ok(InputEvent::PointerWheel {
+                    delta_x: x.delta_x(),
+                    delta_y: x.delta_y(),
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl From<MouseButton> for crate::schema::input::MouseButton {
+    fn from(value: MouseButton) -> Self {
+        match value {
+            MouseButton::Left => Self::Left,
+            MouseButton::Right => Self::Right,
+            MouseButton::Middle => Self::Middle,
+            MouseButton::Back => Self::Back,
+            MouseButton::Forward => Self::Forward,
+        }
+    }
+}
+
+impl TryFrom<crate::schema::input::MouseButton> for MouseButton {
+    type Error = anyhow::Error;
+
+    fn try_from(value: crate::schema::input::MouseButton) -> Result<Self> {
+        use crate::schema::input::MouseButton as Schema;
+
+        Ok(match value {
+            Schema::Left => MouseButton::Left,
+            Schema::Right => MouseButton::Right,
+            Schema::Middle => MouseButton::Middle,
+            Schema::Back => MouseButton::Back,
+            Schema::Forward => MouseButton::Forward,
+            _ => return Err(anyhow!("unknown mouse button {value:?}")),
+        })
+    }
+}
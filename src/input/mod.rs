@@ -0,0 +1,4 @@
+pub mod injector;
+mod input_event;
+
+pub use input_event::{InputEvent, MouseButton, WHEEL_DELTA};
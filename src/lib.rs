@@ -1,6 +1,7 @@
 pub mod audio;
 pub mod client;
 pub mod image;
+pub mod input;
 pub mod network;
 pub mod platform;
 pub mod schema;
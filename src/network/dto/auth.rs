@@ -4,3 +4,60 @@ use serde::{Deserialize, Serialize};
 pub struct AuthSuccessResponse {
     pub token: String,
 }
+
+/// Body of `POST /auth-server?type=pin`
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum AuthServerPinRequest {
+    Commit,
+    Reveal { id: String, client_nonce: String },
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AuthServerCommitResponse {
+    pub id: String,
+    pub commitment: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AuthServerRevealResponse {
+    pub server_nonce: String,
+}
+
+/// Response of `GET /auth`
+#[derive(Serialize, Deserialize)]
+pub struct AuthTypesResponse {
+    pub types: Vec<AuthType>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthType {
+    Password,
+    Token,
+    Username,
+}
+
+impl AuthType {
+    /// Used in `POST /auth/{type}`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthType::Password => "password",
+            AuthType::Token => "token",
+            AuthType::Username => "username",
+        }
+    }
+}
+
+/// Body of `POST /auth/password`
+#[derive(Serialize, Deserialize)]
+pub struct PasswordAuthRequest {
+    pub username: String,
+    pub password: String,
+}
+
+/// Body of `POST /auth/token`
+#[derive(Serialize, Deserialize)]
+pub struct TokenAuthRequest {
+    pub token: String,
+}
@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct StartInput {
+    pub ch: u16,
+
+    /// Monitor the pointer positions are relative to, the primary one if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
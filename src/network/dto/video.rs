@@ -1,3 +1,6 @@
+use std::str::FromStr;
+
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -25,8 +28,59 @@ pub struct Resolution {
     pub height: u32,
 }
 
+/// Parses `{width}x{height}`, such as `1920x1080`
+impl FromStr for Resolution {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s
+            .split_once('x')
+            .context("resolution must be given as {width}x{height}")?;
+
+        Ok(Resolution {
+            width: width.trim().parse().context("invalid width")?,
+            height: height.trim().parse().context("invalid height")?,
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct StartCapture {
     pub ch: u16,
     pub id: String,
+
+    /// Overrides the codec of the server config for this session
+    #[serde(default)]
+    pub codec: Option<DesktopCodec>,
+
+    /// Downscales the desktop to fit in it, keeping the aspect ratio
+    #[serde(default)]
+    pub target_resolution: Option<Resolution>,
+
+    /// Lowers the frame rate below the one of the server config
+    #[serde(default)]
+    pub max_fps: Option<u32>,
+}
+
+/// Codec to encode the desktop with
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum,
+)]
+#[non_exhaustive]
+pub enum DesktopCodec {
+    /// Every frame is a still image. Fast to recover from loss, but uses more bandwidth.
+    #[default]
+    Jpeg,
+
+    /// Software encoded by openh264
+    H264,
+
+    /// Encoded by rav1e for low bitrate links. Requires `av1` cargo feature.
+    Av1,
+
+    /// Pixel exact frames for text and CAD work, at the cost of bandwidth
+    Lossless,
+
+    /// Lossless for text and line art, and JPEG for photos and video in the same frame
+    Hybrid,
 }
@@ -1 +1,5 @@
 pub mod dto;
+pub mod server_auth;
+
+/// Channel carrying `ControlFrame`, which is never allocated for data
+pub const CONTROL_CHANNEL: u16 = 0;
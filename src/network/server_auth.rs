@@ -0,0 +1,55 @@
+//! Short PIN that lets the user authenticate a server certificate.
+//!
+//! The server commits to its nonce before learning the client nonce,
+//! so a man in the middle can't choose nonces to make both PINs match.
+
+use sha2::{Digest, Sha256};
+
+/// SHA-256 of DER encoded certificate
+pub type Fingerprint = [u8; 32];
+
+pub type Nonce = [u8; 32];
+
+const PIN_MODULO: u64 = 1_000_000;
+
+pub fn fingerprint(cert_der: &[u8]) -> Fingerprint {
+    Sha256::digest(cert_der).into()
+}
+
+/// Formats the fingerprint for humans and known hosts file
+pub fn format_fingerprint(fingerprint: &Fingerprint) -> String {
+    format!("sha256:{}", hex::encode(fingerprint))
+}
+
+pub fn parse_fingerprint(s: &str) -> Option<Fingerprint> {
+    let s = s.strip_prefix("sha256:")?;
+
+    let mut output = [0; 32];
+    hex::decode_to_slice(s, &mut output).ok()?;
+
+    Some(output)
+}
+
+/// Sent by the server before the client reveals its nonce
+pub fn commitment(fingerprint: &Fingerprint, server_nonce: &Nonce) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(b"twilight server commitment")
+        .chain_update(fingerprint)
+        .chain_update(server_nonce)
+        .finalize()
+        .into()
+}
+
+/// 6 digit PIN shown by the server and typed into the client
+pub fn pin(fingerprint: &Fingerprint, client_nonce: &Nonce, server_nonce: &Nonce) -> String {
+    let hash: [u8; 32] = Sha256::new()
+        .chain_update(b"twilight server pin")
+        .chain_update(fingerprint)
+        .chain_update(client_nonce)
+        .chain_update(server_nonce)
+        .finalize()
+        .into();
+
+    let value = u64::from_le_bytes(hash[..8].try_into().expect("slice of length 8"));
+    format!("{:06}", value % PIN_MODULO)
+}
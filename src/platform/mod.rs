@@ -1 +1,2 @@
+#[cfg(windows)]
 pub mod win32;
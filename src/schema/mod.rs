@@ -9,7 +9,7 @@ where
 
 /// Returns the embedded "payload", buffer content after the flatbuffer message.
 pub fn parse_msg_payload(data: &bytes::Bytes) -> bytes::Bytes {
-    let start = 4 + get_prefixed_size(&data);
+    let start = 4 + get_prefixed_size(data);
     data.slice(start..)
 }
 
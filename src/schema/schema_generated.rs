@@ -459,14 +459,19 @@ pub mod video {
 #[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
 pub const ENUM_MIN_VIDEO_CODEC: i8 = 0;
 #[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
-pub const ENUM_MAX_VIDEO_CODEC: i8 = 3;
+pub const ENUM_MAX_VIDEO_CODEC: i8 = 8;
 #[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
 #[allow(non_camel_case_types)]
-pub const ENUM_VALUES_VIDEO_CODEC: [VideoCodec; 4] = [
+pub const ENUM_VALUES_VIDEO_CODEC: [VideoCodec; 9] = [
   VideoCodec::Null,
   VideoCodec::Bgra8888,
   VideoCodec::Rgb24,
   VideoCodec::Jpeg,
+  VideoCodec::H264,
+  VideoCodec::Av1,
+  VideoCodec::ZstdDelta,
+  VideoCodec::Hybrid,
+  VideoCodec::Png,
 ];
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
@@ -478,14 +483,24 @@ impl VideoCodec {
   pub const Bgra8888: Self = Self(1);
   pub const Rgb24: Self = Self(2);
   pub const Jpeg: Self = Self(3);
+  pub const H264: Self = Self(4);
+  pub const Av1: Self = Self(5);
+  pub const ZstdDelta: Self = Self(6);
+  pub const Hybrid: Self = Self(7);
+  pub const Png: Self = Self(8);
 
   pub const ENUM_MIN: i8 = 0;
-  pub const ENUM_MAX: i8 = 3;
+  pub const ENUM_MAX: i8 = 8;
   pub const ENUM_VALUES: &'static [Self] = &[
     Self::Null,
     Self::Bgra8888,
     Self::Rgb24,
     Self::Jpeg,
+    Self::H264,
+    Self::Av1,
+    Self::ZstdDelta,
+    Self::Hybrid,
+    Self::Png,
   ];
   /// Returns the variant's name or "" if unknown.
   pub fn variant_name(self) -> Option<&'static str> {
@@ -494,6 +509,11 @@ impl VideoCodec {
       Self::Bgra8888 => Some("Bgra8888"),
       Self::Rgb24 => Some("Rgb24"),
       Self::Jpeg => Some("Jpeg"),
+      Self::H264 => Some("H264"),
+      Self::Av1 => Some("Av1"),
+      Self::ZstdDelta => Some("ZstdDelta"),
+      Self::Hybrid => Some("Hybrid"),
+      Self::Png => Some("Png"),
       _ => None,
     }
   }
@@ -549,6 +569,95 @@ impl<'a> flatbuffers::Verifiable for VideoCodec {
 }
 
 impl flatbuffers::SimpleToVerifyInSlice for VideoCodec {}
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+pub const ENUM_MIN_CHROMA_SUBSAMPLING: i8 = 0;
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+pub const ENUM_MAX_CHROMA_SUBSAMPLING: i8 = 2;
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+#[allow(non_camel_case_types)]
+pub const ENUM_VALUES_CHROMA_SUBSAMPLING: [ChromaSubsampling; 3] = [
+  ChromaSubsampling::Unknown,
+  ChromaSubsampling::Yuv420,
+  ChromaSubsampling::Yuv444,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[repr(transparent)]
+pub struct ChromaSubsampling(pub i8);
+#[allow(non_upper_case_globals)]
+impl ChromaSubsampling {
+  pub const Unknown: Self = Self(0);
+  pub const Yuv420: Self = Self(1);
+  pub const Yuv444: Self = Self(2);
+
+  pub const ENUM_MIN: i8 = 0;
+  pub const ENUM_MAX: i8 = 2;
+  pub const ENUM_VALUES: &'static [Self] = &[
+    Self::Unknown,
+    Self::Yuv420,
+    Self::Yuv444,
+  ];
+  /// Returns the variant's name or "" if unknown.
+  pub fn variant_name(self) -> Option<&'static str> {
+    match self {
+      Self::Unknown => Some("Unknown"),
+      Self::Yuv420 => Some("Yuv420"),
+      Self::Yuv444 => Some("Yuv444"),
+      _ => None,
+    }
+  }
+}
+impl core::fmt::Debug for ChromaSubsampling {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+    if let Some(name) = self.variant_name() {
+      f.write_str(name)
+    } else {
+      f.write_fmt(format_args!("<UNKNOWN {:?}>", self.0))
+    }
+  }
+}
+impl<'a> flatbuffers::Follow<'a> for ChromaSubsampling {
+  type Inner = Self;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    let b = flatbuffers::read_scalar_at::<i8>(buf, loc);
+    Self(b)
+  }
+}
+
+impl flatbuffers::Push for ChromaSubsampling {
+    type Output = ChromaSubsampling;
+    #[inline]
+    unsafe fn push(&self, dst: &mut [u8], _written_len: usize) {
+        flatbuffers::emplace_scalar::<i8>(dst, self.0);
+    }
+}
+
+impl flatbuffers::EndianScalar for ChromaSubsampling {
+  type Scalar = i8;
+  #[inline]
+  fn to_little_endian(self) -> i8 {
+    self.0.to_le()
+  }
+  #[inline]
+  #[allow(clippy::wrong_self_convention)]
+  fn from_little_endian(v: i8) -> Self {
+    let b = i8::from_le(v);
+    Self(b)
+  }
+}
+
+impl<'a> flatbuffers::Verifiable for ChromaSubsampling {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    i8::run_verifier(v, pos)
+  }
+}
+
+impl flatbuffers::SimpleToVerifyInSlice for ChromaSubsampling {}
 // struct Size2u, aligned to 4
 #[repr(transparent)]
 #[derive(Clone, Copy, PartialEq)]
@@ -921,6 +1030,226 @@ impl<'a> Coord2f {
 
 }
 
+// struct VideoTile, aligned to 4
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq)]
+pub struct VideoTile(pub [u8; 20]);
+impl Default for VideoTile { 
+  fn default() -> Self { 
+    Self([0; 20])
+  }
+}
+impl core::fmt::Debug for VideoTile {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+    f.debug_struct("VideoTile")
+      .field("x", &self.x())
+      .field("y", &self.y())
+      .field("width", &self.width())
+      .field("height", &self.height())
+      .field("bytes", &self.bytes())
+      .finish()
+  }
+}
+
+impl flatbuffers::SimpleToVerifyInSlice for VideoTile {}
+impl<'a> flatbuffers::Follow<'a> for VideoTile {
+  type Inner = &'a VideoTile;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    <&'a VideoTile>::follow(buf, loc)
+  }
+}
+impl<'a> flatbuffers::Follow<'a> for &'a VideoTile {
+  type Inner = &'a VideoTile;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    flatbuffers::follow_cast_ref::<VideoTile>(buf, loc)
+  }
+}
+impl<'b> flatbuffers::Push for VideoTile {
+    type Output = VideoTile;
+    #[inline]
+    unsafe fn push(&self, dst: &mut [u8], _written_len: usize) {
+        let src = ::core::slice::from_raw_parts(self as *const VideoTile as *const u8, Self::size());
+        dst.copy_from_slice(src);
+    }
+}
+
+impl<'a> flatbuffers::Verifiable for VideoTile {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.in_buffer::<Self>(pos)
+  }
+}
+
+impl<'a> VideoTile {
+  #[allow(clippy::too_many_arguments)]
+  pub fn new(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    bytes: u32,
+  ) -> Self {
+    let mut s = Self([0; 20]);
+    s.set_x(x);
+    s.set_y(y);
+    s.set_width(width);
+    s.set_height(height);
+    s.set_bytes(bytes);
+    s
+  }
+
+  pub fn x(&self) -> u32 {
+    let mut mem = core::mem::MaybeUninit::<<u32 as EndianScalar>::Scalar>::uninit();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    EndianScalar::from_little_endian(unsafe {
+      core::ptr::copy_nonoverlapping(
+        self.0[0..].as_ptr(),
+        mem.as_mut_ptr() as *mut u8,
+        core::mem::size_of::<<u32 as EndianScalar>::Scalar>(),
+      );
+      mem.assume_init()
+    })
+  }
+
+  pub fn set_x(&mut self, x: u32) {
+    let x_le = x.to_little_endian();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    unsafe {
+      core::ptr::copy_nonoverlapping(
+        &x_le as *const _ as *const u8,
+        self.0[0..].as_mut_ptr(),
+        core::mem::size_of::<<u32 as EndianScalar>::Scalar>(),
+      );
+    }
+  }
+
+  pub fn y(&self) -> u32 {
+    let mut mem = core::mem::MaybeUninit::<<u32 as EndianScalar>::Scalar>::uninit();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    EndianScalar::from_little_endian(unsafe {
+      core::ptr::copy_nonoverlapping(
+        self.0[4..].as_ptr(),
+        mem.as_mut_ptr() as *mut u8,
+        core::mem::size_of::<<u32 as EndianScalar>::Scalar>(),
+      );
+      mem.assume_init()
+    })
+  }
+
+  pub fn set_y(&mut self, x: u32) {
+    let x_le = x.to_little_endian();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    unsafe {
+      core::ptr::copy_nonoverlapping(
+        &x_le as *const _ as *const u8,
+        self.0[4..].as_mut_ptr(),
+        core::mem::size_of::<<u32 as EndianScalar>::Scalar>(),
+      );
+    }
+  }
+
+  pub fn width(&self) -> u32 {
+    let mut mem = core::mem::MaybeUninit::<<u32 as EndianScalar>::Scalar>::uninit();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    EndianScalar::from_little_endian(unsafe {
+      core::ptr::copy_nonoverlapping(
+        self.0[8..].as_ptr(),
+        mem.as_mut_ptr() as *mut u8,
+        core::mem::size_of::<<u32 as EndianScalar>::Scalar>(),
+      );
+      mem.assume_init()
+    })
+  }
+
+  pub fn set_width(&mut self, x: u32) {
+    let x_le = x.to_little_endian();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    unsafe {
+      core::ptr::copy_nonoverlapping(
+        &x_le as *const _ as *const u8,
+        self.0[8..].as_mut_ptr(),
+        core::mem::size_of::<<u32 as EndianScalar>::Scalar>(),
+      );
+    }
+  }
+
+  pub fn height(&self) -> u32 {
+    let mut mem = core::mem::MaybeUninit::<<u32 as EndianScalar>::Scalar>::uninit();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    EndianScalar::from_little_endian(unsafe {
+      core::ptr::copy_nonoverlapping(
+        self.0[12..].as_ptr(),
+        mem.as_mut_ptr() as *mut u8,
+        core::mem::size_of::<<u32 as EndianScalar>::Scalar>(),
+      );
+      mem.assume_init()
+    })
+  }
+
+  pub fn set_height(&mut self, x: u32) {
+    let x_le = x.to_little_endian();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    unsafe {
+      core::ptr::copy_nonoverlapping(
+        &x_le as *const _ as *const u8,
+        self.0[12..].as_mut_ptr(),
+        core::mem::size_of::<<u32 as EndianScalar>::Scalar>(),
+      );
+    }
+  }
+
+  pub fn bytes(&self) -> u32 {
+    let mut mem = core::mem::MaybeUninit::<<u32 as EndianScalar>::Scalar>::uninit();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    EndianScalar::from_little_endian(unsafe {
+      core::ptr::copy_nonoverlapping(
+        self.0[16..].as_ptr(),
+        mem.as_mut_ptr() as *mut u8,
+        core::mem::size_of::<<u32 as EndianScalar>::Scalar>(),
+      );
+      mem.assume_init()
+    })
+  }
+
+  pub fn set_bytes(&mut self, x: u32) {
+    let x_le = x.to_little_endian();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    unsafe {
+      core::ptr::copy_nonoverlapping(
+        &x_le as *const _ as *const u8,
+        self.0[16..].as_mut_ptr(),
+        core::mem::size_of::<<u32 as EndianScalar>::Scalar>(),
+      );
+    }
+  }
+
+}
+
 pub enum NotifyVideoStartOffset {}
 #[derive(Copy, Clone, PartialEq)]
 
@@ -940,6 +1269,7 @@ impl<'a> NotifyVideoStart<'a> {
   pub const VT_STREAM: flatbuffers::VOffsetT = 4;
   pub const VT_RESOLUTION: flatbuffers::VOffsetT = 6;
   pub const VT_DESKTOP_CODEC: flatbuffers::VOffsetT = 8;
+  pub const VT_SCALE: flatbuffers::VOffsetT = 10;
 
   #[inline]
   pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
@@ -951,6 +1281,7 @@ impl<'a> NotifyVideoStart<'a> {
     args: &'args NotifyVideoStartArgs<'args>
   ) -> flatbuffers::WIPOffset<NotifyVideoStart<'bldr>> {
     let mut builder = NotifyVideoStartBuilder::new(_fbb);
+    builder.add_scale(args.scale);
     if let Some(x) = args.resolution { builder.add_resolution(x); }
     builder.add_stream(args.stream);
     builder.add_desktop_codec(args.desktop_codec);
@@ -979,8 +1310,15 @@ impl<'a> NotifyVideoStart<'a> {
     // which contains a valid value in this slot
     unsafe { self._tab.get::<VideoCodec>(NotifyVideoStart::VT_DESKTOP_CODEC, Some(VideoCodec::Null)).unwrap()}
   }
-}
-
+  #[inline]
+  pub fn scale(&self) -> f32 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<f32>(NotifyVideoStart::VT_SCALE, Some(1.0)).unwrap()}
+  }
+}
+
 impl flatbuffers::Verifiable for NotifyVideoStart<'_> {
   #[inline]
   fn run_verifier(
@@ -991,6 +1329,7 @@ impl flatbuffers::Verifiable for NotifyVideoStart<'_> {
      .visit_field::<u16>("stream", Self::VT_STREAM, false)?
      .visit_field::<Size2u>("resolution", Self::VT_RESOLUTION, false)?
      .visit_field::<VideoCodec>("desktop_codec", Self::VT_DESKTOP_CODEC, false)?
+     .visit_field::<f32>("scale", Self::VT_SCALE, false)?
      .finish();
     Ok(())
   }
@@ -999,6 +1338,7 @@ pub struct NotifyVideoStartArgs<'a> {
     pub stream: u16,
     pub resolution: Option<&'a Size2u>,
     pub desktop_codec: VideoCodec,
+    pub scale: f32,
 }
 impl<'a> Default for NotifyVideoStartArgs<'a> {
   #[inline]
@@ -1007,6 +1347,7 @@ impl<'a> Default for NotifyVideoStartArgs<'a> {
       stream: 0,
       resolution: None,
       desktop_codec: VideoCodec::Null,
+      scale: 1.0,
     }
   }
 }
@@ -1029,6 +1370,10 @@ impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> NotifyVideoStartBuilder<'a, 'b,
     self.fbb_.push_slot::<VideoCodec>(NotifyVideoStart::VT_DESKTOP_CODEC, desktop_codec, VideoCodec::Null);
   }
   #[inline]
+  pub fn add_scale(&mut self, scale: f32) {
+    self.fbb_.push_slot::<f32>(NotifyVideoStart::VT_SCALE, scale, 1.0);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> NotifyVideoStartBuilder<'a, 'b, A> {
     let start = _fbb.start_table();
     NotifyVideoStartBuilder {
@@ -1049,6 +1394,7 @@ impl core::fmt::Debug for NotifyVideoStart<'_> {
       ds.field("stream", &self.stream());
       ds.field("resolution", &self.resolution());
       ds.field("desktop_codec", &self.desktop_codec());
+      ds.field("scale", &self.scale());
       ds.finish()
   }
 }
@@ -1149,6 +1495,217 @@ impl core::fmt::Debug for NotifyVideoStop<'_> {
       ds.finish()
   }
 }
+pub enum RequestKeyframeOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct RequestKeyframe<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for RequestKeyframe<'a> {
+  type Inner = RequestKeyframe<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> RequestKeyframe<'a> {
+  pub const VT_STREAM: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    RequestKeyframe { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args RequestKeyframeArgs
+  ) -> flatbuffers::WIPOffset<RequestKeyframe<'bldr>> {
+    let mut builder = RequestKeyframeBuilder::new(_fbb);
+    builder.add_stream(args.stream);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn stream(&self) -> u16 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u16>(RequestKeyframe::VT_STREAM, Some(0)).unwrap()}
+  }
+}
+
+impl flatbuffers::Verifiable for RequestKeyframe<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<u16>("stream", Self::VT_STREAM, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct RequestKeyframeArgs {
+    pub stream: u16,
+}
+impl<'a> Default for RequestKeyframeArgs {
+  #[inline]
+  fn default() -> Self {
+    RequestKeyframeArgs {
+      stream: 0,
+    }
+  }
+}
+
+pub struct RequestKeyframeBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> RequestKeyframeBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_stream(&mut self, stream: u16) {
+    self.fbb_.push_slot::<u16>(RequestKeyframe::VT_STREAM, stream, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> RequestKeyframeBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    RequestKeyframeBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<RequestKeyframe<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for RequestKeyframe<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("RequestKeyframe");
+      ds.field("stream", &self.stream());
+      ds.finish()
+  }
+}
+pub enum AckVideoFrameOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct AckVideoFrame<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for AckVideoFrame<'a> {
+  type Inner = AckVideoFrame<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> AckVideoFrame<'a> {
+  pub const VT_STREAM: flatbuffers::VOffsetT = 4;
+  pub const VT_SEQUENCE: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    AckVideoFrame { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args AckVideoFrameArgs
+  ) -> flatbuffers::WIPOffset<AckVideoFrame<'bldr>> {
+    let mut builder = AckVideoFrameBuilder::new(_fbb);
+    builder.add_sequence(args.sequence);
+    builder.add_stream(args.stream);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn stream(&self) -> u16 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u16>(AckVideoFrame::VT_STREAM, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn sequence(&self) -> u32 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u32>(AckVideoFrame::VT_SEQUENCE, Some(0)).unwrap()}
+  }
+}
+
+impl flatbuffers::Verifiable for AckVideoFrame<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<u16>("stream", Self::VT_STREAM, false)?
+     .visit_field::<u32>("sequence", Self::VT_SEQUENCE, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct AckVideoFrameArgs {
+    pub stream: u16,
+    pub sequence: u32,
+}
+impl<'a> Default for AckVideoFrameArgs {
+  #[inline]
+  fn default() -> Self {
+    AckVideoFrameArgs {
+      stream: 0,
+      sequence: 0,
+    }
+  }
+}
+
+pub struct AckVideoFrameBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> AckVideoFrameBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_stream(&mut self, stream: u16) {
+    self.fbb_.push_slot::<u16>(AckVideoFrame::VT_STREAM, stream, 0);
+  }
+  #[inline]
+  pub fn add_sequence(&mut self, sequence: u32) {
+    self.fbb_.push_slot::<u32>(AckVideoFrame::VT_SEQUENCE, sequence, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> AckVideoFrameBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    AckVideoFrameBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<AckVideoFrame<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for AckVideoFrame<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("AckVideoFrame");
+      ds.field("stream", &self.stream());
+      ds.field("sequence", &self.sequence());
+      ds.finish()
+  }
+}
 pub enum VideoFrameOffset {}
 #[derive(Copy, Clone, PartialEq)]
 
@@ -1168,6 +1725,10 @@ impl<'a> VideoFrame<'a> {
   pub const VT_VIDEO_BYTES: flatbuffers::VOffsetT = 4;
   pub const VT_CURSOR_UPDATE: flatbuffers::VOffsetT = 6;
   pub const VT_TIMINGS: flatbuffers::VOffsetT = 8;
+  pub const VT_TILES: flatbuffers::VOffsetT = 10;
+  pub const VT_SEQUENCE: flatbuffers::VOffsetT = 12;
+  pub const VT_QUALITY: flatbuffers::VOffsetT = 14;
+  pub const VT_SUBSAMPLING: flatbuffers::VOffsetT = 16;
 
   #[inline]
   pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
@@ -1180,8 +1741,12 @@ impl<'a> VideoFrame<'a> {
   ) -> flatbuffers::WIPOffset<VideoFrame<'bldr>> {
     let mut builder = VideoFrameBuilder::new(_fbb);
     builder.add_video_bytes(args.video_bytes);
+    builder.add_sequence(args.sequence);
+    if let Some(x) = args.tiles { builder.add_tiles(x); }
     if let Some(x) = args.timings { builder.add_timings(x); }
     if let Some(x) = args.cursor_update { builder.add_cursor_update(x); }
+    builder.add_subsampling(args.subsampling);
+    builder.add_quality(args.quality);
     builder.finish()
   }
 
@@ -1207,6 +1772,34 @@ impl<'a> VideoFrame<'a> {
     // which contains a valid value in this slot
     unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<Timings>>(VideoFrame::VT_TIMINGS, None)}
   }
+  #[inline]
+  pub fn tiles(&self) -> Option<flatbuffers::Vector<'a, VideoTile>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, VideoTile>>>(VideoFrame::VT_TILES, None)}
+  }
+  #[inline]
+  pub fn sequence(&self) -> u32 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u32>(VideoFrame::VT_SEQUENCE, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn quality(&self) -> u8 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u8>(VideoFrame::VT_QUALITY, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn subsampling(&self) -> ChromaSubsampling {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<ChromaSubsampling>(VideoFrame::VT_SUBSAMPLING, Some(ChromaSubsampling::Unknown)).unwrap()}
+  }
 }
 
 impl flatbuffers::Verifiable for VideoFrame<'_> {
@@ -1219,6 +1812,10 @@ impl flatbuffers::Verifiable for VideoFrame<'_> {
      .visit_field::<u64>("video_bytes", Self::VT_VIDEO_BYTES, false)?
      .visit_field::<flatbuffers::ForwardsUOffset<CursorUpdate>>("cursor_update", Self::VT_CURSOR_UPDATE, false)?
      .visit_field::<flatbuffers::ForwardsUOffset<Timings>>("timings", Self::VT_TIMINGS, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, VideoTile>>>("tiles", Self::VT_TILES, false)?
+     .visit_field::<u32>("sequence", Self::VT_SEQUENCE, false)?
+     .visit_field::<u8>("quality", Self::VT_QUALITY, false)?
+     .visit_field::<ChromaSubsampling>("subsampling", Self::VT_SUBSAMPLING, false)?
      .finish();
     Ok(())
   }
@@ -1227,6 +1824,10 @@ pub struct VideoFrameArgs<'a> {
     pub video_bytes: u64,
     pub cursor_update: Option<flatbuffers::WIPOffset<CursorUpdate<'a>>>,
     pub timings: Option<flatbuffers::WIPOffset<Timings<'a>>>,
+    pub tiles: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, VideoTile>>>,
+    pub sequence: u32,
+    pub quality: u8,
+    pub subsampling: ChromaSubsampling,
 }
 impl<'a> Default for VideoFrameArgs<'a> {
   #[inline]
@@ -1235,6 +1836,10 @@ impl<'a> Default for VideoFrameArgs<'a> {
       video_bytes: 0,
       cursor_update: None,
       timings: None,
+      tiles: None,
+      sequence: 0,
+      quality: 0,
+      subsampling: ChromaSubsampling::Unknown,
     }
   }
 }
@@ -1257,6 +1862,22 @@ impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> VideoFrameBuilder<'a, 'b, A> {
     self.fbb_.push_slot_always::<flatbuffers::WIPOffset<Timings>>(VideoFrame::VT_TIMINGS, timings);
   }
   #[inline]
+  pub fn add_tiles(&mut self, tiles: flatbuffers::WIPOffset<flatbuffers::Vector<'b , VideoTile>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(VideoFrame::VT_TILES, tiles);
+  }
+  #[inline]
+  pub fn add_sequence(&mut self, sequence: u32) {
+    self.fbb_.push_slot::<u32>(VideoFrame::VT_SEQUENCE, sequence, 0);
+  }
+  #[inline]
+  pub fn add_quality(&mut self, quality: u8) {
+    self.fbb_.push_slot::<u8>(VideoFrame::VT_QUALITY, quality, 0);
+  }
+  #[inline]
+  pub fn add_subsampling(&mut self, subsampling: ChromaSubsampling) {
+    self.fbb_.push_slot::<ChromaSubsampling>(VideoFrame::VT_SUBSAMPLING, subsampling, ChromaSubsampling::Unknown);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> VideoFrameBuilder<'a, 'b, A> {
     let start = _fbb.start_table();
     VideoFrameBuilder {
@@ -1277,6 +1898,10 @@ impl core::fmt::Debug for VideoFrame<'_> {
       ds.field("video_bytes", &self.video_bytes());
       ds.field("cursor_update", &self.cursor_update());
       ds.field("timings", &self.timings());
+      ds.field("tiles", &self.tiles());
+      ds.field("sequence", &self.sequence());
+      ds.field("quality", &self.quality());
+      ds.field("subsampling", &self.subsampling());
       ds.finish()
   }
 }
@@ -1432,6 +2057,7 @@ impl<'a> CursorShape<'a> {
   pub const VT_XOR: flatbuffers::VOffsetT = 8;
   pub const VT_HOTSPOT: flatbuffers::VOffsetT = 10;
   pub const VT_RESOLUTION: flatbuffers::VOffsetT = 12;
+  pub const VT_ID: flatbuffers::VOffsetT = 14;
 
   #[inline]
   pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
@@ -1443,6 +2069,7 @@ impl<'a> CursorShape<'a> {
     args: &'args CursorShapeArgs<'args>
   ) -> flatbuffers::WIPOffset<CursorShape<'bldr>> {
     let mut builder = CursorShapeBuilder::new(_fbb);
+    builder.add_id(args.id);
     if let Some(x) = args.resolution { builder.add_resolution(x); }
     if let Some(x) = args.hotspot { builder.add_hotspot(x); }
     if let Some(x) = args.image { builder.add_image(x); }
@@ -1487,6 +2114,13 @@ impl<'a> CursorShape<'a> {
     // which contains a valid value in this slot
     unsafe { self._tab.get::<Size2u>(CursorShape::VT_RESOLUTION, None)}
   }
+  #[inline]
+  pub fn id(&self) -> u64 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u64>(CursorShape::VT_ID, Some(0)).unwrap()}
+  }
 }
 
 impl flatbuffers::Verifiable for CursorShape<'_> {
@@ -1501,6 +2135,7 @@ impl flatbuffers::Verifiable for CursorShape<'_> {
      .visit_field::<bool>("xor", Self::VT_XOR, false)?
      .visit_field::<Coord2f>("hotspot", Self::VT_HOTSPOT, false)?
      .visit_field::<Size2u>("resolution", Self::VT_RESOLUTION, false)?
+     .visit_field::<u64>("id", Self::VT_ID, false)?
      .finish();
     Ok(())
   }
@@ -1511,6 +2146,7 @@ pub struct CursorShapeArgs<'a> {
     pub xor: bool,
     pub hotspot: Option<&'a Coord2f>,
     pub resolution: Option<&'a Size2u>,
+    pub id: u64,
 }
 impl<'a> Default for CursorShapeArgs<'a> {
   #[inline]
@@ -1521,6 +2157,7 @@ impl<'a> Default for CursorShapeArgs<'a> {
       xor: false,
       hotspot: None,
       resolution: None,
+      id: 0,
     }
   }
 }
@@ -1551,6 +2188,10 @@ impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> CursorShapeBuilder<'a, 'b, A> {
     self.fbb_.push_slot_always::<&Size2u>(CursorShape::VT_RESOLUTION, resolution);
   }
   #[inline]
+  pub fn add_id(&mut self, id: u64) {
+    self.fbb_.push_slot::<u64>(CursorShape::VT_ID, id, 0);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> CursorShapeBuilder<'a, 'b, A> {
     let start = _fbb.start_table();
     CursorShapeBuilder {
@@ -1573,6 +2214,7 @@ impl core::fmt::Debug for CursorShape<'_> {
       ds.field("xor", &self.xor());
       ds.field("hotspot", &self.hotspot());
       ds.field("resolution", &self.resolution());
+      ds.field("id", &self.id());
       ds.finish()
   }
 }
@@ -1721,15 +2363,17 @@ pub mod control {
 #[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
 pub const ENUM_MIN_CONTROL_PACKET: u8 = 0;
 #[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
-pub const ENUM_MAX_CONTROL_PACKET: u8 = 4;
+pub const ENUM_MAX_CONTROL_PACKET: u8 = 6;
 #[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
 #[allow(non_camel_case_types)]
-pub const ENUM_VALUES_CONTROL_PACKET: [ControlPacket; 5] = [
+pub const ENUM_VALUES_CONTROL_PACKET: [ControlPacket; 7] = [
   ControlPacket::NONE,
   ControlPacket::video_NotifyVideoStart,
   ControlPacket::video_NotifyVideoStop,
   ControlPacket::audio_NotifyAudioStart,
   ControlPacket::audio_NotifyAudioStop,
+  ControlPacket::video_RequestKeyframe,
+  ControlPacket::video_AckVideoFrame,
 ];
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
@@ -1742,15 +2386,19 @@ impl ControlPacket {
   pub const video_NotifyVideoStop: Self = Self(2);
   pub const audio_NotifyAudioStart: Self = Self(3);
   pub const audio_NotifyAudioStop: Self = Self(4);
+  pub const video_RequestKeyframe: Self = Self(5);
+  pub const video_AckVideoFrame: Self = Self(6);
 
   pub const ENUM_MIN: u8 = 0;
-  pub const ENUM_MAX: u8 = 4;
+  pub const ENUM_MAX: u8 = 6;
   pub const ENUM_VALUES: &'static [Self] = &[
     Self::NONE,
     Self::video_NotifyVideoStart,
     Self::video_NotifyVideoStop,
     Self::audio_NotifyAudioStart,
     Self::audio_NotifyAudioStop,
+    Self::video_RequestKeyframe,
+    Self::video_AckVideoFrame,
   ];
   /// Returns the variant's name or "" if unknown.
   pub fn variant_name(self) -> Option<&'static str> {
@@ -1760,6 +2408,8 @@ impl ControlPacket {
       Self::video_NotifyVideoStop => Some("video_NotifyVideoStop"),
       Self::audio_NotifyAudioStart => Some("audio_NotifyAudioStart"),
       Self::audio_NotifyAudioStop => Some("audio_NotifyAudioStop"),
+      Self::video_RequestKeyframe => Some("video_RequestKeyframe"),
+      Self::video_AckVideoFrame => Some("video_AckVideoFrame"),
       _ => None,
     }
   }
@@ -1926,6 +2576,36 @@ impl<'a> ControlFrame<'a> {
     }
   }
 
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn data_as_video_request_keyframe(&self) -> Option<super::video::RequestKeyframe<'a>> {
+    if self.data_type() == ControlPacket::video_RequestKeyframe {
+      self.data().map(|t| {
+       // Safety:
+       // Created from a valid Table for this object
+       // Which contains a valid union in this slot
+       unsafe { super::video::RequestKeyframe::init_from_table(t) }
+     })
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn data_as_video_ack_video_frame(&self) -> Option<super::video::AckVideoFrame<'a>> {
+    if self.data_type() == ControlPacket::video_AckVideoFrame {
+      self.data().map(|t| {
+       // Safety:
+       // Created from a valid Table for this object
+       // Which contains a valid union in this slot
+       unsafe { super::video::AckVideoFrame::init_from_table(t) }
+     })
+    } else {
+      None
+    }
+  }
+
 }
 
 impl flatbuffers::Verifiable for ControlFrame<'_> {
@@ -1941,6 +2621,8 @@ impl flatbuffers::Verifiable for ControlFrame<'_> {
           ControlPacket::video_NotifyVideoStop => v.verify_union_variant::<flatbuffers::ForwardsUOffset<super::video::NotifyVideoStop>>("ControlPacket::video_NotifyVideoStop", pos),
           ControlPacket::audio_NotifyAudioStart => v.verify_union_variant::<flatbuffers::ForwardsUOffset<super::audio::NotifyAudioStart>>("ControlPacket::audio_NotifyAudioStart", pos),
           ControlPacket::audio_NotifyAudioStop => v.verify_union_variant::<flatbuffers::ForwardsUOffset<super::audio::NotifyAudioStop>>("ControlPacket::audio_NotifyAudioStop", pos),
+          ControlPacket::video_RequestKeyframe => v.verify_union_variant::<flatbuffers::ForwardsUOffset<super::video::RequestKeyframe>>("ControlPacket::video_RequestKeyframe", pos),
+          ControlPacket::video_AckVideoFrame => v.verify_union_variant::<flatbuffers::ForwardsUOffset<super::video::AckVideoFrame>>("ControlPacket::video_AckVideoFrame", pos),
           _ => Ok(()),
         }
      })?
@@ -2023,6 +2705,20 @@ impl core::fmt::Debug for ControlFrame<'_> {
             ds.field("data", &"InvalidFlatbuffer: Union discriminant does not match value.")
           }
         },
+        ControlPacket::video_RequestKeyframe => {
+          if let Some(x) = self.data_as_video_request_keyframe() {
+            ds.field("data", &x)
+          } else {
+            ds.field("data", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        ControlPacket::video_AckVideoFrame => {
+          if let Some(x) = self.data_as_video_ack_video_frame() {
+            ds.field("data", &x)
+          } else {
+            ds.field("data", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
         _ => {
           let x: Option<()> = None;
           ds.field("data", &x)
@@ -2033,3 +2729,863 @@ impl core::fmt::Debug for ControlFrame<'_> {
 }
 }  // pub mod control
 
+#[allow(unused_imports, dead_code)]
+pub mod input {
+
+  use core::mem;
+  use core::cmp::Ordering;
+
+  extern crate flatbuffers;
+  use self::flatbuffers::{EndianScalar, Follow};
+
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+pub const ENUM_MIN_MOUSE_BUTTON: i8 = 0;
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+pub const ENUM_MAX_MOUSE_BUTTON: i8 = 4;
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+#[allow(non_camel_case_types)]
+pub const ENUM_VALUES_MOUSE_BUTTON: [MouseButton; 5] = [
+  MouseButton::Left,
+  MouseButton::Right,
+  MouseButton::Middle,
+  MouseButton::Back,
+  MouseButton::Forward,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[repr(transparent)]
+pub struct MouseButton(pub i8);
+#[allow(non_upper_case_globals)]
+impl MouseButton {
+  pub const Left: Self = Self(0);
+  pub const Right: Self = Self(1);
+  pub const Middle: Self = Self(2);
+  pub const Back: Self = Self(3);
+  pub const Forward: Self = Self(4);
+
+  pub const ENUM_MIN: i8 = 0;
+  pub const ENUM_MAX: i8 = 4;
+  pub const ENUM_VALUES: &'static [Self] = &[
+    Self::Left,
+    Self::Right,
+    Self::Middle,
+    Self::Back,
+    Self::Forward,
+  ];
+  /// Returns the variant's name or "" if unknown.
+  pub fn variant_name(self) -> Option<&'static str> {
+    match self {
+      Self::Left => Some("Left"),
+      Self::Right => Some("Right"),
+      Self::Middle => Some("Middle"),
+      Self::Back => Some("Back"),
+      Self::Forward => Some("Forward"),
+      _ => None,
+    }
+  }
+}
+impl core::fmt::Debug for MouseButton {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+    if let Some(name) = self.variant_name() {
+      f.write_str(name)
+    } else {
+      f.write_fmt(format_args!("<UNKNOWN {:?}>", self.0))
+    }
+  }
+}
+impl<'a> flatbuffers::Follow<'a> for MouseButton {
+  type Inner = Self;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    let b = flatbuffers::read_scalar_at::<i8>(buf, loc);
+    Self(b)
+  }
+}
+
+impl flatbuffers::Push for MouseButton {
+    type Output = MouseButton;
+    #[inline]
+    unsafe fn push(&self, dst: &mut [u8], _written_len: usize) {
+        flatbuffers::emplace_scalar::<i8>(dst, self.0);
+    }
+}
+
+impl flatbuffers::EndianScalar for MouseButton {
+  type Scalar = i8;
+  #[inline]
+  fn to_little_endian(self) -> i8 {
+    self.0.to_le()
+  }
+  #[inline]
+  #[allow(clippy::wrong_self_convention)]
+  fn from_little_endian(v: i8) -> Self {
+    let b = i8::from_le(v);
+    Self(b)
+  }
+}
+
+impl<'a> flatbuffers::Verifiable for MouseButton {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    i8::run_verifier(v, pos)
+  }
+}
+
+impl flatbuffers::SimpleToVerifyInSlice for MouseButton {}
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+pub const ENUM_MIN_INPUT_PACKET: u8 = 0;
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+pub const ENUM_MAX_INPUT_PACKET: u8 = 4;
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+#[allow(non_camel_case_types)]
+pub const ENUM_VALUES_INPUT_PACKET: [InputPacket; 5] = [
+  InputPacket::NONE,
+  InputPacket::KeyEvent,
+  InputPacket::PointerMove,
+  InputPacket::PointerButton,
+  InputPacket::PointerWheel,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[repr(transparent)]
+pub struct InputPacket(pub u8);
+#[allow(non_upper_case_globals)]
+impl InputPacket {
+  pub const NONE: Self = Self(0);
+  pub const KeyEvent: Self = Self(1);
+  pub const PointerMove: Self = Self(2);
+  pub const PointerButton: Self = Self(3);
+  pub const PointerWheel: Self = Self(4);
+
+  pub const ENUM_MIN: u8 = 0;
+  pub const ENUM_MAX: u8 = 4;
+  pub const ENUM_VALUES: &'static [Self] = &[
+    Self::NONE,
+    Self::KeyEvent,
+    Self::PointerMove,
+    Self::PointerButton,
+    Self::PointerWheel,
+  ];
+  /// Returns the variant's name or "" if unknown.
+  pub fn variant_name(self) -> Option<&'static str> {
+    match self {
+      Self::NONE => Some("NONE"),
+      Self::KeyEvent => Some("KeyEvent"),
+      Self::PointerMove => Some("PointerMove"),
+      Self::PointerButton => Some("PointerButton"),
+      Self::PointerWheel => Some("PointerWheel"),
+      _ => None,
+    }
+  }
+}
+impl core::fmt::Debug for InputPacket {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+    if let Some(name) = self.variant_name() {
+      f.write_str(name)
+    } else {
+      f.write_fmt(format_args!("<UNKNOWN {:?}>", self.0))
+    }
+  }
+}
+impl<'a> flatbuffers::Follow<'a> for InputPacket {
+  type Inner = Self;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    let b = flatbuffers::read_scalar_at::<u8>(buf, loc);
+    Self(b)
+  }
+}
+
+impl flatbuffers::Push for InputPacket {
+    type Output = InputPacket;
+    #[inline]
+    unsafe fn push(&self, dst: &mut [u8], _written_len: usize) {
+        flatbuffers::emplace_scalar::<u8>(dst, self.0);
+    }
+}
+
+impl flatbuffers::EndianScalar for InputPacket {
+  type Scalar = u8;
+  #[inline]
+  fn to_little_endian(self) -> u8 {
+    self.0.to_le()
+  }
+  #[inline]
+  #[allow(clippy::wrong_self_convention)]
+  fn from_little_endian(v: u8) -> Self {
+    let b = u8::from_le(v);
+    Self(b)
+  }
+}
+
+impl<'a> flatbuffers::Verifiable for InputPacket {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    u8::run_verifier(v, pos)
+  }
+}
+
+impl flatbuffers::SimpleToVerifyInSlice for InputPacket {}
+pub struct InputPacketUnionTableOffset {}
+
+pub enum KeyEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct KeyEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for KeyEvent<'a> {
+  type Inner = KeyEvent<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> KeyEvent<'a> {
+  pub const VT_USAGE: flatbuffers::VOffsetT = 4;
+  pub const VT_PRESSED: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    KeyEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args KeyEventArgs
+  ) -> flatbuffers::WIPOffset<KeyEvent<'bldr>> {
+    let mut builder = KeyEventBuilder::new(_fbb);
+    builder.add_usage(args.usage);
+    builder.add_pressed(args.pressed);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn usage(&self) -> u16 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u16>(KeyEvent::VT_USAGE, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn pressed(&self) -> bool {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<bool>(KeyEvent::VT_PRESSED, Some(false)).unwrap()}
+  }
+}
+
+impl flatbuffers::Verifiable for KeyEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<u16>("usage", Self::VT_USAGE, false)?
+     .visit_field::<bool>("pressed", Self::VT_PRESSED, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct KeyEventArgs {
+    pub usage: u16,
+    pub pressed: bool,
+}
+impl<'a> Default for KeyEventArgs {
+  #[inline]
+  fn default() -> Self {
+    KeyEventArgs {
+      usage: 0,
+      pressed: false,
+    }
+  }
+}
+
+pub struct KeyEventBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> KeyEventBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_usage(&mut self, usage: u16) {
+    self.fbb_.push_slot::<u16>(KeyEvent::VT_USAGE, usage, 0);
+  }
+  #[inline]
+  pub fn add_pressed(&mut self, pressed: bool) {
+    self.fbb_.push_slot::<bool>(KeyEvent::VT_PRESSED, pressed, false);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> KeyEventBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    KeyEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<KeyEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for KeyEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("KeyEvent");
+      ds.field("usage", &self.usage());
+      ds.field("pressed", &self.pressed());
+      ds.finish()
+  }
+}
+pub enum PointerMoveOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct PointerMove<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for PointerMove<'a> {
+  type Inner = PointerMove<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> PointerMove<'a> {
+  pub const VT_POS: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    PointerMove { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args PointerMoveArgs<'args>
+  ) -> flatbuffers::WIPOffset<PointerMove<'bldr>> {
+    let mut builder = PointerMoveBuilder::new(_fbb);
+    if let Some(x) = args.pos { builder.add_pos(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn pos(&self) -> Option<&'a super::video::Coord2u> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<super::video::Coord2u>(PointerMove::VT_POS, None)}
+  }
+}
+
+impl flatbuffers::Verifiable for PointerMove<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<super::video::Coord2u>("pos", Self::VT_POS, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct PointerMoveArgs<'a> {
+    pub pos: Option<&'a super::video::Coord2u>,
+}
+impl<'a> Default for PointerMoveArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    PointerMoveArgs {
+      pos: None,
+    }
+  }
+}
+
+pub struct PointerMoveBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> PointerMoveBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_pos(&mut self, pos: &super::video::Coord2u) {
+    self.fbb_.push_slot_always::<&super::video::Coord2u>(PointerMove::VT_POS, pos);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> PointerMoveBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    PointerMoveBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<PointerMove<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for PointerMove<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("PointerMove");
+      ds.field("pos", &self.pos());
+      ds.finish()
+  }
+}
+pub enum PointerButtonOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct PointerButton<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for PointerButton<'a> {
+  type Inner = PointerButton<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> PointerButton<'a> {
+  pub const VT_BUTTON: flatbuffers::VOffsetT = 4;
+  pub const VT_PRESSED: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    PointerButton { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args PointerButtonArgs
+  ) -> flatbuffers::WIPOffset<PointerButton<'bldr>> {
+    let mut builder = PointerButtonBuilder::new(_fbb);
+    builder.add_pressed(args.pressed);
+    builder.add_button(args.button);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn button(&self) -> MouseButton {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<MouseButton>(PointerButton::VT_BUTTON, Some(MouseButton::Left)).unwrap()}
+  }
+  #[inline]
+  pub fn pressed(&self) -> bool {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<bool>(PointerButton::VT_PRESSED, Some(false)).unwrap()}
+  }
+}
+
+impl flatbuffers::Verifiable for PointerButton<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<MouseButton>("button", Self::VT_BUTTON, false)?
+     .visit_field::<bool>("pressed", Self::VT_PRESSED, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct PointerButtonArgs {
+    pub button: MouseButton,
+    pub pressed: bool,
+}
+impl<'a> Default for PointerButtonArgs {
+  #[inline]
+  fn default() -> Self {
+    PointerButtonArgs {
+      button: MouseButton::Left,
+      pressed: false,
+    }
+  }
+}
+
+pub struct PointerButtonBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> PointerButtonBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_button(&mut self, button: MouseButton) {
+    self.fbb_.push_slot::<MouseButton>(PointerButton::VT_BUTTON, button, MouseButton::Left);
+  }
+  #[inline]
+  pub fn add_pressed(&mut self, pressed: bool) {
+    self.fbb_.push_slot::<bool>(PointerButton::VT_PRESSED, pressed, false);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> PointerButtonBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    PointerButtonBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<PointerButton<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for PointerButton<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("PointerButton");
+      ds.field("button", &self.button());
+      ds.field("pressed", &self.pressed());
+      ds.finish()
+  }
+}
+pub enum PointerWheelOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct PointerWheel<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for PointerWheel<'a> {
+  type Inner = PointerWheel<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> PointerWheel<'a> {
+  pub const VT_DELTA_X: flatbuffers::VOffsetT = 4;
+  pub const VT_DELTA_Y: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    PointerWheel { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args PointerWheelArgs
+  ) -> flatbuffers::WIPOffset<PointerWheel<'bldr>> {
+    let mut builder = PointerWheelBuilder::new(_fbb);
+    builder.add_delta_y(args.delta_y);
+    builder.add_delta_x(args.delta_x);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn delta_x(&self) -> i32 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<i32>(PointerWheel::VT_DELTA_X, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn delta_y(&self) -> i32 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<i32>(PointerWheel::VT_DELTA_Y, Some(0)).unwrap()}
+  }
+}
+
+impl flatbuffers::Verifiable for PointerWheel<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<i32>("delta_x", Self::VT_DELTA_X, false)?
+     .visit_field::<i32>("delta_y", Self::VT_DELTA_Y, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct PointerWheelArgs {
+    pub delta_x: i32,
+    pub delta_y: i32,
+}
+impl<'a> Default for PointerWheelArgs {
+  #[inline]
+  fn default() -> Self {
+    PointerWheelArgs {
+      delta_x: 0,
+      delta_y: 0,
+    }
+  }
+}
+
+pub struct PointerWheelBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> PointerWheelBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_delta_x(&mut self, delta_x: i32) {
+    self.fbb_.push_slot::<i32>(PointerWheel::VT_DELTA_X, delta_x, 0);
+  }
+  #[inline]
+  pub fn add_delta_y(&mut self, delta_y: i32) {
+    self.fbb_.push_slot::<i32>(PointerWheel::VT_DELTA_Y, delta_y, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> PointerWheelBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    PointerWheelBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<PointerWheel<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for PointerWheel<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("PointerWheel");
+      ds.field("delta_x", &self.delta_x());
+      ds.field("delta_y", &self.delta_y());
+      ds.finish()
+  }
+}
+pub enum InputFrameOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct InputFrame<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for InputFrame<'a> {
+  type Inner = InputFrame<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> InputFrame<'a> {
+  pub const VT_DATA_TYPE: flatbuffers::VOffsetT = 4;
+  pub const VT_DATA: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    InputFrame { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args InputFrameArgs
+  ) -> flatbuffers::WIPOffset<InputFrame<'bldr>> {
+    let mut builder = InputFrameBuilder::new(_fbb);
+    if let Some(x) = args.data { builder.add_data(x); }
+    builder.add_data_type(args.data_type);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn data_type(&self) -> InputPacket {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<InputPacket>(InputFrame::VT_DATA_TYPE, Some(InputPacket::NONE)).unwrap()}
+  }
+  #[inline]
+  pub fn data(&self) -> Option<flatbuffers::Table<'a>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Table<'a>>>(InputFrame::VT_DATA, None)}
+  }
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn data_as_key_event(&self) -> Option<KeyEvent<'a>> {
+    if self.data_type() == InputPacket::KeyEvent {
+      self.data().map(|t| {
+       // Safety:
+       // Created from a valid Table for this object
+       // Which contains a valid union in this slot
+       unsafe { KeyEvent::init_from_table(t) }
+     })
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn data_as_pointer_move(&self) -> Option<PointerMove<'a>> {
+    if self.data_type() == InputPacket::PointerMove {
+      self.data().map(|t| {
+       // Safety:
+       // Created from a valid Table for this object
+       // Which contains a valid union in this slot
+       unsafe { PointerMove::init_from_table(t) }
+     })
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn data_as_pointer_button(&self) -> Option<PointerButton<'a>> {
+    if self.data_type() == InputPacket::PointerButton {
+      self.data().map(|t| {
+       // Safety:
+       // Created from a valid Table for this object
+       // Which contains a valid union in this slot
+       unsafe { PointerButton::init_from_table(t) }
+     })
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn data_as_pointer_wheel(&self) -> Option<PointerWheel<'a>> {
+    if self.data_type() == InputPacket::PointerWheel {
+      self.data().map(|t| {
+       // Safety:
+       // Created from a valid Table for this object
+       // Which contains a valid union in this slot
+       unsafe { PointerWheel::init_from_table(t) }
+     })
+    } else {
+      None
+    }
+  }
+
+}
+
+impl flatbuffers::Verifiable for InputFrame<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_union::<InputPacket, _>("data_type", Self::VT_DATA_TYPE, "data", Self::VT_DATA, false, |key, v, pos| {
+        match key {
+          InputPacket::KeyEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<KeyEvent>>("InputPacket::KeyEvent", pos),
+          InputPacket::PointerMove => v.verify_union_variant::<flatbuffers::ForwardsUOffset<PointerMove>>("InputPacket::PointerMove", pos),
+          InputPacket::PointerButton => v.verify_union_variant::<flatbuffers::ForwardsUOffset<PointerButton>>("InputPacket::PointerButton", pos),
+          InputPacket::PointerWheel => v.verify_union_variant::<flatbuffers::ForwardsUOffset<PointerWheel>>("InputPacket::PointerWheel", pos),
+          _ => Ok(()),
+        }
+     })?
+     .finish();
+    Ok(())
+  }
+}
+pub struct InputFrameArgs {
+    pub data_type: InputPacket,
+    pub data: Option<flatbuffers::WIPOffset<flatbuffers::UnionWIPOffset>>,
+}
+impl<'a> Default for InputFrameArgs {
+  #[inline]
+  fn default() -> Self {
+    InputFrameArgs {
+      data_type: InputPacket::NONE,
+      data: None,
+    }
+  }
+}
+
+pub struct InputFrameBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> InputFrameBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_data_type(&mut self, data_type: InputPacket) {
+    self.fbb_.push_slot::<InputPacket>(InputFrame::VT_DATA_TYPE, data_type, InputPacket::NONE);
+  }
+  #[inline]
+  pub fn add_data(&mut self, data: flatbuffers::WIPOffset<flatbuffers::UnionWIPOffset>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(InputFrame::VT_DATA, data);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> InputFrameBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    InputFrameBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<InputFrame<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for InputFrame<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("InputFrame");
+      ds.field("data_type", &self.data_type());
+      match self.data_type() {
+        InputPacket::KeyEvent => {
+          if let Some(x) = self.data_as_key_event() {
+            ds.field("data", &x)
+          } else {
+            ds.field("data", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        InputPacket::PointerMove => {
+          if let Some(x) = self.data_as_pointer_move() {
+            ds.field("data", &x)
+          } else {
+            ds.field("data", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        InputPacket::PointerButton => {
+          if let Some(x) = self.data_as_pointer_button() {
+            ds.field("data", &x)
+          } else {
+            ds.field("data", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        InputPacket::PointerWheel => {
+          if let Some(x) = self.data_as_pointer_wheel() {
+            ds.field("data", &x)
+          } else {
+            ds.field("data", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        _ => {
+          let x: Option<()> = None;
+          ds.field("data", &x)
+        },
+      };
+      ds.finish()
+  }
+}
+}  // pub mod input
+
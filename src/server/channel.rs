@@ -1,9 +1,15 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use actix::Addr;
 use bytes::Bytes;
 use flatbuffers::{FlatBufferBuilder, WIPOffset};
 use futures_util::{stream::FuturesUnordered, StreamExt};
 use parking_lot::RwLock;
 use smallvec::SmallVec;
+use tokio::sync::{watch, Notify};
+
+use crate::network::CONTROL_CHANNEL;
+use crate::schema::control::ControlFrame;
 
 use super::web::{OutgoingMessage, WebsocketActor};
 
@@ -12,6 +18,11 @@ use super::web::{OutgoingMessage, WebsocketActor};
 pub struct Channel {
     pub ch: u16,
     clients: RwLock<SmallVec<[Addr<WebsocketActor>; 2]>>,
+    accept_input: AtomicBool,
+    input_monitor: RwLock<Option<String>>,
+    closed: watch::Sender<bool>,
+    keyframe: Notify,
+    acked: watch::Sender<Option<u32>>,
 }
 
 impl Channel {
@@ -19,12 +30,92 @@ impl Channel {
         Self {
             ch,
             clients: Default::default(),
+            accept_input: AtomicBool::new(false),
+            input_monitor: RwLock::new(None),
+            closed: watch::Sender::new(false),
+            keyframe: Notify::new(),
+            acked: watch::Sender::new(None),
+        }
+    }
+
+    /// Stop producing messages for this channel.
+    pub fn close(&self) {
+        self.closed.send_replace(true);
+    }
+
+    /// Resolves once `close` is called.
+    pub async fn closed(&self) {
+        let mut closed = self.closed.subscribe();
+
+        // never fails because self holds the sender
+        let _ = closed.wait_for(|x| *x).await;
+    }
+
+    /// Ask the producer of this channel to send a keyframe.
+    pub fn request_keyframe(&self) {
+        self.keyframe.notify_one();
+    }
+
+    /// Resolves once `request_keyframe` is called.
+    /// Requests made while nobody waits are not lost.
+    pub async fn keyframe_requested(&self) {
+        self.keyframe.notified().await;
+    }
+
+    /// Record that a client received every frame up to `sequence`.
+    pub fn ack(&self, sequence: u32) {
+        self.acked.send_replace(Some(sequence));
+    }
+
+    /// Latest sequence acknowledged by clients
+    pub fn subscribe_acks(&self) -> watch::Receiver<Option<u32>> {
+        self.acked.subscribe()
+    }
+
+    /// Allow clients to send input events through this channel.
+    pub fn set_accept_input(&self, accept: bool) {
+        self.accept_input.store(accept, Ordering::Relaxed);
+    }
+
+    pub fn accepts_input(&self) -> bool {
+        self.accept_input.load(Ordering::Relaxed)
+    }
+
+    /// Set the monitor that pointer positions of input events are relative to.
+    /// None means the primary monitor.
+    pub fn set_input_monitor(&self, monitor: Option<String>) {
+        *self.input_monitor.write() = monitor;
+    }
+
+    pub fn input_monitor(&self) -> Option<String> {
+        self.input_monitor.read().clone()
+    }
+
+    /// Clients whose connection is closed are dropped along the way.
+    /// Returns false if `addr` was already a client.
+    pub fn add_client(&self, addr: Addr<WebsocketActor>) -> bool {
+        let mut clients = self.clients.write();
+        clients.retain(|x| x.connected());
+
+        if clients.contains(&addr) {
+            return false;
         }
+        clients.push(addr);
+        true
     }
 
-    pub fn add_client(&self, addr: Addr<WebsocketActor>) {
-        //TODO: Remove closed actors
-        self.clients.write().push(addr);
+    /// Stop sending messages to `addr`, e.g. once its connection is closed.
+    /// Closes this channel when its last client leaves.
+    pub fn remove_client(&self, addr: &Addr<WebsocketActor>) {
+        let mut clients = self.clients.write();
+        let before = clients.len();
+        clients.retain(|x| x != addr && x.connected());
+
+        if clients.len() < before && clients.is_empty() {
+            drop(clients);
+            log::debug!("Last client left channel {}", self.ch);
+            self.close();
+        }
     }
 
     async fn send_bytes(&self, msg: Bytes) {
@@ -47,22 +138,30 @@ impl Channel {
         builder: &mut FlatBufferBuilder<'builder>,
         f: impl FnOnce(&mut FlatBufferBuilder<'builder>) -> WIPOffset<T>,
     ) {
-        builder.reset();
-
-        let msg = f(builder);
-        builder.finish_size_prefixed(msg, None);
-
-        let packet = builder.finished_data();
+        self.send_msg_on(self.ch, builder, &[], f).await;
+    }
 
-        let mut buf = Vec::with_capacity(2 + packet.len());
-        buf.extend_from_slice(&self.ch.to_le_bytes());
-        buf.extend_from_slice(packet);
+    pub async fn send_msg_payload_with<'builder, T>(
+        &self,
+        builder: &mut FlatBufferBuilder<'builder>,
+        payload: &[u8],
+        f: impl FnOnce(&mut FlatBufferBuilder<'builder>) -> WIPOffset<T>,
+    ) {
+        self.send_msg_on(self.ch, builder, payload, f).await;
+    }
 
-        self.send_bytes(buf.into()).await;
+    /// Send `ControlFrame` to the clients of this channel, through the control channel.
+    pub async fn send_control_with<'builder>(
+        &self,
+        builder: &mut FlatBufferBuilder<'builder>,
+        f: impl FnOnce(&mut FlatBufferBuilder<'builder>) -> WIPOffset<ControlFrame<'builder>>,
+    ) {
+        self.send_msg_on(CONTROL_CHANNEL, builder, &[], f).await;
     }
 
-    pub async fn send_msg_payload_with<'builder, T>(
+    async fn send_msg_on<'builder, T>(
         &self,
+        ch: u16,
         builder: &mut FlatBufferBuilder<'builder>,
         payload: &[u8],
         f: impl FnOnce(&mut FlatBufferBuilder<'builder>) -> WIPOffset<T>,
@@ -75,7 +174,7 @@ impl Channel {
         let packet = builder.finished_data();
 
         let mut buf = Vec::with_capacity(2 + packet.len() + payload.len());
-        buf.extend_from_slice(&self.ch.to_le_bytes());
+        buf.extend_from_slice(&ch.to_le_bytes());
         buf.extend_from_slice(packet);
         buf.extend_from_slice(payload);
 
@@ -0,0 +1,136 @@
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::password_hash::{rand_core::OsRng, SaltString};
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use rand::{thread_rng, RngCore};
+use rustc_hash::FxHashMap;
+use sha2::{Digest, Sha256};
+
+use crate::network::dto::auth::AuthType;
+
+use super::AuthConfig;
+
+/// Hash a password for `PasswordCredential`.
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow!("unable to hash password: {e}"))?;
+
+    Ok(hash.to_string())
+}
+
+/// Hash a pre-shared token for `AuthConfig::token`.
+/// Tokens are random, so a fast hash is enough.
+pub fn hash_token(token: &str) -> String {
+    format!("sha256:{}", hex::encode(Sha256::digest(token.as_bytes())))
+}
+
+/// Generate a random token suitable for pre-shared token auth.
+pub fn generate_token() -> String {
+    let mut token = [0u8; 24];
+    thread_rng().fill_bytes(&mut token);
+    hex::encode(token)
+}
+
+/// Credentials accepted by the server, validated from `AuthConfig`.
+#[derive(Debug)]
+pub struct Credentials {
+    username: bool,
+    password: FxHashMap<String, String>,
+    token: Vec<String>,
+}
+
+impl Credentials {
+    /// Errors name the offending field of the config.
+    pub fn new(config: &AuthConfig) -> Result<Self> {
+        let mut password = FxHashMap::default();
+        for (i, cred) in config.password.iter().enumerate() {
+            PasswordHash::new(&cred.hash)
+                .map_err(|e| anyhow!("auth.password[{i}].hash: invalid password hash ({e})"))?;
+
+            if password
+                .insert(cred.username.clone(), cred.hash.clone())
+                .is_some()
+            {
+                bail!(
+                    "auth.password[{i}].username: duplicate user {}",
+                    cred.username
+                );
+            }
+        }
+
+        let mut token = Vec::with_capacity(config.token.len());
+        for (i, hash) in config.token.iter().enumerate() {
+            let hex_part = hash
+                .strip_prefix("sha256:")
+                .with_context(|| format!("auth.token[{i}]: hash must start with sha256:"))?;
+
+            let mut decoded = [0u8; 32];
+            hex::decode_to_slice(hex_part, &mut decoded)
+                .with_context(|| format!("auth.token[{i}]: invalid hash"))?;
+
+            token.push(hash.to_ascii_lowercase());
+        }
+
+        Ok(Self {
+            username: config.username,
+            password,
+            token,
+        })
+    }
+
+    /// True if no client can authenticate.
+    pub fn is_empty(&self) -> bool {
+        !self.username && self.password.is_empty() && self.token.is_empty()
+    }
+
+    /// Accept the token in addition to configured ones.
+    pub fn add_token(&mut self, token: &str) {
+        self.token.push(hash_token(token));
+    }
+
+    /// Enabled auth types in the order of preference
+    pub fn types(&self) -> Vec<AuthType> {
+        let mut types = Vec::with_capacity(3);
+
+        if !self.password.is_empty() {
+            types.push(AuthType::Password);
+        }
+
+        if !self.token.is_empty() {
+            types.push(AuthType::Token);
+        }
+
+        if self.username {
+            types.push(AuthType::Username);
+        }
+
+        types
+    }
+
+    pub fn accepts_username(&self) -> bool {
+        self.username
+    }
+
+    /// This is slow on purpose. Do not call it from async context directly.
+    pub fn verify_password(&self, username: &str, password: &str) -> bool {
+        let hash = match self.password.get(username) {
+            Some(x) => x,
+            None => return false,
+        };
+
+        let hash = match PasswordHash::new(hash) {
+            Ok(x) => x,
+            Err(_) => return false,
+        };
+
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    }
+
+    pub fn verify_token(&self, token: &str) -> bool {
+        let hash = hash_token(token);
+        self.token.contains(&hash)
+    }
+}
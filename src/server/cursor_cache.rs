@@ -0,0 +1,113 @@
+use crate::image::encode_png;
+use crate::util::{CursorShape, CursorState, LruCache, CURSOR_CACHE_SIZE};
+
+/// Cursor shape ready to be sent, without the image if the client has it already
+#[derive(Debug)]
+pub struct PreparedShape {
+    pub id: u64,
+    pub png: Option<Vec<u8>>,
+    pub xor: bool,
+    pub hotspot_x: f32,
+    pub hotspot_y: f32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Cursor to be sent along with a frame
+#[derive(Debug)]
+pub struct PreparedCursor {
+    pub visible: bool,
+    pub pos_x: u32,
+    pub pos_y: u32,
+    pub shape: Option<PreparedShape>,
+}
+
+/// Tracks the cursor shapes a client has received, so that each is sent only once.
+///
+/// Mirrors the cache of the client: both are touched in the same order and are of the same size,
+/// so a shape is known to the client exactly while it is in here.
+#[derive(Debug)]
+pub struct SentCursorShapes {
+    sent: LruCache<u64, ()>,
+    /// Last cursor sent, with the shape last sent
+    current: Option<CursorState>,
+    /// Send the current shape along with the next frame
+    resend: bool,
+}
+
+impl SentCursorShapes {
+    pub fn new() -> Self {
+        SentCursorShapes {
+            sent: LruCache::new(CURSOR_CACHE_SIZE),
+            current: None,
+            resend: false,
+        }
+    }
+
+    /// Forget everything sent, as the client may have lost it.
+    /// The current shape is sent again with the next frame.
+    pub fn reset(&mut self) {
+        self.sent.clear();
+        self.resend = self.current.is_some();
+    }
+
+    /// The cursor to send along with a frame carrying `cursor`.
+    pub fn prepare(&mut self, cursor: Option<&CursorState>) -> Option<PreparedCursor> {
+        match cursor {
+            Some(cursor) => {
+                let shape = cursor
+                    .shape
+                    .clone()
+                    .or_else(|| self.current.as_mut()?.shape.take());
+                self.current = Some(CursorState {
+                    visible: cursor.visible,
+                    pos_x: cursor.pos_x,
+                    pos_y: cursor.pos_y,
+                    shape,
+                });
+            }
+            None if self.resend => {}
+            None => return None,
+        }
+
+        let current = self.current.as_ref()?;
+        let shape_changed = cursor.is_some_and(|x| x.shape.is_some());
+        let shape = match &current.shape {
+            Some(shape) if shape_changed || self.resend => prepare_shape(&mut self.sent, shape),
+            _ => None,
+        };
+        self.resend = false;
+
+        Some(PreparedCursor {
+            visible: current.visible,
+            pos_x: current.pos_x,
+            pos_y: current.pos_y,
+            shape,
+        })
+    }
+}
+
+/// With the image only if it is not in `sent`. None if the image cannot be encoded.
+fn prepare_shape(sent: &mut LruCache<u64, ()>, shape: &CursorShape) -> Option<PreparedShape> {
+    let id = shape.content_id();
+
+    let png = if sent.get(&id).is_some() {
+        None
+    } else {
+        let png = encode_png(shape.image.as_data_ref())
+            .map_err(|e| log::warn!("Failed to encode cursor shape: {e:?}"))
+            .ok()?;
+        sent.insert(id, ());
+        Some(png)
+    };
+
+    Some(PreparedShape {
+        id,
+        png,
+        xor: shape.xor,
+        hotspot_x: shape.hotspot_x,
+        hotspot_y: shape.hotspot_y,
+        width: shape.image.width,
+        height: shape.image.height,
+    })
+}
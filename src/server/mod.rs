@@ -1,11 +1,16 @@
 mod channel;
+mod credentials;
+mod cursor_cache;
 mod serve;
 mod server_config;
+mod server_launch_args;
 mod twilight_server;
 mod web;
 
 use channel::*;
 
+pub use credentials::{generate_token, hash_password, hash_token};
 pub use serve::serve;
 pub use server_config::*;
+pub use server_launch_args::ServerLaunchArgs;
 pub use twilight_server::*;
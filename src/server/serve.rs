@@ -1,9 +1,9 @@
 use anyhow::Result;
 
-use super::web::serve_web;
+use super::{web::serve_web, ServerConfig};
 
-pub async fn serve() -> Result<()> {
-    serve_web().await?;
+pub async fn serve(config: ServerConfig) -> Result<()> {
+    serve_web(config).await?;
 
     Ok(())
 }
@@ -1,37 +1,493 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, ensure, Context, Result};
 use serde::{Deserialize, Serialize};
 
+use crate::image::ScaleFilter;
+use crate::network::dto::video::{DesktopCodec, RefreshRate, Resolution};
+
+use super::credentials::Credentials;
+
 /// Server config common to all platforms
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ServerConfig {
+    /// Addresses to serve, each with or without TLS
+    pub listen: Option<Vec<ListenConfig>>,
+
+    /// Prefix of every HTTP endpoint. Use empty string to serve at the root.
+    pub base_path: Option<String>,
+
+    /// Falls back to the method of `fallback_defaults` if the default method fails to start
     pub desktop_capture_method: Option<DesktopCaptureMethod>,
+    pub video: VideoConfig,
+    pub synthetic: SyntheticCaptureConfig,
+    pub replay: ReplayCaptureConfig,
+    pub input_injection: Option<InputInjectionMethod>,
+    pub tls: TlsConfig,
+    pub auth: AuthConfig,
     pub windows: Win32ServerConfig,
 }
 
+/// File format of the server config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// JSON if the file extension is `.json`, otherwise TOML.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Toml,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Load and validate config file. Missing values are left as default.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("unable to read {}", path.display()))?;
+
+        let config = Self::parse(&content, ConfigFormat::from_path(path))
+            .and_then(|config| config.validate().map(|_| config))
+            .with_context(|| format!("invalid config {}", path.display()))?;
+
+        Ok(config)
+    }
+
+    /// Errors name the path to the offending field.
+    pub fn parse(content: &str, format: ConfigFormat) -> Result<Self> {
+        match format {
+            ConfigFormat::Toml => {
+                serde_path_to_error::deserialize(toml::Deserializer::new(content))
+                    .map_err(|e| anyhow!("{}: {}", e.path(), e.inner()))
+            }
+            ConfigFormat::Json => {
+                let mut de = serde_json::Deserializer::from_str(content);
+                serde_path_to_error::deserialize(&mut de)
+                    .map_err(|e| anyhow!("{}: {}", e.path(), e.inner()))
+            }
+        }
+    }
+
+    pub fn to_string(&self, format: ConfigFormat) -> Result<String> {
+        let output = match format {
+            ConfigFormat::Toml => toml::to_string_pretty(self)?,
+            ConfigFormat::Json => serde_json::to_string_pretty(self)?,
+        };
+
+        Ok(output)
+    }
+
+    /// Check values that parse but make no sense.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(base_path) = &self.base_path {
+            ensure!(
+                base_path.is_empty() || base_path.starts_with('/'),
+                "base_path: must be empty or start with a slash"
+            );
+        }
+
+        if let Some(listen) = &self.listen {
+            ensure!(!listen.is_empty(), "listen: must not be empty");
+        }
+
+        let Resolution { width, height } = self.synthetic.resolution;
+        ensure!(
+            width > 0 && height > 0 && width % 2 == 0 && height % 2 == 0,
+            "synthetic.resolution: must be a positive multiple of 2"
+        );
+
+        let RefreshRate { num, den } = self.synthetic.refresh_rate;
+        ensure!(
+            num > 0 && den > 0,
+            "synthetic.refresh_rate: must be positive"
+        );
+
+        if self.desktop_capture_method == Some(DesktopCaptureMethod::Replay) {
+            ensure!(
+                !self.replay.path.as_os_str().is_empty(),
+                "replay.path: must be set to use replay capture"
+            );
+        }
+
+        ensure!(
+            self.tls.cert_path.is_some() == self.tls.key_path.is_some(),
+            "tls: cert_path and key_path must be set together"
+        );
+
+        let JpegConfig {
+            min_quality,
+            max_quality,
+            ..
+        } = self.video.jpeg;
+        ensure!(
+            (1..=100).contains(&max_quality),
+            "video.jpeg.max_quality: must be between 1 and 100"
+        );
+        ensure!(
+            (1..=max_quality).contains(&min_quality),
+            "video.jpeg.min_quality: must be between 1 and max_quality"
+        );
+        if let Some(quality) = self.replay.record_jpeg_quality {
+            ensure!(
+                (1..=100).contains(&quality),
+                "replay.record_jpeg_quality: must be between 1 and 100"
+            );
+        }
+        ensure!(
+            self.video.pacing.max_fps > 0,
+            "video.pacing.max_fps: must be positive"
+        );
+        ensure!(
+            self.video.pacing.keepalive_ms > 0,
+            "video.pacing.keepalive_ms: must be positive"
+        );
+        ensure!(
+            self.video.h264.bitrate_kbps > 0,
+            "video.h264.bitrate_kbps: must be positive"
+        );
+        ensure!(
+            self.video.av1.bitrate_kbps > 0,
+            "video.av1.bitrate_kbps: must be positive"
+        );
+        ensure!(
+            self.video.av1.speed <= 10,
+            "video.av1.speed: must be between 0 and 10"
+        );
+        ensure!(
+            cfg!(feature = "av1") || self.video.codec != DesktopCodec::Av1,
+            "video.codec: Av1 requires the server built with `av1` feature"
+        );
+
+        Credentials::new(&self.auth)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListenConfig {
+    pub address: SocketAddr,
+    pub tls: bool,
+}
+
 /// Method to capture the desktop
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum DesktopCaptureMethod {
     // windows
     Dxgi,
     Gdi,
+
+    // all platforms
+    Synthetic,
+    Replay,
+}
+
+/// How the desktop is encoded before sending to clients
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoConfig {
+    pub codec: DesktopCodec,
+
+    /// Filter to downscale the desktop with, for clients requesting a smaller resolution
+    pub scale_filter: ScaleFilter,
+
+    pub pacing: PacingConfig,
+    pub jpeg: JpegConfig,
+    pub h264: H264Config,
+    pub av1: Av1Config,
+}
+
+/// Limits on how often frames are encoded.
+/// Clients may request a lower frame rate, and it never exceeds the refresh rate of the monitor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PacingConfig {
+    /// Most frames per second to encode
+    pub max_fps: u32,
+
+    /// Skip frames where neither the desktop nor the cursor changed
+    pub skip_unchanged: bool,
+
+    /// Most milliseconds between frames while the desktop is idle
+    pub keepalive_ms: u32,
+}
+
+impl Default for PacingConfig {
+    fn default() -> Self {
+        Self {
+            max_fps: 60,
+            skip_unchanged: true,
+            keepalive_ms: 1000,
+        }
+    }
+}
+
+/// Config for the JPEG encoder.
+/// Quality and subsampling adapt to the link within these bounds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JpegConfig {
+    /// Lowest quality from 1 to 100, used when the link cannot keep up.
+    /// Set to `max_quality` to disable adaptation.
+    pub min_quality: u8,
+
+    /// Highest quality from 1 to 100, used at start
+    pub max_quality: u8,
+
+    /// Use 4:4:4 chroma instead of 4:2:0 while the link keeps up at `max_quality`
+    pub yuv444: bool,
+}
+
+impl Default for JpegConfig {
+    fn default() -> Self {
+        Self {
+            min_quality: 40,
+            max_quality: 90,
+            yuv444: false,
+        }
+    }
+}
+
+/// Config for the H.264 encoder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct H264Config {
+    /// Target bitrate in kilobits per second
+    pub bitrate_kbps: u32,
+}
+
+impl Default for H264Config {
+    fn default() -> Self {
+        Self { bitrate_kbps: 8000 }
+    }
+}
+
+/// Config for the AV1 encoder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Av1Config {
+    /// Target bitrate in kilobits per second
+    pub bitrate_kbps: u32,
+
+    /// rav1e speed preset from 0 (slowest) to 10 (fastest)
+    pub speed: u8,
+}
+
+impl Default for Av1Config {
+    fn default() -> Self {
+        Self {
+            bitrate_kbps: 2000,
+            speed: 10,
+        }
+    }
+}
+
+/// Method to deliver input received from clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum InputInjectionMethod {
+    // windows
+    SendInput,
+
+    // all platforms
+    /// Ignore all input
+    Disabled,
+}
+
+/// Config for the synthetic capture backend, which generates frames instead of capturing them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyntheticCaptureConfig {
+    pub resolution: Resolution,
+    pub refresh_rate: RefreshRate,
+    pub pattern: SyntheticPattern,
+
+    /// Draw a box bouncing around the screen
+    pub moving_box: bool,
+
+    /// Move a generated cursor around the screen
+    pub fake_cursor: bool,
+}
+
+/// Background drawn by the synthetic capture backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyntheticPattern {
+    ColorBars,
+    Gradient,
+    Checkerboard,
+}
+
+impl Default for SyntheticCaptureConfig {
+    fn default() -> Self {
+        Self {
+            resolution: Resolution {
+                width: 1920,
+                height: 1080,
+            },
+            refresh_rate: RefreshRate { num: 60, den: 1 },
+            pattern: SyntheticPattern::ColorBars,
+            moving_box: true,
+            fake_cursor: true,
+        }
+    }
+}
+
+/// Config for the replay capture backend, which streams frames from a recorded file,
+/// and for recording such files.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplayCaptureConfig {
+    pub path: PathBuf,
+    pub pace: ReplayPace,
+
+    /// Restart from the beginning upon reaching end of file
+    pub looping: bool,
+
+    /// Record the desktop of every stream into a replay file in this directory,
+    /// as captured before any downscaling
+    pub record_dir: Option<PathBuf>,
+
+    /// Store recorded frames as JPEG of this quality instead of uncompressed
+    pub record_jpeg_quality: Option<u8>,
+}
+
+/// How fast the replay capture backend emits frames
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReplayPace {
+    /// Follow timestamps in the recording
+    #[default]
+    Recorded,
+
+    /// Emit frames as fast as the next stage accepts them
+    AsFastAsPossible,
+}
+
+/// Config for serving over TLS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// PEM encoded certificate chain.
+    /// If the file does not exist, a self-signed certificate is generated and saved here.
+    /// If both paths are unset, a self-signed certificate is generated on every launch.
+    pub cert_path: Option<PathBuf>,
+
+    /// PEM encoded private key for `cert_path`
+    pub key_path: Option<PathBuf>,
+}
+
+impl Default for TlsConfig {
+    /// Keeps the generated certificate in the local data directory,
+    /// so clients that pinned it can reconnect after restart.
+    fn default() -> Self {
+        let dir = dirs::data_local_dir().map(|x| x.join("twilight"));
+
+        Self {
+            cert_path: dir.as_ref().map(|x| x.join("server-cert.pem")),
+            key_path: dir.as_ref().map(|x| x.join("server-key.pem")),
+        }
+    }
+}
+
+/// Credentials accepted from clients
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Accept anyone who sends a username. Insecure, only for debugging.
+    pub username: bool,
+
+    /// Users who may log in with a password
+    pub password: Vec<PasswordCredential>,
+
+    /// Pre-shared tokens hashed by `hash_token`.
+    /// If no credential is configured at all, a random token is generated on every launch.
+    pub token: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordCredential {
+    pub username: String,
+
+    /// PHC string produced by `hash_password`
+    pub hash: String,
 }
 
 /// Server confg specific to Windows
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Win32ServerConfig {}
 
+/// TLS and cleartext on localhost, using the default ports of twilight and twilightc scheme.
+fn default_listen() -> Vec<ListenConfig> {
+    vec![
+        ListenConfig {
+            address: (Ipv4Addr::LOCALHOST, 1517).into(),
+            tls: true,
+        },
+        ListenConfig {
+            address: (Ipv4Addr::LOCALHOST, 1518).into(),
+            tls: false,
+        },
+    ]
+}
+
 /// "Normal" default values for current platform.
 pub fn normal_defaults() -> ServerConfig {
+    let desktop_capture_method = if cfg!(windows) {
+        DesktopCaptureMethod::Dxgi
+    } else {
+        DesktopCaptureMethod::Synthetic
+    };
+    let input_injection = if cfg!(windows) {
+        InputInjectionMethod::SendInput
+    } else {
+        InputInjectionMethod::Disabled
+    };
+
     ServerConfig {
-        desktop_capture_method: Some(DesktopCaptureMethod::Dxgi),
+        listen: Some(default_listen()),
+        base_path: Some("/twilight".into()),
+        desktop_capture_method: Some(desktop_capture_method),
+        video: Default::default(),
+        synthetic: Default::default(),
+        replay: Default::default(),
+        input_injection: Some(input_injection),
+        tls: Default::default(),
+        auth: Default::default(),
         windows: Win32ServerConfig {},
     }
 }
 
 /// "Fallback" default values for current platform.
 pub fn fallback_defaults() -> ServerConfig {
+    let desktop_capture_method = if cfg!(windows) {
+        DesktopCaptureMethod::Gdi
+    } else {
+        DesktopCaptureMethod::Synthetic
+    };
+    let input_injection = if cfg!(windows) {
+        InputInjectionMethod::SendInput
+    } else {
+        InputInjectionMethod::Disabled
+    };
+
     ServerConfig {
-        desktop_capture_method: Some(DesktopCaptureMethod::Gdi),
+        listen: Some(default_listen()),
+        base_path: Some("/twilight".into()),
+        desktop_capture_method: Some(desktop_capture_method),
+        video: Default::default(),
+        synthetic: Default::default(),
+        replay: Default::default(),
+        input_injection: Some(input_injection),
+        tls: Default::default(),
+        auth: Default::default(),
         windows: Win32ServerConfig {},
     }
 }
@@ -0,0 +1,67 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use super::{ConfigFormat, ListenConfig, ServerConfig};
+
+const ABOUT: &str = "Server of Twilight Remote Desktop. The form of \
+arguments may change at any time during the alpha version.";
+
+#[derive(Parser, Debug, Clone)]
+#[command(version, about = ABOUT, long_about = None)]
+pub struct ServerLaunchArgs {
+    /// Config file in TOML format, or JSON if the extension is .json
+    #[clap(long)]
+    pub config: Option<PathBuf>,
+
+    /// Serve cleartext on the address. Example: [::]:1518
+    ///
+    /// May be repeated. Replaces the addresses in the config if given.
+    #[clap(long)]
+    pub listen: Vec<SocketAddr>,
+
+    /// Serve TLS on the address. Example: 0.0.0.0:1517
+    ///
+    /// May be repeated. Replaces the addresses in the config if given.
+    #[clap(long)]
+    pub listen_tls: Vec<SocketAddr>,
+
+    /// Prefix of every HTTP endpoint. Example: /remote
+    #[clap(long)]
+    pub base_path: Option<String>,
+
+    /// Read a password from the terminal, print its hash for the config and exit.
+    #[clap(long)]
+    pub hash_password: bool,
+
+    /// Print a random pre-shared token and its hash for the config and exit.
+    #[clap(long)]
+    pub generate_token: bool,
+
+    /// Print the default config and exit.
+    #[clap(long, value_enum, num_args = 0..=1, default_missing_value = "toml")]
+    pub print_default_config: Option<ConfigFormat>,
+}
+
+impl ServerLaunchArgs {
+    /// Override the config with values given in command line
+    pub fn apply(&self, config: &mut ServerConfig) {
+        if !self.listen.is_empty() || !self.listen_tls.is_empty() {
+            let cleartext = self.listen.iter().map(|&address| ListenConfig {
+                address,
+                tls: false,
+            });
+            let tls = self
+                .listen_tls
+                .iter()
+                .map(|&address| ListenConfig { address, tls: true });
+
+            config.listen = Some(tls.chain(cleartext).collect());
+        }
+
+        if let Some(base_path) = &self.base_path {
+            config.base_path = Some(base_path.clone());
+        }
+    }
+}
@@ -3,13 +3,39 @@ use flatbuffers::FlatBufferBuilder;
 use parking_lot::RwLock;
 
 use std::{
+    collections::VecDeque,
     mem::MaybeUninit,
     sync::{Arc, Weak},
+    time::Instant,
 };
 
-use crate::{schema::video::*, util::DesktopUpdate, video::capture_pipeline};
+use crate::{
+    input::{
+        injector::{create_injector, InputInjector},
+        InputEvent,
+    },
+    network::{
+        dto::video::{DesktopCodec, MonitorInfo, Resolution},
+        CONTROL_CHANNEL,
+    },
+    schema::{
+        control::{ControlFrame, ControlFrameArgs, ControlPacket},
+        video::*,
+    },
+    util::DesktopUpdate,
+    video::{
+        capture_factory, capture_pipeline,
+        encoder::{DeliveryReport, EncodedFrame},
+    },
+};
+
+use super::{
+    cursor_cache::{PreparedCursor, SentCursorShapes},
+    normal_defaults, Channel, ServerConfig,
+};
 
-use super::{Channel, ServerConfig};
+/// Frames awaiting acknowledgement are forgotten beyond this
+const MAX_IN_FLIGHT_TRACKED: usize = 256;
 
 /// The type that's carried around
 pub type SharedTwilightServer = RwLock<TwilightServer>;
@@ -20,44 +46,187 @@ pub struct TwilightServer {
     config: ServerConfig,
     channels: Box<[Weak<Channel>; u16::MAX as usize]>,
     next_channel: u16,
+    injector: Arc<dyn InputInjector>,
 }
 
 impl TwilightServer {
     pub fn new(config: ServerConfig) -> SharedTwilightServer {
+        let method = config
+            .input_injection
+            .unwrap_or_else(|| normal_defaults().input_injection.unwrap());
+        let injector = create_injector(method);
+
+        Self::with_injector(config, injector)
+    }
+
+    /// Create a server that delivers input to the given injector.
+    pub fn with_injector(
+        config: ServerConfig,
+        injector: Arc<dyn InputInjector>,
+    ) -> SharedTwilightServer {
         RwLock::new(Self {
             config,
             channels: boxed_array_of_weak(),
-            next_channel: 0,
+            next_channel: CONTROL_CHANNEL + 1,
+            injector,
         })
     }
 
     /// This function is called from async context. Never perform too much work.
-    pub fn recv_message(&self, msg: &[u8]) {
-        println!("unexpected message received from {msg:?}");
+    pub fn recv_message(&self, channel: &Channel, msg: &[u8]) {
+        if !channel.accepts_input() {
+            log::warn!(
+                "Ignoring message for channel {} which does not accept input",
+                channel.ch
+            );
+            return;
+        }
+
+        let event = match InputEvent::parse(msg) {
+            Ok(x) => x,
+            Err(e) => {
+                log::warn!("Ignoring invalid input message: {e:?}");
+                return;
+            }
+        };
+
+        let monitor = channel.input_monitor();
+        if let Err(e) = self.injector.inject(&event, monitor.as_deref()) {
+            log::error!("Failed to inject {event:?}: {e:?}");
+        }
+    }
+
+    /// List monitors available to `subscribe_desktop`.
+    pub fn list_monitors(&self) -> Result<Vec<MonitorInfo>> {
+        capture_factory(&self.config)?.list()
+    }
+
+    /// True if `id` is one of the monitors listed by `list_monitors`.
+    pub fn has_monitor(&self, id: &str) -> Result<bool> {
+        Ok(self.list_monitors()?.iter().any(|x| x.id == id))
     }
 
-    pub fn subscribe_desktop(&mut self, monitor: &str, channel: Arc<Channel>) -> Result<()> {
+    /// Encodes with `codec` if given, or the codec of the config otherwise.
+    /// Downscales the desktop to fit in `target_resolution` if given.
+    /// Sends at most `max_fps` frames per second if given, within the limit of the config.
+    ///
+    /// Capture and encoding stop once `channel` is closed, e.g. when its last client leaves.
+    pub fn subscribe_desktop(
+        &mut self,
+        monitor: &str,
+        channel: Arc<Channel>,
+        codec: Option<DesktopCodec>,
+        target_resolution: Option<&Resolution>,
+        max_fps: Option<u32>,
+    ) -> Result<()> {
         println!("subscribe to desktop on monitor {monitor}");
 
-        let (_, output) = capture_pipeline(&self.config)?;
+        let output = capture_pipeline(&self.config, monitor, codec, target_resolution, max_fps)?;
 
         tokio::spawn(async move {
             let mut builder = FlatBufferBuilder::with_capacity(8192);
 
+            // codec, resolution and scale last announced to the clients
+            let mut announced: Option<(VideoCodec, Resolution, f32)> = None;
+
+            let mut cursor_shapes = SentCursorShapes::new();
+
+            let mut acks = channel.subscribe_acks();
+            let mut sequence: u32 = 0;
+
+            // sequence, size and time of frames not acknowledged yet
+            let mut in_flight: VecDeque<(u32, usize, Instant)> = VecDeque::new();
+
             loop {
-                let update = match output.recv_async().await {
-                    Ok(x) => x,
-                    Err(_) => break,
+                let update = tokio::select! {
+                    _ = channel.closed() => break,
+                    _ = channel.keyframe_requested() => {
+                        log::debug!("Keyframe requested on channel {}", channel.ch);
+                        output.encoder.request_keyframe();
+                        // also sent when the client misses a cursor shape
+                        cursor_shapes.reset();
+                        continue;
+                    }
+                    Ok(()) = acks.changed() => {
+                        let acked = match *acks.borrow_and_update() {
+                            Some(x) => x,
+                            None => continue,
+                        };
+
+                        while let Some(&(seq, bytes, sent_at)) = in_flight.front() {
+                            if seq > acked {
+                                break;
+                            }
+
+                            in_flight.pop_front();
+                            let now = Instant::now();
+                            output.encoder.report_delivery(&DeliveryReport {
+                                bytes,
+                                rtt: now - sent_at,
+                                acked_at: now,
+                                in_flight: in_flight.len() as u32,
+                            });
+                        }
+                        continue;
+                    }
+                    x = output.rx.recv_async() => match x {
+                        Ok(x) => x,
+                        Err(_) => break,
+                    },
                 };
 
-                match send_desktop_update(&channel, &mut builder, &update).await {
-                    Ok(_) => {}
+                let frame = &update.desktop;
+                let format = (frame.codec, frame.resolution.clone(), update.scale);
+                if announced.as_ref() != Some(&format) {
+                    log::info!(
+                        "Announcing video on channel {} ({}x{} {:?} scale={})",
+                        channel.ch,
+                        frame.resolution.width,
+                        frame.resolution.height,
+                        frame.codec,
+                        update.scale
+                    );
+                    send_video_start(
+                        &channel,
+                        &mut builder,
+                        &frame.resolution,
+                        frame.codec,
+                        update.scale,
+                    )
+                    .await;
+                    announced = Some(format);
+                }
+
+                let sent_at = Instant::now();
+                let cursor = cursor_shapes.prepare(update.cursor.as_ref());
+                match send_desktop_update(&channel, &mut builder, &update, cursor, sequence).await {
+                    Ok(_) => {
+                        // clients which never acknowledge must not grow it forever
+                        if in_flight.len() >= MAX_IN_FLIGHT_TRACKED {
+                            in_flight.pop_front();
+                        }
+                        in_flight.push_back((sequence, update.desktop.data.len(), sent_at));
+                        sequence = sequence.wrapping_add(1);
+                    }
                     Err(e) => {
                         log::error!("unexpected error whild sending message: {}", e);
                         break;
                     }
                 }
             }
+
+            if announced.is_some() {
+                send_video_stop(&channel, &mut builder).await;
+            }
+
+            // joining the threads of the stages blocks until they finish their current frame
+            if let Err(e) = tokio::task::spawn_blocking(move || output.shutdown()).await {
+                log::error!(
+                    "Failed to stop desktop capture on channel {}: {e:?}",
+                    channel.ch
+                );
+            }
+            log::debug!("Stopped desktop capture on channel {}", channel.ch);
         });
 
         Ok(())
@@ -68,6 +237,10 @@ impl TwilightServer {
             let ch = self.next_channel;
             self.next_channel = ch.wrapping_add(1);
 
+            if ch == CONTROL_CHANNEL {
+                continue;
+            }
+
             if let Some(x) = self.channels[ch as usize].upgrade() {
                 assert_eq!(ch, x.ch, "channel number has modified");
                 continue;
@@ -99,24 +272,71 @@ fn boxed_array_of_weak<T: Sized, const LEN: usize>() -> Box<[Weak<T>; LEN]> {
     unsafe { std::mem::transmute::<_, Box<[Weak<T>; LEN]>>(boxed) }
 }
 
+async fn send_video_start(
+    ch: &Channel,
+    builder: &mut FlatBufferBuilder<'_>,
+    resolution: &Resolution,
+    codec: VideoCodec,
+    scale: f32,
+) {
+    ch.send_control_with(builder, |builder| {
+        let data = NotifyVideoStart::create(
+            builder,
+            &NotifyVideoStartArgs {
+                stream: ch.ch,
+                resolution: Some(&Size2u::new(resolution.width, resolution.height)),
+                desktop_codec: codec,
+                scale,
+            },
+        );
+
+        ControlFrame::create(
+            builder,
+            &ControlFrameArgs {
+                data_type: ControlPacket::video_NotifyVideoStart,
+                data: Some(data.as_union_value()),
+            },
+        )
+    })
+    .await;
+}
+
+async fn send_video_stop(ch: &Channel, builder: &mut FlatBufferBuilder<'_>) {
+    ch.send_control_with(builder, |builder| {
+        let data = NotifyVideoStop::create(builder, &NotifyVideoStopArgs { stream: ch.ch });
+
+        ControlFrame::create(
+            builder,
+            &ControlFrameArgs {
+                data_type: ControlPacket::video_NotifyVideoStop,
+                data: Some(data.as_union_value()),
+            },
+        )
+    })
+    .await;
+}
+
 async fn send_desktop_update(
     ch: &Channel,
     builder: &mut FlatBufferBuilder<'_>,
-    update: &DesktopUpdate<Vec<u8>>,
+    update: &DesktopUpdate<EncodedFrame>,
+    cursor: Option<PreparedCursor>,
+    sequence: u32,
 ) -> Result<()> {
-    ch.send_msg_payload_with(builder, &update.desktop, |builder| {
-        let cursor_update = update.cursor.as_ref().map(|cursor| {
+    ch.send_msg_payload_with(builder, &update.desktop.data, |builder| {
+        let cursor_update = cursor.as_ref().map(|cursor| {
             let shape = cursor.shape.as_ref().map(|shape| {
-                let image = builder.create_vector(&shape.image.data);
+                let image = shape.png.as_ref().map(|x| builder.create_vector(x));
 
                 CursorShape::create(
                     builder,
                     &CursorShapeArgs {
-                        image: Some(image),
-                        codec: VideoCodec::Jpeg,
+                        image,
+                        codec: VideoCodec::Png,
                         xor: shape.xor,
                         hotspot: Some(&Coord2f::new(shape.hotspot_x, shape.hotspot_y)),
-                        resolution: Some(&Size2u::new(shape.image.width, shape.image.height)),
+                        resolution: Some(&Size2u::new(shape.width, shape.height)),
+                        id: shape.id,
                     },
                 )
             });
@@ -131,6 +351,23 @@ async fn send_desktop_update(
             )
         });
 
+        let tiles = update.desktop.tiles.as_ref().map(|tiles| {
+            let tiles: Vec<_> = tiles
+                .iter()
+                .map(|tile| {
+                    VideoTile::new(
+                        tile.rect.x,
+                        tile.rect.y,
+                        tile.rect.width,
+                        tile.rect.height,
+                        tile.len,
+                    )
+                })
+                .collect();
+
+            builder.create_vector(&tiles)
+        });
+
         let timings = Timings::create(
             builder,
             &TimingsArgs {
@@ -143,9 +380,13 @@ async fn send_desktop_update(
         VideoFrame::create(
             builder,
             &VideoFrameArgs {
-                video_bytes: update.desktop.len().try_into().unwrap(),
+                video_bytes: update.desktop.data.len().try_into().unwrap(),
                 cursor_update,
                 timings: Some(timings),
+                tiles,
+                sequence,
+                quality: update.desktop.quality,
+                subsampling: update.desktop.subsampling,
             },
         )
     })
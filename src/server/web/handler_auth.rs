@@ -1,25 +1,95 @@
-use actix_web::{post, web, HttpResponse, Responder};
+use actix_web::{get, post, web, HttpResponse, Responder};
 use lazy_static::lazy_static;
 use regex::Regex;
 
-use crate::{network::dto::auth::AuthSuccessResponse, server::web::Sessions};
+use crate::{
+    network::dto::auth::{
+        AuthSuccessResponse, AuthTypesResponse, PasswordAuthRequest, TokenAuthRequest,
+    },
+    server::{credentials::Credentials, web::Sessions},
+};
 
 pub fn handler_auth(cfg: &mut web::ServiceConfig) {
-    cfg.service((auth_username,));
+    cfg.service((auth_types, auth_password, auth_token, auth_username));
+}
+
+#[get("/auth")]
+async fn auth_types(credentials: web::Data<Credentials>) -> impl Responder {
+    HttpResponse::Ok().json(AuthTypesResponse {
+        types: credentials.types(),
+    })
+}
+
+#[post("/auth/password")]
+async fn auth_password(
+    body: web::Json<PasswordAuthRequest>,
+    credentials: web::Data<Credentials>,
+    sessions: web::Data<Sessions>,
+) -> impl Responder {
+    let body = body.into_inner();
+    let username = body.username.clone();
+
+    // hashing takes a while, so keep it away from the async runtime
+    let verified = web::block(move || credentials.verify_password(&body.username, &body.password))
+        .await
+        .unwrap_or(false);
+
+    if !verified {
+        log::warn!("Password auth failed for user {username:?}");
+        return HttpResponse::Unauthorized().body("Wrong username or password");
+    }
+
+    create_session(&sessions)
+}
+
+#[post("/auth/token")]
+async fn auth_token(
+    body: web::Json<TokenAuthRequest>,
+    credentials: web::Data<Credentials>,
+    sessions: web::Data<Sessions>,
+) -> impl Responder {
+    if !credentials.verify_token(&body.token) {
+        log::warn!("Token auth failed");
+        return HttpResponse::Unauthorized().body("Wrong token");
+    }
+
+    create_session(&sessions)
 }
 
 #[post("/auth/username")]
-async fn auth_username(body: web::Bytes, sessions: web::Data<Sessions>) -> impl Responder {
+async fn auth_username(
+    body: web::Bytes,
+    credentials: web::Data<Credentials>,
+    sessions: web::Data<Sessions>,
+) -> impl Responder {
     lazy_static! {
         static ref USERNAME_REGEX: Regex = Regex::new("^[A-Za-z0-9\\-_]+$").expect("valid regex");
     }
 
-    let username = std::str::from_utf8(&body).unwrap();
+    if !credentials.accepts_username() {
+        return HttpResponse::NotFound().finish();
+    }
+
+    let username = match std::str::from_utf8(&body) {
+        Ok(x) => x.trim(),
+        Err(_) => return HttpResponse::BadRequest().body("Username is not UTF-8"),
+    };
+
     if !USERNAME_REGEX.is_match(username) {
-        panic!("invalid username");
+        return HttpResponse::BadRequest().body("Invalid username");
     }
 
-    let session = sessions.lock().create_session().unwrap();
+    create_session(&sessions)
+}
+
+fn create_session(sessions: &Sessions) -> HttpResponse {
+    let session = match sessions.lock().create_session() {
+        Ok(x) => x,
+        Err(e) => {
+            log::error!("Unable to create session\n{e:?}");
+            return HttpResponse::ServiceUnavailable().finish();
+        }
+    };
 
     HttpResponse::Ok().json(AuthSuccessResponse {
         token: session.sid().to_hex(),
@@ -0,0 +1,174 @@
+use std::io::BufRead;
+use std::time::{Duration, Instant};
+
+use actix_web::{post, web, HttpResponse, Responder};
+use anyhow::Result;
+use parking_lot::Mutex;
+use rand::{thread_rng, RngCore};
+use serde::Deserialize;
+
+use crate::network::{
+    dto::auth::{AuthServerCommitResponse, AuthServerPinRequest, AuthServerRevealResponse},
+    server_auth::{commitment, format_fingerprint, pin, Fingerprint, Nonce},
+};
+
+/// Unrevealed nonces and unshown PINs older than this are discarded
+const PENDING_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// New commitments are refused for this long after each reveal,
+/// so that a man-in-the-middle cannot retry until the PINs of both sides match
+const REVEAL_COOLDOWN: Duration = Duration::from_secs(30);
+
+pub fn handler_auth_server(cfg: &mut web::ServiceConfig) {
+    cfg.service((auth_server,));
+}
+
+/// Identity of the server presented over TLS
+#[derive(Debug)]
+pub struct ServerIdentity {
+    /// None if TLS is disabled
+    fingerprint: Option<Fingerprint>,
+    pairing: Mutex<Pairing>,
+}
+
+/// At most one client authenticates the server at a time
+#[derive(Debug, Default)]
+struct Pairing {
+    /// Committed but not revealed yet
+    pending: Option<PendingPin>,
+
+    /// Revealed, waiting for the operator to show it
+    pin: Option<(String, Instant)>,
+
+    last_reveal: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct PendingPin {
+    id: String,
+    server_nonce: Nonce,
+    created: Instant,
+}
+
+impl ServerIdentity {
+    pub fn new(fingerprint: Option<Fingerprint>) -> Self {
+        Self {
+            fingerprint,
+            pairing: Default::default(),
+        }
+    }
+
+    /// Take the PIN of the last reveal, unless it has expired
+    fn take_pin(&self) -> Option<String> {
+        let (pin, revealed) = self.pairing.lock().pin.take()?;
+        (revealed.elapsed() < PENDING_TIMEOUT).then_some(pin)
+    }
+}
+
+/// Show the PIN of a reveal only when the operator presses Enter on the console,
+/// so that each printed PIN takes an explicit action.
+pub fn spawn_pin_console(identity: web::Data<ServerIdentity>) -> Result<()> {
+    std::thread::Builder::new()
+        .name("pin_console".to_string())
+        .spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                if line.is_err() {
+                    break;
+                }
+
+                match identity.take_pin() {
+                    Some(pin) => println!("PIN: {pin}"),
+                    None => println!("No client is waiting for a PIN."),
+                }
+            }
+        })?;
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct AuthServerQuery {
+    #[serde(rename = "type")]
+    auth_type: String,
+}
+
+#[post("/auth-server")]
+async fn auth_server(
+    query: web::Query<AuthServerQuery>,
+    body: web::Json<AuthServerPinRequest>,
+    identity: web::Data<ServerIdentity>,
+) -> impl Responder {
+    if query.auth_type != "pin" {
+        return HttpResponse::NotFound().finish();
+    }
+
+    let fingerprint = match &identity.fingerprint {
+        Some(x) => x,
+        None => return HttpResponse::NotFound().finish(),
+    };
+
+    let mut pairing = identity.pairing.lock();
+    pairing.pending = pairing
+        .pending
+        .take()
+        .filter(|x| x.created.elapsed() < PENDING_TIMEOUT);
+
+    match body.into_inner() {
+        AuthServerPinRequest::Commit => {
+            let cooling_down = pairing
+                .last_reveal
+                .is_some_and(|x| x.elapsed() < REVEAL_COOLDOWN);
+            if pairing.pending.is_some() || cooling_down {
+                return HttpResponse::TooManyRequests().finish();
+            }
+
+            let mut rng = thread_rng();
+
+            let mut id = [0u8; 16];
+            rng.fill_bytes(&mut id);
+            let id = hex::encode(id);
+
+            let mut server_nonce = [0u8; 32];
+            rng.fill_bytes(&mut server_nonce);
+
+            let response = AuthServerCommitResponse {
+                id: id.clone(),
+                commitment: hex::encode(commitment(fingerprint, &server_nonce)),
+            };
+
+            pairing.pending = Some(PendingPin {
+                id,
+                server_nonce,
+                created: Instant::now(),
+            });
+
+            HttpResponse::Ok().json(response)
+        }
+        AuthServerPinRequest::Reveal { id, client_nonce } => {
+            let mut nonce = Nonce::default();
+            if hex::decode_to_slice(&client_nonce, &mut nonce).is_err() {
+                return HttpResponse::BadRequest().finish();
+            }
+
+            // each commitment can only be revealed once
+            let server_nonce = match pairing.pending.take_if(|x| x.id == id) {
+                Some(x) => x.server_nonce,
+                None => return HttpResponse::NotFound().finish(),
+            };
+
+            let now = Instant::now();
+            pairing.pin = Some((pin(fingerprint, &nonce, &server_nonce), now));
+            pairing.last_reveal = Some(now);
+
+            println!(
+                "A client is authenticating this server (certificate {}).\n\
+                 Press Enter to show its PIN.",
+                format_fingerprint(fingerprint)
+            );
+
+            HttpResponse::Ok().json(AuthServerRevealResponse {
+                server_nonce: hex::encode(server_nonce),
+            })
+        }
+    }
+}
@@ -3,7 +3,7 @@ use std::sync::Arc;
 use actix_web::{get, post, web, HttpResponse, Responder};
 
 use crate::{
-    network::dto::video::{DesktopInfo, MonitorInfo, RefreshRate, Resolution, StartCapture},
+    network::dto::video::{DesktopInfo, StartCapture},
     server::{web::SessionGuard, SharedTwilightServer},
 };
 
@@ -14,21 +14,15 @@ pub fn handler_capture(cfg: &mut web::ServiceConfig) {
 #[get("/capture/desktop")]
 async fn capture_desktop_get(
     _session: SessionGuard,
-    _server: web::Data<SharedTwilightServer>,
+    server: web::Data<SharedTwilightServer>,
 ) -> impl Responder {
-    HttpResponse::Ok().json(DesktopInfo {
-        monitor: [MonitorInfo {
-            id: "dummy".into(),
-            name: "dummy monitor".into(),
-            resolution: Resolution {
-                height: 1080,
-                width: 1920,
-            },
-            refresh_rate: RefreshRate { den: 60, num: 1 },
-        }]
-        .into_iter()
-        .collect(),
-    })
+    match server.read().list_monitors() {
+        Ok(monitor) => HttpResponse::Ok().json(DesktopInfo { monitor }),
+        Err(e) => {
+            log::error!("Failed to list monitors\n{e:?}");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
 }
 
 #[post("/capture/desktop")]
@@ -51,19 +45,34 @@ async fn capture_desktop_post(
         }
     };
 
-    match server
-        .write()
-        .subscribe_desktop(&body.id, Arc::clone(&channel))
-    {
-        Ok(_) => {}
+    match server.read().has_monitor(&body.id) {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::NotFound().finish(),
         Err(e) => {
-            //FIXME: What should i do?
-            log::error!("What should i do?\n{e:?}");
+            log::error!("Failed to list monitors\n{e:?}");
             return HttpResponse::InternalServerError().finish();
         }
     }
 
-    channel.add_client(stream);
+    // registered first so the client does not miss NotifyVideoStart
+    let added = channel.add_client(stream.clone());
+
+    match server.write().subscribe_desktop(
+        &body.id,
+        Arc::clone(&channel),
+        body.codec,
+        body.target_resolution.as_ref(),
+        body.max_fps,
+    ) {
+        Ok(_) => {}
+        Err(e) => {
+            log::error!("Failed to capture monitor {}\n{e:?}", body.id);
+            if added {
+                channel.remove_client(&stream);
+            }
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
 
     HttpResponse::Ok().finish()
 }
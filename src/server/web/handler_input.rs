@@ -0,0 +1,40 @@
+use actix_web::{post, web, HttpResponse, Responder};
+
+use crate::network::dto::input::StartInput;
+use crate::server::SharedTwilightServer;
+
+use super::SessionGuard;
+
+pub fn handler_input(cfg: &mut web::ServiceConfig) {
+    cfg.service((input_desktop_post,));
+}
+
+#[post("/input/desktop")]
+async fn input_desktop_post(
+    session: SessionGuard,
+    server: web::Data<SharedTwilightServer>,
+    body: web::Json<StartInput>,
+) -> impl Responder {
+    let channel = match session.get_channel(body.ch) {
+        Some(x) => x,
+        None => {
+            return HttpResponse::FailedDependency().finish();
+        }
+    };
+
+    if let Some(id) = &body.id {
+        match server.read().has_monitor(id) {
+            Ok(true) => {}
+            Ok(false) => return HttpResponse::NotFound().finish(),
+            Err(e) => {
+                log::error!("Failed to list monitors\n{e:?}");
+                return HttpResponse::InternalServerError().finish();
+            }
+        }
+    }
+
+    channel.set_input_monitor(body.id.clone());
+    channel.set_accept_input(true);
+
+    HttpResponse::Ok().finish()
+}
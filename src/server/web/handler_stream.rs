@@ -6,6 +6,11 @@ use actix_web_actors::ws;
 use bytes::Bytes;
 use serde::Deserialize;
 
+use crate::network::CONTROL_CHANNEL;
+use crate::schema::control::{ControlFrame, ControlPacket};
+use crate::schema::parse_msg;
+use crate::server::SharedTwilightServer;
+
 use super::{SessionId, Sessions, WebSession};
 
 pub fn handler_stream(cfg: &mut web::ServiceConfig) {
@@ -21,6 +26,7 @@ struct AuthQuery {
 async fn stream_v1(
     query: web::Query<AuthQuery>,
     sessions: web::Data<Sessions>,
+    server: web::Data<SharedTwilightServer>,
     req: HttpRequest,
     stream: web::Payload,
 ) -> impl Responder {
@@ -41,6 +47,7 @@ async fn stream_v1(
 
     let actor = WebsocketActor {
         session,
+        server: server.into_inner(),
         did_register: false,
     };
 
@@ -49,11 +56,82 @@ async fn stream_v1(
 
 pub struct WebsocketActor {
     session: Arc<WebSession>,
+    server: Arc<SharedTwilightServer>,
 
     /// True if called `session.open_stream` successfully.
     did_register: bool,
 }
 
+impl WebsocketActor {
+    fn recv_binary(&self, msg: Bytes) {
+        if msg.len() < 2 {
+            log::warn!("Ignoring too short message (len={})", msg.len());
+            return;
+        }
+
+        let ch = u16::from_le_bytes(msg[..2].try_into().expect("checked above"));
+
+        if ch == CONTROL_CHANNEL {
+            self.recv_control(&msg[2..]);
+            return;
+        }
+
+        let channel = match self.session.get_channel(ch) {
+            Some(x) => x,
+            None => {
+                log::warn!("Ignoring message for non-existing channel {ch}");
+                return;
+            }
+        };
+
+        self.server.read().recv_message(&channel, &msg[2..]);
+    }
+
+    fn recv_control(&self, msg: &[u8]) {
+        let truncated = msg.len() < 4
+            || msg.len() < 4 + u32::from_le_bytes(msg[..4].try_into().unwrap()) as usize;
+        if truncated {
+            log::warn!("Ignoring truncated control message (len={})", msg.len());
+            return;
+        }
+
+        let frame: ControlFrame = match parse_msg(msg) {
+            Ok(x) => x,
+            Err(e) => {
+                log::warn!("Ignoring invalid control message: {e:?}");
+                return;
+            }
+        };
+
+        match frame.data_type() {
+            ControlPacket::video_RequestKeyframe => {
+                let stream = match frame.data_as_video_request_keyframe() {
+                    Some(x) => x.stream(),
+                    None => return,
+                };
+
+                match self.session.get_channel(stream) {
+                    Some(channel) => channel.request_keyframe(),
+                    None => {
+                        log::warn!("Ignoring keyframe request for non-existing channel {stream}")
+                    }
+                }
+            }
+            ControlPacket::video_AckVideoFrame => {
+                let ack = match frame.data_as_video_ack_video_frame() {
+                    Some(x) => x,
+                    None => return,
+                };
+
+                if let Some(channel) = self.session.get_channel(ack.stream()) {
+                    channel.ack(ack.sequence());
+                }
+            }
+            other => log::warn!("Ignoring unexpected control packet {other:?}"),
+        }
+    }
+}
+
 impl Actor for WebsocketActor {
     type Context = ws::WebsocketContext<Self>;
 
@@ -69,8 +147,9 @@ impl Actor for WebsocketActor {
         }
     }
 
-    fn stopped(&mut self, _ctx: &mut Self::Context) {
+    fn stopped(&mut self, ctx: &mut Self::Context) {
         if self.did_register {
+            self.session.leave_channels(&ctx.address());
             self.session.close_stream().unwrap();
         }
     }
@@ -89,7 +168,7 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebsocketActor {
     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
         match msg {
             Ok(ws::Message::Ping(msg)) => ctx.pong(&msg),
-            Ok(ws::Message::Binary(msg)) => println!("unexpected message received: {msg:?}"),
+            Ok(ws::Message::Binary(msg)) => self.recv_binary(msg),
             _ => (),
         }
     }
@@ -1,9 +1,12 @@
 mod handler_auth;
+mod handler_auth_server;
 mod handler_capture;
 mod handler_channel;
+mod handler_input;
 mod handler_stream;
 mod serve;
 mod session_id;
+mod tls;
 mod web_session;
 
 use session_id::*;
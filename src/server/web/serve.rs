@@ -2,39 +2,107 @@ use actix_web::{
     web::{self, ServiceConfig},
     App, HttpServer,
 };
-use anyhow::Result;
+use anyhow::{ensure, Context, Result};
 
-use crate::server::{normal_defaults, TwilightServer};
+use crate::server::{
+    credentials::Credentials, generate_token, normal_defaults, ListenConfig, ServerConfig,
+    TwilightServer,
+};
 
 use super::{
-    handler_auth::handler_auth, handler_capture::handler_capture, handler_channel::handler_channel,
-    handler_stream::handler_stream, SessionStorage,
+    handler_auth::handler_auth,
+    handler_auth_server::{handler_auth_server, spawn_pin_console, ServerIdentity},
+    handler_capture::handler_capture,
+    handler_channel::handler_channel,
+    handler_input::handler_input,
+    handler_stream::handler_stream,
+    tls::load_tls_config,
+    SessionStorage,
 };
 
-pub async fn serve_web() -> Result<()> {
-    let base_path = "/twilight";
+pub async fn serve_web(config: ServerConfig) -> Result<()> {
+    let defaults = normal_defaults();
+
+    let base_path = config
+        .base_path
+        .clone()
+        .or(defaults.base_path)
+        .expect("normal_defaults must have base_path");
+    let base_path = normalize_base_path(&base_path)?;
+
+    let listen = config
+        .listen
+        .clone()
+        .or(defaults.listen)
+        .expect("normal_defaults must have listen");
+    ensure!(!listen.is_empty(), "no address to listen on");
 
-    let config = normal_defaults();
+    let mut credentials = Credentials::new(&config.auth)?;
+    if credentials.is_empty() {
+        let token = generate_token();
+        println!("No credential configured. Use this token to connect: {token}");
+        credentials.add_token(&token);
+    }
+
+    let (tls_config, fingerprint) = if listen.iter().any(|x| x.tls) {
+        let (tls_config, fingerprint) = load_tls_config(&config.tls)?;
+        (Some(tls_config), Some(fingerprint))
+    } else {
+        (None, None)
+    };
 
     let session_storage = web::Data::new(SessionStorage::new());
+    let credentials = web::Data::new(credentials);
     let twilight_server = web::Data::new(TwilightServer::new(config));
+    let server_identity = web::Data::new(ServerIdentity::new(fingerprint));
+    if tls_config.is_some() {
+        spawn_pin_console(server_identity.clone()).context("unable to start the PIN console")?;
+    }
 
-    HttpServer::new(move || {
+    let scope_path = base_path.clone();
+    let mut server = HttpServer::new(move || {
         App::new()
             .app_data(session_storage.clone())
+            .app_data(credentials.clone())
             .app_data(twilight_server.clone())
-            .service(web::scope(base_path).configure(all_handlers))
-    })
-    .bind(("127.0.0.1", 1518))?
-    .run()
-    .await?;
+            .app_data(server_identity.clone())
+            .service(web::scope(&scope_path).configure(all_handlers))
+    });
+
+    for ListenConfig { address, tls } in listen {
+        server = if tls {
+            let tls_config = tls_config.clone().expect("loaded if any address uses TLS");
+            server.bind_rustls_0_23(address, tls_config)
+        } else {
+            server.bind(address)
+        }
+        .with_context(|| format!("unable to listen on {address}"))?;
+
+        let scheme = if tls { "twilight" } else { "twilightc" };
+        println!("Listening on {scheme}://{address}{base_path}/");
+    }
+
+    server.run().await?;
 
     Ok(())
 }
 
+/// Removes trailing slash, so that "/" becomes the empty base path.
+fn normalize_base_path(path: &str) -> Result<String> {
+    let path = path.trim_end_matches('/');
+    ensure!(
+        path.is_empty() || path.starts_with('/'),
+        "base path must start with a slash"
+    );
+
+    Ok(path.to_string())
+}
+
 fn all_handlers(config: &mut ServiceConfig) {
     config.configure(handler_auth);
+    config.configure(handler_auth_server);
     config.configure(handler_capture);
     config.configure(handler_channel);
+    config.configure(handler_input);
     config.configure(handler_stream);
 }
@@ -0,0 +1,120 @@
+use std::{
+    fs,
+    io::{BufReader, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::{bail, Context, Result};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+
+use crate::network::server_auth::{fingerprint, format_fingerprint, Fingerprint};
+use crate::server::TlsConfig;
+
+/// Subject names of the generated self-signed certificate
+const SELF_SIGNED_NAMES: &[&str] = &["localhost", "127.0.0.1", "::1"];
+
+/// Build rustls config from the server config, generating a certificate if needed.
+/// Also returns the fingerprint of the leaf certificate.
+pub fn load_tls_config(config: &TlsConfig) -> Result<(rustls::ServerConfig, Fingerprint)> {
+    let (certs, key) = match (&config.cert_path, &config.key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            if !cert_path.exists() && !key_path.exists() {
+                log::info!(
+                    "Generating self-signed certificate at {}",
+                    cert_path.display()
+                );
+                save_self_signed(cert_path, key_path)?;
+            }
+
+            (read_certs(cert_path)?, read_key(key_path)?)
+        }
+        (None, None) => {
+            log::warn!("No certificate configured. Using an ephemeral self-signed certificate");
+            generate_self_signed()?
+        }
+        _ => bail!("cert_path and key_path must be set together"),
+    };
+
+    let cert_fingerprint = fingerprint(&certs[0]);
+    log::info!(
+        "Certificate fingerprint is {}",
+        format_fingerprint(&cert_fingerprint)
+    );
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let tls_config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("invalid certificate or private key")?;
+
+    Ok((tls_config, cert_fingerprint))
+}
+
+fn generate_self_signed() -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+    let generated = self_signed()?;
+
+    let cert = generated.cert.der().clone();
+    let key = PrivatePkcs8KeyDer::from(generated.key_pair.serialize_der());
+
+    Ok((vec![cert], key.into()))
+}
+
+fn save_self_signed(cert_path: &Path, key_path: &Path) -> Result<()> {
+    let generated = self_signed()?;
+
+    for path in [cert_path, key_path] {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("unable to create {}", parent.display()))?;
+        }
+    }
+
+    write_private(key_path, generated.key_pair.serialize_pem().as_bytes())
+        .with_context(|| format!("unable to write {}", key_path.display()))?;
+    fs::write(cert_path, generated.cert.pem())
+        .with_context(|| format!("unable to write {}", cert_path.display()))?;
+
+    Ok(())
+}
+
+/// Readable by the owner only on unix, as it holds the private key.
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options.open(path)?.write_all(contents)
+}
+
+fn self_signed() -> Result<rcgen::CertifiedKey> {
+    let names: Vec<String> = SELF_SIGNED_NAMES.iter().map(|x| x.to_string()).collect();
+    Ok(rcgen::generate_simple_self_signed(names)?)
+}
+
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let file =
+        fs::File::open(path).with_context(|| format!("unable to open {}", path.display()))?;
+
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("unable to parse {}", path.display()))?;
+
+    if certs.is_empty() {
+        bail!("no certificate found in {}", path.display());
+    }
+
+    Ok(certs)
+}
+
+fn read_key(path: &Path) -> Result<PrivateKeyDer<'static>> {
+    let file =
+        fs::File::open(path).with_context(|| format!("unable to open {}", path.display()))?;
+
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .with_context(|| format!("unable to parse {}", path.display()))?
+        .with_context(|| format!("no private key found in {}", path.display()))
+}
@@ -108,7 +108,7 @@ impl SessionStorage {
         let valid_after = Instant::now() - EXPIRE_TIMEOUT;
 
         while let Some(entry) = self.last_used.first_entry() {
-            if &valid_after <= &entry.key().0 {
+            if valid_after <= entry.key().0 {
                 // minimum instant in this table is valid. No need to check more.
                 break;
             }
@@ -186,11 +186,20 @@ impl WebSession {
     }
 
     pub fn get_channel(&self, ch: u16) -> Option<Arc<Channel>> {
-        self.channels.read().get(&ch).map(|x| Arc::clone(x))
+        self.channels.read().get(&ch).map(Arc::clone)
+    }
+
+    /// Remove `addr` from the clients of every channel of this session.
+    pub fn leave_channels(&self, addr: &Addr<WebsocketActor>) {
+        for channel in self.channels.read().values() {
+            channel.remove_client(addr);
+        }
     }
 
     pub fn close_channel(&self, ch: u16) {
-        self.channels.write().remove(&ch);
+        if let Some(channel) = self.channels.write().remove(&ch) {
+            channel.close();
+        }
     }
 }
 
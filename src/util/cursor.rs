@@ -1,4 +1,10 @@
+use sha2::{Digest, Sha256};
+
 use crate::image::ImageBuf;
+use crate::util::AsUsize;
+
+/// Cursor shapes every client keeps at least, so that the server can refer to them by id
+pub const CURSOR_CACHE_SIZE: usize = 32;
 
 #[derive(Clone, Debug)]
 pub struct CursorState {
@@ -23,3 +29,32 @@ pub struct CursorShape {
     pub hotspot_x: f32,
     pub hotspot_y: f32,
 }
+
+impl CursorShape {
+    /// Hash of the pixels and hotspot, identifying the shape. Never 0.
+    pub fn content_id(&self) -> u64 {
+        let img = &self.image;
+        let mut hasher = Sha256::new();
+
+        hasher.update(img.width.to_le_bytes());
+        hasher.update(img.height.to_le_bytes());
+        hasher.update([img.color_format as u8, self.xor as u8]);
+        hasher.update(self.hotspot_x.to_le_bytes());
+        hasher.update(self.hotspot_y.to_le_bytes());
+
+        let line_len = (img.width * img.color_format.pixel_stride()).as_usize();
+        if line_len > 0 {
+            for row in img
+                .data
+                .chunks(img.stride.as_usize())
+                .take(img.height.as_usize())
+            {
+                hasher.update(&row[..line_len.min(row.len())]);
+            }
+        }
+
+        let digest = hasher.finalize();
+        let id = u64::from_le_bytes(digest[..8].try_into().expect("digest is 32 bytes"));
+        id.max(1)
+    }
+}
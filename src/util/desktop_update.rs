@@ -1,9 +1,15 @@
+use crate::image::Rect;
 use crate::util::{CursorState, Timings};
 
 #[derive(Debug, Clone)]
 pub struct DesktopUpdate<T: ?Sized> {
     pub cursor: Option<CursorState>,
     pub timings: Timings,
+    /// Areas of the desktop changed since the previous update. None if unknown.
+    pub damage: Option<Vec<Rect>>,
+    /// Size of `desktop` relative to the captured desktop, which positions are multiplied by.
+    /// 1.0 unless scaled.
+    pub scale: f32,
     pub desktop: T,
 }
 
@@ -13,6 +19,8 @@ impl<T> DesktopUpdate<T> {
             DesktopUpdate {
                 cursor: self.cursor,
                 timings: self.timings,
+                damage: self.damage,
+                scale: self.scale,
                 desktop: (),
             },
             self.desktop,
@@ -24,6 +32,8 @@ impl<T> DesktopUpdate<T> {
             DesktopUpdate {
                 cursor: self.cursor.clone(),
                 timings: self.timings.clone(),
+                damage: self.damage.clone(),
+                scale: self.scale,
                 desktop: (),
             },
             &self.desktop,
@@ -34,6 +44,8 @@ impl<T> DesktopUpdate<T> {
         DesktopUpdate {
             cursor: self.cursor,
             timings: self.timings,
+            damage: self.damage,
+            scale: self.scale,
             desktop,
         }
     }
@@ -45,6 +57,8 @@ impl<T> DesktopUpdate<T> {
         DesktopUpdate {
             cursor: self.cursor,
             timings: self.timings,
+            damage: self.damage,
+            scale: self.scale,
             desktop: map_fn(self.desktop),
         }
     }
@@ -56,21 +70,30 @@ impl<T> DesktopUpdate<T> {
         Ok(DesktopUpdate {
             cursor: self.cursor,
             timings: self.timings,
+            damage: self.damage,
+            scale: self.scale,
             desktop: map_fn(self.desktop)?,
         })
     }
 
     pub fn collapse_from(&mut self, prev: Self) {
-        if self.cursor.is_none() {
-            self.cursor = prev.cursor;
-        } else if prev.cursor.is_some() {
-            // merge cursor shape
-            let curr_cursor = self.cursor.as_mut().expect("already checked");
-            let prev_cursor = prev.cursor.expect("already checked");
+        self.damage = match (self.damage.take(), prev.damage) {
+            (Some(mut damage), Some(prev)) => {
+                damage.extend(prev);
+                Some(damage)
+            }
+            _ => None,
+        };
 
-            if curr_cursor.shape.is_none() {
-                curr_cursor.shape = prev_cursor.shape;
+        if let Some(curr_cursor) = &mut self.cursor {
+            // merge cursor shape
+            if let Some(prev_cursor) = prev.cursor {
+                if curr_cursor.shape.is_none() {
+                    curr_cursor.shape = prev_cursor.shape;
+                }
             }
+        } else {
+            self.cursor = prev.cursor;
         }
     }
 
@@ -79,6 +102,11 @@ impl<T> DesktopUpdate<T> {
         let mut shape = None;
 
         for now in prev {
+            match (self.damage.as_mut(), now.damage) {
+                (Some(damage), Some(prev)) => damage.extend(prev),
+                _ => self.damage = None,
+            }
+
             if let Some(mut c) = now.cursor {
                 if let Some(s) = c.shape.take() {
                     shape = Some(s);
@@ -0,0 +1,277 @@
+use std::fmt::{Debug, Formatter};
+use std::io::{self, Write};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+
+use parking_lot::Mutex;
+
+/// Buffers kept by a pool by default, enough for a frame in flight between every two stages
+pub const DEFAULT_POOL_SIZE: usize = 8;
+
+/// Recycles frame sized buffers, instead of allocating them for every frame.
+///
+/// Buffers taken from the pool return to it when dropped, wherever they end up, e.g. in a
+/// `DesktopUpdate` left in a channel between stages. Buffers outliving the pool are freed.
+#[derive(Clone, Debug)]
+pub struct FramePool {
+    inner: Arc<PoolInner>,
+}
+
+#[derive(Debug)]
+struct PoolInner {
+    free: Mutex<Vec<Vec<u8>>>,
+    max_buffers: usize,
+    allocated: AtomicU64,
+    reused: AtomicU64,
+    returned: AtomicU64,
+    discarded: AtomicU64,
+}
+
+/// Counters of a `FramePool` since it was created
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Buffers allocated, as none in the pool was large enough
+    pub allocated: u64,
+    /// Buffers taken from the pool
+    pub reused: u64,
+    /// Buffers back in the pool after use
+    pub returned: u64,
+    /// Buffers freed after use, as the pool was full
+    pub discarded: u64,
+    /// Buffers in the pool now
+    pub idle: usize,
+    /// Capacity of the buffers in the pool now, in bytes
+    pub idle_bytes: usize,
+}
+
+impl PoolStats {
+    /// Fraction of buffers taken from the pool rather than allocated
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.allocated + self.reused;
+        if total == 0 {
+            0.0
+        } else {
+            self.reused as f64 / total as f64
+        }
+    }
+}
+
+impl Default for FramePool {
+    fn default() -> Self {
+        FramePool::new(DEFAULT_POOL_SIZE)
+    }
+}
+
+impl FramePool {
+    /// Keeps at most `max_buffers` buffers not in use.
+    pub fn new(max_buffers: usize) -> Self {
+        FramePool {
+            inner: Arc::new(PoolInner {
+                free: Mutex::new(Vec::with_capacity(max_buffers)),
+                max_buffers,
+                allocated: AtomicU64::new(0),
+                reused: AtomicU64::new(0),
+                returned: AtomicU64::new(0),
+                discarded: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Buffer of `len` bytes, holding whatever was left in it by its previous user.
+    pub fn buffer(&self, len: usize) -> PooledBuf {
+        let mut buf = self.take(len);
+        buf.resize(len, 0);
+        self.wrap(buf)
+    }
+
+    /// Empty buffer, able to hold `capacity` bytes without reallocating.
+    pub fn with_capacity(&self, capacity: usize) -> PooledBuf {
+        let mut buf = self.take(capacity);
+        buf.clear();
+        self.wrap(buf)
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        let inner = &self.inner;
+        let free = inner.free.lock();
+
+        PoolStats {
+            allocated: inner.allocated.load(Ordering::Relaxed),
+            reused: inner.reused.load(Ordering::Relaxed),
+            returned: inner.returned.load(Ordering::Relaxed),
+            discarded: inner.discarded.load(Ordering::Relaxed),
+            idle: free.len(),
+            idle_bytes: free.iter().map(|x| x.capacity()).sum(),
+        }
+    }
+
+    fn take(&self, capacity: usize) -> Vec<u8> {
+        let mut free = self.inner.free.lock();
+
+        // the smallest one large enough, leaving larger ones for larger frames
+        let fit = free
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| x.capacity() >= capacity)
+            .min_by_key(|(_, x)| x.capacity())
+            .map(|(i, _)| i);
+
+        match fit {
+            Some(i) => {
+                self.inner.reused.fetch_add(1, Ordering::Relaxed);
+                free.swap_remove(i)
+            }
+            None => {
+                drop(free);
+                self.inner.allocated.fetch_add(1, Ordering::Relaxed);
+                Vec::with_capacity(capacity)
+            }
+        }
+    }
+
+    fn wrap(&self, buf: Vec<u8>) -> PooledBuf {
+        PooledBuf {
+            buf,
+            pool: Arc::downgrade(&self.inner),
+        }
+    }
+}
+
+impl PoolInner {
+    /// Once full, the smallest buffer is dropped, as it is the least likely to fit a frame.
+    fn put(&self, buf: Vec<u8>) {
+        let mut free = self.free.lock();
+
+        if free.len() < self.max_buffers {
+            free.push(buf);
+            self.returned.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        self.discarded.fetch_add(1, Ordering::Relaxed);
+        let smallest = free
+            .iter_mut()
+            .min_by_key(|x| x.capacity())
+            .filter(|x| x.capacity() < buf.capacity());
+        if let Some(smallest) = smallest {
+            *smallest = buf;
+            self.returned.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Bytes returning to their `FramePool` when dropped.
+/// Buffers made from a `Vec` belong to no pool, and are freed as usual.
+#[derive(Default)]
+pub struct PooledBuf {
+    buf: Vec<u8>,
+    pool: Weak<PoolInner>,
+}
+
+impl PooledBuf {
+    /// Mutable access to the underlying `Vec`, e.g. to change its length.
+    pub fn as_mut_vec(&mut self) -> &mut Vec<u8> {
+        &mut self.buf
+    }
+
+    /// Detach from the pool.
+    pub fn into_vec(mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buf)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    pub fn is_pooled(&self) -> bool {
+        self.pool.strong_count() > 0
+    }
+}
+
+impl From<Vec<u8>> for PooledBuf {
+    fn from(buf: Vec<u8>) -> Self {
+        PooledBuf {
+            buf,
+            pool: Weak::new(),
+        }
+    }
+}
+
+impl Drop for PooledBuf {
+    fn drop(&mut self) {
+        if self.buf.capacity() == 0 {
+            return;
+        }
+
+        if let Some(pool) = self.pool.upgrade() {
+            pool.put(std::mem::take(&mut self.buf));
+        }
+    }
+}
+
+/// Copied into a buffer of the same pool, if any.
+impl Clone for PooledBuf {
+    fn clone(&self) -> Self {
+        match self.pool.upgrade() {
+            Some(inner) => {
+                let mut copy = FramePool { inner }.with_capacity(self.len());
+                copy.buf.extend_from_slice(&self.buf);
+                copy
+            }
+            None => PooledBuf::from(self.buf.clone()),
+        }
+    }
+}
+
+impl Deref for PooledBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+}
+
+impl AsRef<[u8]> for PooledBuf {
+    fn as_ref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl PartialEq for PooledBuf {
+    fn eq(&self, other: &Self) -> bool {
+        self.buf == other.buf
+    }
+}
+
+impl Eq for PooledBuf {}
+
+impl Debug for PooledBuf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PooledBuf")
+            .field("len", &self.buf.len())
+            .field("pooled", &self.is_pooled())
+            .finish()
+    }
+}
+
+/// Appends, e.g. for encoders writing their output into a pooled buffer.
+impl Write for PooledBuf {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.write(data)
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        self.buf.write_all(data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
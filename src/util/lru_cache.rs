@@ -0,0 +1,45 @@
+use std::collections::VecDeque;
+
+/// Map evicting the least recently used entry once full.
+/// Lookups are linear, so it is meant for a few dozen entries at most.
+#[derive(Debug)]
+pub struct LruCache<K, V> {
+    capacity: usize,
+    /// Most recently used at the back
+    entries: VecDeque<(K, V)>,
+}
+
+impl<K: PartialEq, V> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be positive");
+
+        LruCache {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Marks the entry as the most recently used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let pos = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(pos).expect("position is in bounds");
+        self.entries.push_back(entry);
+
+        self.entries.back().map(|(_, v)| v)
+    }
+
+    /// Replaces the entry of the same key, or evicts the least recently used one if full.
+    pub fn insert(&mut self, key: K, value: V) {
+        if let Some(pos) = self.entries.iter().position(|(k, _)| *k == key) {
+            self.entries.remove(pos);
+        } else if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back((key, value));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
@@ -71,7 +71,7 @@ impl Micros {
     }
 }
 
-impl<'a, 'b> Add<&'b Micros> for &'a Micros {
+impl<'b> Add<&'b Micros> for &Micros {
     type Output = Micros;
 
     fn add(self, rhs: &'b Micros) -> Micros {
@@ -79,7 +79,7 @@ impl<'a, 'b> Add<&'b Micros> for &'a Micros {
     }
 }
 
-impl<'a, 'b> Sub<&'b Micros> for &'a Micros {
+impl<'b> Sub<&'b Micros> for &Micros {
     type Output = Micros;
 
     fn sub(self, rhs: &'b Micros) -> Micros {
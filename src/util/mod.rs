@@ -1,22 +1,28 @@
 mod as_usize;
 mod cursor;
 mod desktop_update;
+mod frame_pool;
+mod lru_cache;
 mod micros;
 mod nonsend;
 mod performance_monitor;
 mod spawn_thread_asyncify;
+mod stage_worker;
 mod thread_manager;
 mod timer;
 mod timings;
 mod unwrapped_refmut;
 
 pub use as_usize::AsUsize;
-pub use cursor::{CursorShape, CursorState};
+pub use cursor::{CursorShape, CursorState, CURSOR_CACHE_SIZE};
 pub use desktop_update::DesktopUpdate;
+pub use frame_pool::{FramePool, PoolStats, PooledBuf, DEFAULT_POOL_SIZE};
+pub use lru_cache::LruCache;
 pub use micros::Micros;
 pub use nonsend::NonSend;
 pub use performance_monitor::{PerformanceMonitor, PerformanceStats};
 pub use spawn_thread_asyncify::spawn_thread_asyncify;
+pub use stage_worker::{StageWorker, StopSignal};
 pub use thread_manager::ThreadManager;
 pub use timer::Timer;
 pub use timings::Timings;
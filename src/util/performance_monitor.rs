@@ -46,7 +46,7 @@ impl PerformanceMonitor {
         self.samples[curr_pos] = duration;
     }
 
-    pub fn start_zone(&mut self) -> PerformanceMonitorZone {
+    pub fn start_zone(&mut self) -> PerformanceMonitorZone<'_> {
         self.last_measure = Instant::now();
         PerformanceMonitorZone(self)
     }
@@ -0,0 +1,165 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+use anyhow::{anyhow, bail, Result};
+use parking_lot::Mutex;
+
+/// Worker threads of all stages, started and not finished yet
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// The thread of a pipeline stage, which is asked to stop and joined by `stop`.
+///
+/// Created idle, so that the stage owning it exists before the thread starts.
+/// Dropping it stops the thread as well, so a stage never outlives its worker.
+#[derive(Debug)]
+pub struct StageWorker {
+    name: &'static str,
+    stop: Mutex<Option<flume::Sender<()>>>,
+    handle: Mutex<Option<JoinHandle<Result<()>>>>,
+}
+
+/// Given to the thread of a `StageWorker`, to learn when it should stop.
+/// Waits on channels are cut short by `stop`, so the thread never misses it while blocked.
+#[derive(Clone, Debug)]
+pub struct StopSignal {
+    rx: flume::Receiver<()>,
+}
+
+impl StageWorker {
+    pub fn new(name: &'static str) -> Self {
+        StageWorker {
+            name,
+            stop: Mutex::new(None),
+            handle: Mutex::new(None),
+        }
+    }
+
+    /// Number of stage threads running in this process, e.g. to check that none leaked.
+    pub fn running() -> usize {
+        RUNNING.load(Ordering::SeqCst)
+    }
+
+    /// Spawn the thread. Fails if it was started already.
+    pub fn start(&self, f: impl FnOnce(StopSignal) -> Result<()> + Send + 'static) -> Result<()> {
+        let mut handle = self.handle.lock();
+        if handle.is_some() {
+            bail!("{} already started", self.name);
+        }
+
+        let (stop_tx, stop_rx) = flume::bounded(0);
+        let signal = StopSignal { rx: stop_rx };
+
+        RUNNING.fetch_add(1, Ordering::SeqCst);
+        let spawned = std::thread::Builder::new()
+            .name(self.name.into())
+            .spawn(move || {
+                let _running = Running;
+                f(signal)
+            });
+
+        match spawned {
+            Ok(x) => {
+                *handle = Some(x);
+                *self.stop.lock() = Some(stop_tx);
+                Ok(())
+            }
+            Err(e) => {
+                RUNNING.fetch_sub(1, Ordering::SeqCst);
+                Err(anyhow!(e).context(format!("unable to spawn {}", self.name)))
+            }
+        }
+    }
+
+    /// The thread has returned, or was never started.
+    pub fn is_finished(&self) -> bool {
+        self.handle
+            .lock()
+            .as_ref()
+            .is_none_or(|x| x.is_finished())
+    }
+
+    /// Wait for the thread to return on its own, and take its result.
+    pub fn join(&self) -> Result<()> {
+        let handle = self.handle.lock().take();
+
+        match handle.map(|x| x.join()) {
+            None => Ok(()),
+            Some(Ok(result)) => result,
+            Some(Err(_)) => bail!("{} panicked", self.name),
+        }
+    }
+
+    /// Ask the thread to stop, and wait until it does. Errors of the thread are logged.
+    /// Does nothing if it was never started or is stopped already.
+    pub fn stop(&self) {
+        drop(self.stop.lock().take());
+
+        // the last owner of a stage may be its own thread, which can't join itself
+        let is_current = self
+            .handle
+            .lock()
+            .as_ref()
+            .is_some_and(|x| x.thread().id() == std::thread::current().id());
+        if is_current {
+            return;
+        }
+
+        match self.join() {
+            Ok(()) => log::debug!("{} stopped", self.name),
+            Err(e) => log::error!("{} stopped with an error\n{e:?}", self.name),
+        }
+    }
+}
+
+impl Drop for StageWorker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl StopSignal {
+    pub fn is_stopped(&self) -> bool {
+        self.rx.is_disconnected()
+    }
+
+    /// Next message of `rx`, or None once stopped or all senders of `rx` are dropped.
+    pub fn recv<T>(&self, rx: &flume::Receiver<T>) -> Option<T> {
+        flume::Selector::new()
+            .recv(rx, |x| x.ok())
+            .recv(&self.rx, |_| None)
+            .wait()
+    }
+
+    /// Send `msg` through `tx`, waiting for room if it is full.
+    /// Returns false if stopped meanwhile, or the receiver is dropped.
+    pub fn send<T>(&self, tx: &flume::Sender<T>, msg: T) -> bool {
+        flume::Selector::new()
+            .send(tx, msg, |x| x.is_ok())
+            .recv(&self.rx, |_| false)
+            .wait()
+    }
+
+    /// Sleep until `deadline`. Returns false if stopped meanwhile.
+    pub fn sleep_until(&self, deadline: Instant) -> bool {
+        matches!(
+            self.rx.recv_deadline(deadline),
+            Err(flume::RecvTimeoutError::Timeout)
+        )
+    }
+
+    /// To be waited on along with other channels, in a `flume::Selector`.
+    /// Disconnected once stopped.
+    pub fn receiver(&self) -> &flume::Receiver<()> {
+        &self.rx
+    }
+}
+
+/// Counted in `RUNNING` until the thread returns or panics
+struct Running;
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
@@ -39,9 +39,10 @@ impl Timings {
 
 /// A time (`std::time::Instant`) that becomes `Self::Remote` when serialized.
 /// Thus, a time that is "sided".
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum SidedTime {
     Local(Instant),
+    #[default]
     Remote,
 }
 
@@ -51,12 +52,6 @@ impl From<Instant> for SidedTime {
     }
 }
 
-impl Default for SidedTime {
-    fn default() -> Self {
-        SidedTime::Remote
-    }
-}
-
 impl SidedTime {
     pub fn as_local(&self) -> Option<&Instant> {
         match self {
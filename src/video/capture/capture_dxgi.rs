@@ -1,19 +1,22 @@
-use crate::image::{ColorFormat, Image, ImageBuf};
-use crate::util::{AsUsize, CursorShape, CursorState, DesktopUpdate, NonSend, Timings};
+use crate::image::{ColorFormat, Image, ImageBuf, Rect};
+use crate::util::{
+    AsUsize, CursorShape, CursorState, DesktopUpdate, FramePool, NonSend, StageWorker, StopSignal,
+    Timings,
+};
+use crate::video::capture::factory_win32::trim_end_null;
 use crate::video::capture::CaptureStage;
 use crate::video::encoder::EncoderStage;
-use anyhow::{anyhow, ensure, Context, Result};
-use log::{error, info};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use log::{debug, error, info};
 use parking_lot::RwLock;
 use std::ffi::c_void;
 use std::marker::PhantomData;
-use std::mem::zeroed;
+use std::mem::{size_of, zeroed};
 use std::ptr::slice_from_raw_parts;
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, OnceLock};
-use std::thread::JoinHandle;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use windows::core::*;
+use windows::Win32::Foundation::RECT;
 use windows::Win32::Graphics::Direct3D::*;
 use windows::Win32::Graphics::Direct3D11::*;
 use windows::Win32::Graphics::Dxgi::Common::*;
@@ -21,14 +24,17 @@ use windows::Win32::Graphics::Dxgi::*;
 
 use crate::network::dto::video::{RefreshRate, Resolution};
 
+/// Mode changes take a while to settle, so reinitialization is retried until this elapses
+const REINIT_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug)]
 pub struct CaptureDxgi {
     factory: IDXGIFactory1,
     dev_id: Vec<u16>,
-    shutdown: AtomicBool,
     next_stage: OnceLock<Arc<dyn EncoderStage>>,
-    worker: RwLock<Option<JoinHandle<Result<()>>>>,
-    desc: OnceLock<DXGI_OUTDUPL_DESC>,
+    worker: StageWorker,
+    /// Updated when the duplication is recreated after a mode change
+    desc: RwLock<Option<DXGI_OUTDUPL_DESC>>,
 }
 
 impl CaptureDxgi {
@@ -43,20 +49,16 @@ impl CaptureDxgi {
         Ok(Arc::new(Self {
             factory,
             dev_id,
-            shutdown: AtomicBool::new(false),
             next_stage: Default::default(),
-            worker: Default::default(),
+            worker: StageWorker::new("dxgi_capture"),
             desc: Default::default(),
         }))
     }
 
     fn read_desc<T: 'static>(&self, f: impl FnOnce(&DXGI_OUTDUPL_DESC) -> T) -> T {
         loop {
-            match self.desc.get() {
-                Some(x) => {
-                    return f(x);
-                }
-                None => {}
+            if let Some(x) = self.desc.read().as_ref() {
+                return f(x);
             }
 
             std::thread::yield_now();
@@ -66,7 +68,7 @@ impl CaptureDxgi {
 
 impl CaptureStage for CaptureDxgi {
     fn configured(&self) -> bool {
-        self.desc.get().is_some()
+        self.desc.read().is_some()
     }
 
     fn resolution(&self) -> Result<Resolution> {
@@ -95,19 +97,23 @@ impl CaptureStage for CaptureDxgi {
         }
 
         let this = Arc::clone(&self);
+        self.worker.start(move |stop| capture_loop(this, stop))?;
 
-        let mut guard = self.worker.write();
-        if guard.is_some() {
-            return Err(anyhow!("worker already running"));
-        }
+        // wait for initialization, so that the failure can be reported
+        while self.desc.read().is_none() {
+            if self.worker.is_finished() {
+                self.worker.join()?;
+                bail!("worker has silently finished without configuring");
+            }
 
-        *guard = Some(std::thread::spawn(move || capture_loop(this)));
+            std::thread::sleep(Duration::from_millis(1));
+        }
 
         Ok(())
     }
 
     fn shutdown(&self) {
-        self.shutdown.store(true, Ordering::Relaxed);
+        self.worker.stop();
     }
 }
 
@@ -122,7 +128,7 @@ struct Resources {
     staging_tex: ID3D11Texture2D,
 }
 
-fn capture_loop(stage: Arc<CaptureDxgi>) -> Result<()> {
+fn capture_loop(stage: Arc<CaptureDxgi>, stop: StopSignal) -> Result<()> {
     let next_tx = stage
         .next_stage
         .get()
@@ -131,24 +137,74 @@ fn capture_loop(stage: Arc<CaptureDxgi>) -> Result<()> {
 
     unsafe {
         let mut res = init_capture(&stage)?;
-        stage.desc.set(res.desc.clone());
+        *stage.desc.write() = Some(res.desc.clone());
 
         let mut is_first = true;
+        let pool = FramePool::default();
+
+        while !stop.is_stopped() {
+            let update = match next_img(&mut res, is_first) {
+                Ok(x) => x,
+                Err(e) if is_access_lost(&e) => {
+                    info!("Desktop duplication was lost, possibly by a mode change");
+                    res = reinit_capture(&stage, res, &stop)?;
+                    is_first = true;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
 
-        while !stage.shutdown.load(Ordering::Relaxed) {
-            if let Some(update) = next_img(&mut res, is_first)? {
-                let update = update.and_then_desktop(|tex| download_image(&mut res, &tex))?;
+            if let Some(update) = update {
+                let update =
+                    update.and_then_desktop(|tex| download_image(&mut res, &tex, &pool))?;
 
-                next_tx.send(update)?;
+                if !stop.send(&next_tx, update) {
+                    break;
+                }
 
                 is_first = false;
             }
         }
+
+        debug!("Frame pool of DXGI capture: {:?}", pool.stats());
     }
 
     Ok(())
 }
 
+fn is_access_lost(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<Error>()
+        .is_some_and(|e| e.code() == DXGI_ERROR_ACCESS_LOST)
+}
+
+/// Release the lost duplication and duplicate the output again.
+unsafe fn reinit_capture(
+    stage: &CaptureDxgi,
+    res: Resources,
+    stop: &StopSignal,
+) -> Result<Resources> {
+    std::mem::drop(res);
+
+    let start = Instant::now();
+    loop {
+        match init_capture(stage) {
+            Ok(res) => {
+                info!(
+                    "Desktop duplication reinitialized at {}x{}",
+                    res.desc.ModeDesc.Width, res.desc.ModeDesc.Height
+                );
+                *stage.desc.write() = Some(res.desc.clone());
+                return Ok(res);
+            }
+            Err(e) if start.elapsed() < REINIT_TIMEOUT && !stop.is_stopped() => {
+                debug!("Retrying to reinitialize desktop duplication: {e:?}");
+                stop.sleep_until(Instant::now() + Duration::from_millis(100));
+            }
+            Err(e) => return Err(e.context("unable to reinitialize desktop duplication")),
+        }
+    }
+}
+
 unsafe fn init_capture(stage: &CaptureDxgi) -> Result<Resources> {
     let adapter;
     let output;
@@ -161,14 +217,8 @@ unsafe fn init_capture(stage: &CaptureDxgi) -> Result<Resources> {
     let factory = &stage.factory;
 
     unsafe {
-        let adapters = list_adapters(factory)?;
-        ensure!(!adapters.is_empty(), "No D3D adapter found");
-
-        // Select primary adapter
-        adapter = adapters
-            .into_iter()
-            .next()
-            .expect("No primary adapter found");
+        let (selected_adapter, selected_output) = find_output(factory, &stage.dev_id)?;
+        adapter = selected_adapter;
 
         let mut adapter_desc = zeroed();
         adapter.GetDesc1(&mut adapter_desc)?;
@@ -176,12 +226,7 @@ unsafe fn init_capture(stage: &CaptureDxgi) -> Result<Resources> {
         let adapter_name = u16_to_string(&adapter_desc.Description);
         info!("Selected adapter {adapter_name}");
 
-        let primary_output = list_outputs(&adapter)?
-            .into_iter()
-            .next()
-            .context("No primary output found")?;
-
-        output = primary_output
+        output = selected_output
             .cast::<IDXGIOutput1>()
             .context("Output does not support DXGI 1.1")?;
 
@@ -284,6 +329,36 @@ unsafe fn list_adapters(factory: &IDXGIFactory1) -> Result<Vec<IDXGIAdapter1>> {
     Ok(output)
 }
 
+/// Find the output whose device name equals to `dev_id`.
+/// Selects the primary output of the primary adapter if `dev_id` is empty.
+///
+/// SAFETY: Safe as long as `factory` is a valid IDXGIFactory1 instance
+unsafe fn find_output(
+    factory: &IDXGIFactory1,
+    dev_id: &[u16],
+) -> Result<(IDXGIAdapter1, IDXGIOutput)> {
+    let name = trim_end_null(dev_id);
+
+    let adapters = list_adapters(factory)?;
+    ensure!(!adapters.is_empty(), "No D3D adapter found");
+
+    for adapter in adapters {
+        for output in list_outputs(&adapter)? {
+            let mut desc = zeroed();
+            output.GetDesc(&mut desc)?;
+
+            if name.is_empty() || trim_end_null(&desc.DeviceName) == name {
+                return Ok((adapter, output));
+            }
+        }
+    }
+
+    Err(anyhow!(
+        "No output named {}",
+        String::from_utf16_lossy(name)
+    ))
+}
+
 /// SAFETY: Safe as long as `adapter` is a valid IDXGIAdapter1 instance
 unsafe fn list_outputs(adapter: &IDXGIAdapter1) -> Result<Vec<IDXGIOutput>> {
     let mut idx = 0;
@@ -362,9 +437,15 @@ unsafe fn next_img(
                     shape,
                 });
             }
-            if frame_info.LastPresentTime != 0 || is_first {
-                //TODO: Some optimization may happen here
-            }
+
+            let damage = if is_first {
+                None
+            } else if frame_info.LastPresentTime == 0 {
+                // only the pointer has been updated
+                Some(Vec::new())
+            } else {
+                read_damage(res, &frame_info)?
+            };
 
             let mut timings = Timings::new();
             timings.capture = Instant::now().into();
@@ -372,6 +453,8 @@ unsafe fn next_img(
             Ok(Some(DesktopUpdate {
                 cursor,
                 timings,
+                damage,
+                scale: 1.0,
                 desktop: desktop
                     .context("a successful AcquireNextFrame did not return texture")?
                     .cast()?,
@@ -384,7 +467,57 @@ unsafe fn next_img(
     }
 }
 
-unsafe fn download_image(res: &mut Resources, tex: &ID3D11Texture2D) -> Result<ImageBuf> {
+/// Dirty rects and destinations of move rects of the acquired frame.
+/// None if the metadata is not available.
+unsafe fn read_damage(
+    res: &Resources,
+    frame_info: &DXGI_OUTDUPL_FRAME_INFO,
+) -> Result<Option<Vec<Rect>>> {
+    let buf_size = frame_info.TotalMetadataBufferSize;
+    if buf_size == 0 {
+        return Ok(None);
+    }
+
+    let move_size = size_of::<DXGI_OUTDUPL_MOVE_RECT>();
+    let mut moves: Vec<DXGI_OUTDUPL_MOVE_RECT> =
+        vec![zeroed(); buf_size.as_usize() / move_size + 1];
+    let mut moves_len = 0;
+    res.outdupl
+        .GetFrameMoveRects(buf_size, moves.as_mut_ptr(), &mut moves_len)?;
+    moves.truncate(moves_len.as_usize() / move_size);
+
+    let dirty_size = size_of::<RECT>();
+    let mut dirty: Vec<RECT> = vec![zeroed(); buf_size.as_usize() / dirty_size + 1];
+    let mut dirty_len = 0;
+    res.outdupl
+        .GetFrameDirtyRects(buf_size, dirty.as_mut_ptr(), &mut dirty_len)?;
+    dirty.truncate(dirty_len.as_usize() / dirty_size);
+
+    let to_rect = |r: &RECT| {
+        Rect::new(
+            r.left.max(0) as u32,
+            r.top.max(0) as u32,
+            (r.right - r.left).max(0) as u32,
+            (r.bottom - r.top).max(0) as u32,
+        )
+    };
+
+    // sources of move rects are left intact, so only destinations are damaged
+    let damage = moves
+        .iter()
+        .map(|x| &x.DestinationRect)
+        .chain(dirty.iter())
+        .map(to_rect)
+        .collect();
+
+    Ok(Some(damage))
+}
+
+unsafe fn download_image(
+    res: &mut Resources,
+    tex: &ID3D11Texture2D,
+    pool: &FramePool,
+) -> Result<ImageBuf> {
     let mut src_desc = zeroed();
     res.staging_tex.GetDesc(&mut src_desc);
     assert_eq!(
@@ -398,12 +531,19 @@ unsafe fn download_image(res: &mut Resources, tex: &ID3D11Texture2D) -> Result<I
     res.ctx
         .CopySubresourceRegion(&res.staging_tex, 0, 0, 0, 0, tex, 0, None);
 
-    let mut dst = ImageBuf::alloc(width, height, None, ColorFormat::Bgra8888);
-
     let mut info = zeroed();
     res.ctx
         .Map(&res.staging_tex, 0, D3D11_MAP_READ, 0, Some(&mut info))?;
 
+    // of the same stride, so that the rows are copied at once
+    let mut dst = ImageBuf::alloc_in(
+        pool,
+        width,
+        height,
+        Some(info.RowPitch),
+        ColorFormat::Bgra8888,
+    );
+
     //TODO: Handle desktop rotation
     let slice =
         &*slice_from_raw_parts(info.pData as *const u8, (info.RowPitch * height).as_usize());
@@ -1,28 +1,30 @@
 use crate::image::{ColorFormat, Image, ImageBuf};
 use crate::network::dto::video::{RefreshRate, Resolution};
-use crate::util::{CursorShape, CursorState, DesktopUpdate, Timings};
+use crate::util::{CursorShape, CursorState, DesktopUpdate, FramePool, StageWorker, Timings};
+use crate::video::capture::factory_win32::{get_refresh_rate_gdi, trim_end_null};
 use crate::video::capture::CaptureStage;
 use crate::video::encoder::EncoderStage;
 use anyhow::{anyhow, bail, ensure, Result};
 use std::ffi::c_void;
 use std::mem::{size_of, zeroed};
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, OnceLock, RwLock};
-use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 use windows::Win32::Foundation::*;
 use windows::Win32::Graphics::Gdi::*;
 use windows::Win32::UI::WindowsAndMessaging::*;
 
+/// Time between captures if the refresh rate of the monitor is unknown
+const DEFAULT_INTERVAL: Duration = Duration::from_micros(16_667);
+
 // https://github.com/obsproject/obs-studio/blob/6fb83abaeb711d1e12054d2ef539da5c43237c58/plugins/win-capture/dc-capture.c#L38
 
 #[derive(Debug)]
 pub struct CaptureGdi {
     dev_id: Vec<u16>,
-    shutdown: AtomicBool,
     next_stage: OnceLock<Arc<dyn EncoderStage>>,
-    worker: RwLock<Option<JoinHandle<Result<()>>>>,
-    resolution: OnceLock<Resolution>,
+    worker: StageWorker,
+    /// Updated when the monitor changes its mode
+    resolution: RwLock<Option<Resolution>>,
 }
 
 impl CaptureGdi {
@@ -36,9 +38,8 @@ impl CaptureGdi {
 
         Ok(Arc::new(CaptureGdi {
             dev_id,
-            shutdown: AtomicBool::new(false),
             next_stage: Default::default(),
-            worker: Default::default(),
+            worker: StageWorker::new("gdi_capture"),
             resolution: Default::default(),
         }))
     }
@@ -46,20 +47,20 @@ impl CaptureGdi {
 
 impl CaptureStage for CaptureGdi {
     fn configured(&self) -> bool {
-        self.resolution.get().is_some()
+        self.resolution.read().unwrap().is_some()
     }
 
     fn resolution(&self) -> Result<Resolution> {
         self.resolution
-            .get()
-            .cloned()
+            .read()
+            .unwrap()
+            .clone()
             .ok_or_else(|| anyhow!("CaptureGdi not initialized yet"))
     }
 
     fn refresh_rate(&self) -> Result<RefreshRate> {
-        //FIXME: Report actual refresh rate (if possible at all)
-        log::warn!("STUB: reporting refresh rate of 1Hz");
-        Ok(RefreshRate { num: 1, den: 1 })
+        unsafe { get_refresh_rate_gdi(&self.dev_id) }
+            .ok_or_else(|| anyhow!("EnumDisplaySettingsW failed"))
     }
 
     fn set_next_stage(&self, encoder: Arc<dyn EncoderStage>) -> Result<()> {
@@ -76,26 +77,50 @@ impl CaptureStage for CaptureGdi {
             .map(|stage| stage.input())
             .ok_or_else(|| anyhow!("next_stage not set"))?;
 
-        *self.worker.write().unwrap() = Some(std::thread::spawn(move || {
+        self.worker.start(move |stop| {
             let mut resources = init_resources(&this)?;
-            while !this.shutdown.load(Ordering::Acquire) {
-                let update = capture_once(&mut resources)?;
-                next_tx.send(update)?;
+
+            // GDI has no notion of new frames, so capture no more often than the monitor refreshes
+            let interval = match this.refresh_rate() {
+                Ok(RefreshRate { num, den }) if num > 0 && den > 0 => {
+                    Duration::from_secs(den.into()) / num
+                }
+                _ => DEFAULT_INTERVAL,
+            };
+            let mut next_frame = Instant::now();
+            let pool = FramePool::default();
+
+            loop {
+                if !stop.sleep_until(next_frame) {
+                    break;
+                }
+                next_frame = (next_frame + interval).max(Instant::now());
+
+                if monitor_changed(&this, &resources)? {
+                    // release the old bitmap before allocating the new one
+                    std::mem::drop(resources);
+                    resources = init_resources(&this)?;
+                    log::info!(
+                        "Monitor changed to {}x{}",
+                        resources.width,
+                        resources.height
+                    );
+                }
+
+                let update = capture_once(&mut resources, &pool)?;
+                if !stop.send(&next_tx, update) {
+                    break;
+                }
             }
+
+            log::debug!("Frame pool of GDI capture: {:?}", pool.stats());
             Ok(())
-        }));
+        })?;
 
         //TODO: Convert into a proper event based one
-        //      And what the hell is going on with all that unwraps?
-        while self.resolution.get().is_none() {
-            if self.worker.read().unwrap().as_ref().unwrap().is_finished() {
-                self.worker
-                    .write()
-                    .unwrap()
-                    .take()
-                    .unwrap()
-                    .join()
-                    .unwrap()?;
+        while self.resolution.read().unwrap().is_none() {
+            if self.worker.is_finished() {
+                self.worker.join()?;
                 bail!("worker has silently finished without configuring");
             }
             std::thread::sleep(Duration::from_millis(1));
@@ -105,13 +130,11 @@ impl CaptureStage for CaptureGdi {
     }
 
     fn shutdown(&self) {
-        self.shutdown.store(true, Ordering::Release);
+        self.worker.stop();
     }
 }
 
 fn init_resources(this: &CaptureGdi) -> Result<Resources> {
-    //TODO: Use dev_id to decide which monitor to capture
-
     unsafe {
         //FIXME: Resource leak on early return
 
@@ -121,16 +144,17 @@ fn init_resources(this: &CaptureGdi) -> Result<Resources> {
         let memdc = CreateCompatibleDC(hdc);
         ensure!(!memdc.is_invalid(), "unable to create compatible DC");
 
-        let width = GetSystemMetrics(SM_CXSCREEN);
-        let height = GetSystemMetrics(SM_CYSCREEN);
+        // desktop DC spans the whole virtual screen, so locate the monitor within it
+        let rect = find_monitor_rect(&this.dev_id)?;
+
+        let width = rect.right - rect.left;
+        let height = rect.bottom - rect.top;
         ensure!(width > 0 && height > 0, "unable to query screen size");
 
         let width = width as u32;
         let height = height as u32;
 
-        this.resolution
-            .set(Resolution { width, height })
-            .map_err(|_| anyhow!("resolution already set"))?;
+        *this.resolution.write().unwrap() = Some(Resolution { width, height });
 
         // negate height to produce top-bottom bitmap
         let bitmapinfo = BITMAPINFO {
@@ -167,6 +191,8 @@ fn init_resources(this: &CaptureGdi) -> Result<Resources> {
         Ok(Resources {
             hdc,
             memdc,
+            left: rect.left,
+            top: rect.top,
             width,
             height,
             bitmap,
@@ -177,9 +203,81 @@ fn init_resources(this: &CaptureGdi) -> Result<Resources> {
     }
 }
 
+/// True if the monitor has moved or changed its resolution since `res` was created.
+fn monitor_changed(this: &CaptureGdi, res: &Resources) -> Result<bool> {
+    let rect = find_monitor_rect(&this.dev_id)?;
+
+    Ok(rect.left != res.left
+        || rect.top != res.top
+        || rect.right - rect.left != res.width as i32
+        || rect.bottom - rect.top != res.height as i32)
+}
+
+type MonitorSearch<'a> = (&'a [u16], Option<RECT>);
+
+/// Find the rectangle of the monitor named `dev_id` in virtual screen coordinates.
+/// Selects the primary monitor if `dev_id` is empty.
+pub(super) fn find_monitor_rect(dev_id: &[u16]) -> Result<RECT> {
+    let name = trim_end_null(dev_id);
+    let mut search: MonitorSearch = (name, None);
+
+    unsafe {
+        EnumDisplayMonitors(
+            None,
+            None,
+            Some(monitor_rect_accessor),
+            LPARAM(&mut search as *mut MonitorSearch as isize),
+        );
+    }
+
+    search
+        .1
+        .ok_or_else(|| anyhow!("No monitor named {}", String::from_utf16_lossy(name)))
+}
+
+/// Called by EnumDisplayMonitors
+unsafe extern "system" fn monitor_rect_accessor(
+    hmonitor: HMONITOR,
+    _: HDC,
+    _: *mut RECT,
+    data: LPARAM,
+) -> BOOL {
+    // Safe because this callback is called solely within EnumDisplayMonitors
+    let search = &mut *(data.0 as *mut MonitorSearch);
+
+    let mut info: MONITORINFOEXW = zeroed();
+    info.monitorInfo.cbSize = size_of::<MONITORINFOEXW>() as u32;
+
+    if !GetMonitorInfoW(
+        hmonitor,
+        &mut info as *mut MONITORINFOEXW as *mut MONITORINFO,
+    )
+    .as_bool()
+    {
+        return BOOL(1);
+    }
+
+    let primary = info.monitorInfo.dwFlags & MONITORINFOF_PRIMARY != 0;
+    let matches = if search.0.is_empty() {
+        primary
+    } else {
+        trim_end_null(&info.szDevice) == search.0
+    };
+
+    if matches {
+        search.1 = Some(info.monitorInfo.rcMonitor);
+        // stop enumeration
+        return BOOL(0);
+    }
+
+    BOOL(1)
+}
+
 struct Resources {
     hdc: HDC,
     memdc: HDC,
+    left: i32,
+    top: i32,
     width: u32,
     height: u32,
     bitmap: HBITMAP,
@@ -202,7 +300,7 @@ impl Drop for Resources {
     }
 }
 
-fn capture_once(res: &mut Resources) -> Result<DesktopUpdate<ImageBuf>> {
+fn capture_once(res: &mut Resources, pool: &FramePool) -> Result<DesktopUpdate<ImageBuf>> {
     let cursor_state;
 
     let slice = unsafe {
@@ -213,8 +311,8 @@ fn capture_once(res: &mut Resources) -> Result<DesktopUpdate<ImageBuf>> {
             res.width as i32,
             res.height as i32,
             res.hdc,
-            0,
-            0,
+            res.left,
+            res.top,
             SRCCOPY,
         )?;
 
@@ -252,10 +350,15 @@ fn capture_once(res: &mut Resources) -> Result<DesktopUpdate<ImageBuf>> {
                 None
             };
 
+            // make the position relative to the captured monitor
+            let x = cursor_info.ptScreenPos.x - res.left;
+            let y = cursor_info.ptScreenPos.y - res.top;
+            let inside = 0 <= x && x < res.width as i32 && 0 <= y && y < res.height as i32;
+
             Some(CursorState {
-                visible: (cursor_info.flags.0 & CURSOR_SHOWING.0) != 0,
-                pos_x: cursor_info.ptScreenPos.x as u32,
-                pos_y: cursor_info.ptScreenPos.y as u32,
+                visible: inside && (cursor_info.flags.0 & CURSOR_SHOWING.0) != 0,
+                pos_x: x.max(0) as u32,
+                pos_y: y.max(0) as u32,
                 shape,
             })
         } else {
@@ -275,6 +378,8 @@ fn capture_once(res: &mut Resources) -> Result<DesktopUpdate<ImageBuf>> {
     Ok(DesktopUpdate {
         cursor: cursor_state,
         timings,
+        damage: None,
+        scale: 1.0,
         desktop: Image {
             color_format: ColorFormat::Bgra8888,
             width: res.width,
@@ -282,7 +387,7 @@ fn capture_once(res: &mut Resources) -> Result<DesktopUpdate<ImageBuf>> {
             stride: res.width * 4,
             data: slice,
         }
-        .copied(),
+        .copied_in(pool),
     })
 }
 
@@ -349,7 +454,7 @@ unsafe fn get_cursor_color(iconinfo: &ICONINFO, xor: &mut bool) -> Option<ImageB
         bmp_color.bmHeight as u32,
         bmp_color.bmWidthBytes as u32,
         ColorFormat::Bgra8888,
-        color,
+        color.into(),
     ))
 }
 
@@ -395,6 +500,6 @@ unsafe fn get_cursor_monochrome(iconinfo: &ICONINFO, xor: &mut bool) -> Option<I
         height,
         bmp.bmWidth as u32 * 4,
         ColorFormat::Bgra8888,
-        output,
+        output.into(),
     ))
 }
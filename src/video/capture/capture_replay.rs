@@ -0,0 +1,150 @@
+use crate::network::dto::video::{RefreshRate, Resolution};
+use crate::server::{ReplayCaptureConfig, ReplayPace};
+use crate::util::{DesktopUpdate, StageWorker, StopSignal, Timings};
+use crate::video::capture::replay_file::ReplayReader;
+use crate::video::capture::CaptureStage;
+use crate::video::encoder::EncoderStage;
+use anyhow::{anyhow, ensure, Context, Result};
+use log::info;
+use parking_lot::RwLock;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+
+/// Streams frames from a file written by `ReplayWriter`.
+#[derive(Debug)]
+pub struct CaptureReplay {
+    config: ReplayCaptureConfig,
+    /// Follows the frames, which may change resolution mid-file
+    resolution: RwLock<Resolution>,
+    refresh_rate: RefreshRate,
+    next_stage: OnceLock<Arc<dyn EncoderStage>>,
+    worker: StageWorker,
+}
+
+impl CaptureReplay {
+    pub fn new(config: ReplayCaptureConfig) -> Result<Arc<Self>> {
+        let reader = open_replay(&config)?;
+        let resolution = reader.resolution().clone();
+        let refresh_rate = reader.refresh_rate().clone();
+
+        ensure!(
+            resolution.width > 0 && resolution.height > 0,
+            "replay file has empty resolution"
+        );
+
+        Ok(Arc::new(Self {
+            config,
+            resolution: RwLock::new(resolution),
+            refresh_rate,
+            next_stage: Default::default(),
+            worker: StageWorker::new("replay_capture"),
+        }))
+    }
+}
+
+impl CaptureStage for CaptureReplay {
+    fn configured(&self) -> bool {
+        !self.worker.is_finished()
+    }
+
+    fn resolution(&self) -> Result<Resolution> {
+        Ok(self.resolution.read().clone())
+    }
+
+    fn refresh_rate(&self) -> Result<RefreshRate> {
+        Ok(self.refresh_rate.clone())
+    }
+
+    fn set_next_stage(&self, encoder: Arc<dyn EncoderStage>) -> Result<()> {
+        self.next_stage
+            .set(encoder)
+            .map_err(|_| anyhow!("next_stage already set"))
+    }
+
+    fn configure(self: Arc<Self>) -> Result<()> {
+        if self.next_stage.get().is_none() {
+            return Err(anyhow!("next_stage not set"));
+        }
+
+        let this = Arc::clone(&self);
+        self.worker.start(move |stop| replay_loop(this, stop))
+    }
+
+    fn shutdown(&self) {
+        self.worker.stop();
+    }
+}
+
+fn open_replay(config: &ReplayCaptureConfig) -> Result<ReplayReader<BufReader<File>>> {
+    let file = File::open(&config.path)
+        .with_context(|| format!("unable to open replay file {:?}", config.path))?;
+
+    ReplayReader::new(BufReader::new(file))
+}
+
+fn replay_loop(stage: Arc<CaptureReplay>, stop: StopSignal) -> Result<()> {
+    let next_tx = stage
+        .next_stage
+        .get()
+        .map(|stage| stage.input())
+        .context("next_stage not set")?;
+
+    let mut reader = open_replay(&stage.config)?;
+    let mut start = Instant::now();
+    let mut frames_read = 0u64;
+
+    while !stop.is_stopped() {
+        let frame = match reader.next_frame()? {
+            Some(x) => x,
+            None if stage.config.looping => {
+                ensure!(frames_read != 0, "replay file contains no frame");
+                info!("Replay reached end of file. Restarting from the beginning.");
+                reader = open_replay(&stage.config)?;
+                start = Instant::now();
+                frames_read = 0;
+                continue;
+            }
+            None => {
+                info!("Replay reached end of file.");
+                break;
+            }
+        };
+        frames_read += 1;
+
+        if stage.config.pace == ReplayPace::Recorded && !stop.sleep_until(start + frame.timestamp) {
+            break;
+        }
+
+        let desktop = frame.decode()?;
+
+        let resolution = Resolution {
+            width: desktop.width,
+            height: desktop.height,
+        };
+        if *stage.resolution.read() != resolution {
+            info!(
+                "Replay changed resolution to {}x{}",
+                resolution.width, resolution.height
+            );
+            *stage.resolution.write() = resolution;
+        }
+
+        let mut timings = Timings::new();
+        timings.capture = Instant::now().into();
+
+        let update = DesktopUpdate {
+            cursor: frame.cursor,
+            timings,
+            damage: None,
+            scale: 1.0,
+            desktop,
+        };
+        if !stop.send(&next_tx, update) {
+            break;
+        }
+    }
+
+    Ok(())
+}
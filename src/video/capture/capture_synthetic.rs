@@ -0,0 +1,284 @@
+use crate::image::{ColorFormat, ImageBuf};
+use crate::network::dto::video::{RefreshRate, Resolution};
+use crate::server::{SyntheticCaptureConfig, SyntheticPattern};
+use crate::util::{
+    AsUsize, CursorShape, CursorState, DesktopUpdate, FramePool, StageWorker, Timings,
+};
+use crate::video::capture::CaptureStage;
+use crate::video::encoder::EncoderStage;
+use anyhow::{anyhow, ensure, Result};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+const CURSOR_SIZE: u32 = 32;
+
+/// A capture backend that generates frames by itself.
+/// Works on every platform, so the whole pipeline can run without a desktop.
+#[derive(Debug)]
+pub struct CaptureSynthetic {
+    config: SyntheticCaptureConfig,
+    next_stage: OnceLock<Arc<dyn EncoderStage>>,
+    worker: StageWorker,
+}
+
+impl CaptureSynthetic {
+    pub fn new(config: SyntheticCaptureConfig) -> Result<Arc<Self>> {
+        let Resolution { width, height } = config.resolution;
+        ensure!(width > 0 && height > 0, "resolution must not be empty");
+        ensure!(
+            width % 2 == 0 && height % 2 == 0,
+            "resolution must be a multiple of 2"
+        );
+
+        let RefreshRate { num, den } = config.refresh_rate;
+        ensure!(num > 0 && den > 0, "refresh rate must be positive");
+
+        Ok(Arc::new(Self {
+            config,
+            next_stage: Default::default(),
+            worker: StageWorker::new("synthetic_capture"),
+        }))
+    }
+}
+
+impl CaptureStage for CaptureSynthetic {
+    fn configured(&self) -> bool {
+        !self.worker.is_finished()
+    }
+
+    fn resolution(&self) -> Result<Resolution> {
+        Ok(self.config.resolution.clone())
+    }
+
+    fn refresh_rate(&self) -> Result<RefreshRate> {
+        Ok(self.config.refresh_rate.clone())
+    }
+
+    fn set_next_stage(&self, encoder: Arc<dyn EncoderStage>) -> Result<()> {
+        self.next_stage
+            .set(encoder)
+            .map_err(|_| anyhow!("next_stage already set"))
+    }
+
+    fn configure(self: Arc<Self>) -> Result<()> {
+        let next_tx = self
+            .next_stage
+            .get()
+            .map(|stage| stage.input())
+            .ok_or_else(|| anyhow!("next_stage not set"))?;
+
+        let config = self.config.clone();
+
+        self.worker.start(move |stop| {
+            let mut generator = FrameGenerator::new(&config);
+
+            let RefreshRate { num, den } = config.refresh_rate;
+            let interval = Duration::from_secs(den.into()) / num;
+            let mut next_frame = Instant::now();
+
+            loop {
+                if !stop.sleep_until(next_frame) {
+                    break;
+                }
+                next_frame += interval;
+
+                // don't try to catch up if we fell behind too much
+                let now = Instant::now();
+                if next_frame + interval < now {
+                    next_frame = now;
+                }
+
+                if !stop.send(&next_tx, generator.next_frame()) {
+                    break;
+                }
+            }
+
+            log::debug!(
+                "Frame pool of synthetic capture: {:?}",
+                generator.pool.stats()
+            );
+            Ok(())
+        })
+    }
+
+    fn shutdown(&self) {
+        self.worker.stop();
+    }
+}
+
+struct FrameGenerator {
+    background: ImageBuf,
+    pool: FramePool,
+    cursor: Option<CursorShape>,
+    moving_box: bool,
+    fake_cursor: bool,
+    frame_num: u64,
+    frames_per_sec: f32,
+}
+
+impl FrameGenerator {
+    fn new(config: &SyntheticCaptureConfig) -> Self {
+        let Resolution { width, height } = config.resolution;
+        let RefreshRate { num, den } = config.refresh_rate;
+
+        FrameGenerator {
+            background: draw_pattern(width, height, config.pattern),
+            pool: FramePool::default(),
+            cursor: config.fake_cursor.then(draw_cursor),
+            moving_box: config.moving_box,
+            fake_cursor: config.fake_cursor,
+            frame_num: 0,
+            frames_per_sec: num as f32 / den as f32,
+        }
+    }
+
+    fn next_frame(&mut self) -> DesktopUpdate<ImageBuf> {
+        let mut timings = Timings::new();
+        timings.capture = Instant::now().into();
+
+        let mut desktop = self.background.copied_in(&self.pool);
+        let width = desktop.width;
+        let height = desktop.height;
+
+        if self.moving_box {
+            let size = u32::max(u32::min(width, height) / 8, 1);
+            let x = bounce(self.frame_num * 4, width.saturating_sub(size));
+            let y = bounce(self.frame_num * 3, height.saturating_sub(size));
+            fill_rect(&mut desktop, x, y, size, size, [0x20, 0x90, 0xFF, 0xFF]);
+        }
+
+        let cursor = if self.fake_cursor {
+            let t = self.frame_num as f32 / self.frames_per_sec;
+            let cx = width as f32 / 2.0;
+            let cy = height as f32 / 2.0;
+
+            Some(CursorState {
+                visible: true,
+                pos_x: (cx + cx * 0.6 * f32::cos(t)) as u32,
+                pos_y: (cy + cy * 0.6 * f32::sin(t * 2.0)) as u32,
+                // shape is sent only once
+                shape: self.cursor.take(),
+            })
+        } else {
+            None
+        };
+
+        self.frame_num += 1;
+
+        DesktopUpdate {
+            cursor,
+            timings,
+            damage: None,
+            scale: 1.0,
+            desktop,
+        }
+    }
+}
+
+/// Moves back and forth between 0 and `max`
+fn bounce(pos: u64, max: u32) -> u32 {
+    if max == 0 {
+        return 0;
+    }
+
+    let period = 2 * max as u64;
+    let pos = pos % period;
+
+    if pos <= max as u64 {
+        pos as u32
+    } else {
+        (period - pos) as u32
+    }
+}
+
+fn draw_pattern(width: u32, height: u32, pattern: SyntheticPattern) -> ImageBuf {
+    let mut img = ImageBuf::alloc(width, height, None, ColorFormat::Bgra8888);
+    let stride = img.stride.as_usize();
+
+    for y in 0..height {
+        let row = &mut img.data[y.as_usize() * stride..][..width.as_usize() * 4];
+
+        for (x, pixel) in (0..width).zip(row.chunks_exact_mut(4)) {
+            let bgra: [u8; 4] = match pattern {
+                SyntheticPattern::ColorBars => {
+                    // white, yellow, cyan, green, magenta, red, blue, black
+                    const BARS: [[u8; 4]; 8] = [
+                        [0xC0, 0xC0, 0xC0, 0xFF],
+                        [0x00, 0xC0, 0xC0, 0xFF],
+                        [0xC0, 0xC0, 0x00, 0xFF],
+                        [0x00, 0xC0, 0x00, 0xFF],
+                        [0xC0, 0x00, 0xC0, 0xFF],
+                        [0x00, 0x00, 0xC0, 0xFF],
+                        [0xC0, 0x00, 0x00, 0xFF],
+                        [0x00, 0x00, 0x00, 0xFF],
+                    ];
+                    BARS[(x as u64 * 8 / width as u64) as usize]
+                }
+                SyntheticPattern::Gradient => {
+                    let r = (x as u64 * 255 / width as u64) as u8;
+                    let g = (y as u64 * 255 / height as u64) as u8;
+                    [0x80, g, r, 0xFF]
+                }
+                SyntheticPattern::Checkerboard => {
+                    if ((x / 32) + (y / 32)) % 2 == 0 {
+                        [0xFF, 0xFF, 0xFF, 0xFF]
+                    } else {
+                        [0x40, 0x40, 0x40, 0xFF]
+                    }
+                }
+            };
+
+            pixel.copy_from_slice(&bgra);
+        }
+    }
+
+    img
+}
+
+fn fill_rect(img: &mut ImageBuf, x: u32, y: u32, w: u32, h: u32, bgra: [u8; 4]) {
+    let x_end = u32::min(x + w, img.width);
+    let y_end = u32::min(y + h, img.height);
+    let stride = img.stride.as_usize();
+
+    for i in y..y_end {
+        let row = &mut img.data[i.as_usize() * stride..];
+        for j in x..x_end {
+            let pos = j.as_usize() * 4;
+            row[pos..pos + 4].copy_from_slice(&bgra);
+        }
+    }
+}
+
+/// Draws a white arrow with black outline
+fn draw_cursor() -> CursorShape {
+    let mut image = ImageBuf::alloc(CURSOR_SIZE, CURSOR_SIZE, None, ColorFormat::Bgra8888);
+
+    let inside = |x: i32, y: i32| (0..20).contains(&y) && 0 <= x && x * 3 <= y * 2;
+
+    for y in 0..CURSOR_SIZE as i32 {
+        for x in 0..CURSOR_SIZE as i32 {
+            if !inside(x, y) {
+                continue;
+            }
+
+            let is_edge =
+                !inside(x - 1, y) || !inside(x + 1, y) || !inside(x, y - 1) || !inside(x, y + 1);
+
+            let bgra = if is_edge {
+                [0x00, 0x00, 0x00, 0xFF]
+            } else {
+                [0xFF, 0xFF, 0xFF, 0xFF]
+            };
+
+            let pos = y as usize * image.stride.as_usize() + x as usize * 4;
+            image.data[pos..pos + 4].copy_from_slice(&bgra);
+        }
+    }
+
+    CursorShape {
+        image,
+        xor: false,
+        hotspot_x: 0.0,
+        hotspot_y: 0.0,
+    }
+}
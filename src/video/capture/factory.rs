@@ -0,0 +1,23 @@
+use anyhow::Result;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::network::dto::video::MonitorInfo;
+use crate::server::DesktopCaptureMethod;
+
+use super::CaptureStage;
+
+/// Enumerates capturable monitors and starts capture stages for them.
+pub trait CaptureFactory: Debug + Send {
+    /// List monitors that can be passed to `start`.
+    fn list(&mut self) -> Result<Vec<MonitorInfo>>;
+
+    fn available_backends(&mut self) -> Vec<DesktopCaptureMethod>;
+
+    /// Create a capture stage for the monitor with given id.
+    /// The id must be one of the ids returned by `list`.
+    fn start(&mut self, backend: DesktopCaptureMethod, id: &str) -> Result<Arc<dyn CaptureStage>>;
+}
+
+// ensure object safety
+const _: Option<&dyn CaptureFactory> = None;
@@ -0,0 +1,81 @@
+use anyhow::{anyhow, ensure, Result};
+use std::sync::Arc;
+
+use crate::network::dto::video::MonitorInfo;
+use crate::server::{DesktopCaptureMethod, ReplayCaptureConfig, SyntheticCaptureConfig};
+
+use super::{CaptureFactory, CaptureReplay, CaptureStage, CaptureSynthetic};
+
+const SYNTHETIC_ID: &str = "synthetic";
+const REPLAY_ID: &str = "replay";
+
+/// Factory for backends that don't need a real display.
+/// Each backend exposes a single monitor.
+#[derive(Debug)]
+pub struct CaptureFactoryVirtual {
+    synthetic: SyntheticCaptureConfig,
+    replay: ReplayCaptureConfig,
+    backend: DesktopCaptureMethod,
+}
+
+impl CaptureFactoryVirtual {
+    pub fn new(
+        backend: DesktopCaptureMethod,
+        synthetic: SyntheticCaptureConfig,
+        replay: ReplayCaptureConfig,
+    ) -> Result<Self> {
+        ensure!(
+            matches!(
+                backend,
+                DesktopCaptureMethod::Synthetic | DesktopCaptureMethod::Replay
+            ),
+            "capture method {backend:?} is not a virtual backend"
+        );
+
+        Ok(Self {
+            synthetic,
+            replay,
+            backend,
+        })
+    }
+}
+
+impl CaptureFactory for CaptureFactoryVirtual {
+    fn list(&mut self) -> Result<Vec<MonitorInfo>> {
+        let monitor = match self.backend {
+            DesktopCaptureMethod::Synthetic => MonitorInfo {
+                id: SYNTHETIC_ID.into(),
+                name: "synthetic monitor".into(),
+                resolution: self.synthetic.resolution.clone(),
+                refresh_rate: self.synthetic.refresh_rate.clone(),
+            },
+            _ => {
+                // opening the file is the only way to know its resolution
+                let capture = CaptureReplay::new(self.replay.clone())?;
+                MonitorInfo {
+                    id: REPLAY_ID.into(),
+                    name: self.replay.path.display().to_string(),
+                    resolution: capture.resolution()?,
+                    refresh_rate: capture.refresh_rate()?,
+                }
+            }
+        };
+
+        Ok(vec![monitor])
+    }
+
+    fn available_backends(&mut self) -> Vec<DesktopCaptureMethod> {
+        vec![self.backend]
+    }
+
+    fn start(&mut self, backend: DesktopCaptureMethod, id: &str) -> Result<Arc<dyn CaptureStage>> {
+        use DesktopCaptureMethod as M;
+
+        match (backend, id) {
+            (M::Synthetic, SYNTHETIC_ID) => Ok(CaptureSynthetic::new(self.synthetic.clone())?),
+            (M::Replay, REPLAY_ID) => Ok(CaptureReplay::new(self.replay.clone())?),
+            (M::Synthetic | M::Replay, _) => Err(anyhow!("no such monitor: {id}")),
+            _ => Err(anyhow!("no such capture backend available")),
+        }
+    }
+}
@@ -12,19 +12,22 @@ use windows::{core::*, Win32::Graphics::Dxgi::CreateDXGIFactory1};
 use crate::{
     network::dto::video::{MonitorInfo, RefreshRate, Resolution},
     server::DesktopCaptureMethod,
-    video::capture::CaptureGdi,
+    video::capture::{capture_gdi::find_monitor_rect, CaptureGdi},
 };
 
-use super::{CaptureDxgi, CaptureStage};
+use super::{CaptureDxgi, CaptureFactory, CaptureStage};
 
+#[derive(Debug)]
 pub struct CaptureFactoryWin32 {}
 
 impl CaptureFactoryWin32 {
     pub fn new() -> Result<Self> {
         Ok(Self {})
     }
+}
 
-    pub fn list(&mut self) -> Vec<MonitorInfo> {
+impl CaptureFactory for CaptureFactoryWin32 {
+    fn list(&mut self) -> Result<Vec<MonitorInfo>> {
         unsafe {
             let mut output = vec![];
 
@@ -35,27 +38,26 @@ impl CaptureFactoryWin32 {
                 transmute::<&mut AccessorParam, LPARAM>(&mut output),
             );
 
-            if !ok.as_bool() {
-                println!("EnumDisplayMonitors failed");
-            }
+            ensure!(ok.as_bool(), "EnumDisplayMonitors failed");
 
-            output
+            Ok(output)
         }
     }
 
-    pub fn available_backends(&mut self) -> Vec<DesktopCaptureMethod> {
+    fn available_backends(&mut self) -> Vec<DesktopCaptureMethod> {
         use DesktopCaptureMethod as M;
 
         vec![M::Dxgi, M::Gdi]
     }
 
-    pub fn start(
-        &mut self,
-        backend: DesktopCaptureMethod,
-        id: &str,
-    ) -> Result<Arc<dyn CaptureStage>> {
+    fn start(&mut self, backend: DesktopCaptureMethod, id: &str) -> Result<Arc<dyn CaptureStage>> {
         use DesktopCaptureMethod as M;
 
+        ensure!(
+            self.list()?.iter().any(|monitor| monitor.id == id),
+            "no such monitor: {id}"
+        );
+
         let dev_id = decode_hex(id)?;
 
         // apparantly non_exhausive don't work in same crate...
@@ -71,6 +73,12 @@ impl CaptureFactoryWin32 {
     }
 }
 
+/// Rectangle of the monitor with given id in virtual screen coordinates.
+/// Selects the primary monitor if `id` is empty.
+pub fn monitor_rect(id: &str) -> Result<RECT> {
+    find_monitor_rect(&decode_hex(id)?)
+}
+
 type AccessorParam = Vec<MonitorInfo>;
 
 /// Called by EnumDisplayMonitors
@@ -84,7 +92,7 @@ unsafe extern "system" fn accessor(hmonitor: HMONITOR, _: HDC, _: *mut RECT, dat
     let ok = GetMonitorInfoW(hmonitor, transmute(&mut info));
     if !ok.as_bool() {
         // skip if failed to get monitor info
-        //TODO: A log would be nice
+        log::warn!("GetMonitorInfoW failed, skipping monitor");
         return BOOL(1);
     }
 
@@ -113,8 +121,9 @@ unsafe fn get_refresh_rate_dxgi(_dev_name: &[u16; 32]) -> Option<RefreshRate> {
     None
 }
 
-/// This method produces inexact, rounded value. Prefer dxgi version whenever possible
-unsafe fn get_refresh_rate_gdi(dev_name: &[u16; 32]) -> Option<RefreshRate> {
+/// This method produces inexact, rounded value. Prefer dxgi version whenever possible.
+/// `dev_name` must end with a NULL character.
+pub(super) unsafe fn get_refresh_rate_gdi(dev_name: &[u16]) -> Option<RefreshRate> {
     let mut mode: DEVMODEW = zeroed();
     mode.dmSize = size_of_val(&mode) as u16;
     mode.dmDriverExtra = 0;
@@ -162,7 +171,7 @@ fn decode_hex(s: &str) -> Result<Vec<u16>> {
 }
 
 /// Trim the UTF-16 string to remove NULL at end. Works even if no NULL is found.
-fn trim_end_null(s: &[u16]) -> &[u16] {
+pub(super) fn trim_end_null(s: &[u16]) -> &[u16] {
     for (i, x) in s.iter().enumerate() {
         if *x == 0 {
             return &s[0..i];
@@ -1,9 +1,25 @@
+#[cfg(windows)]
 mod capture_dxgi;
+#[cfg(windows)]
 mod capture_gdi;
+mod capture_replay;
+mod capture_synthetic;
+mod factory;
+mod factory_virtual;
+#[cfg(windows)]
 mod factory_win32;
+mod replay_file;
 mod stage;
 
+#[cfg(windows)]
 pub use capture_dxgi::CaptureDxgi;
+#[cfg(windows)]
 pub use capture_gdi::CaptureGdi;
+pub use capture_replay::CaptureReplay;
+pub use capture_synthetic::CaptureSynthetic;
+pub use factory::CaptureFactory;
+pub use factory_virtual::CaptureFactoryVirtual;
+#[cfg(windows)]
 pub use factory_win32::*;
+pub use replay_file::{ReplayFrame, ReplayReader, ReplayWriter};
 pub use stage::CaptureStage;
@@ -0,0 +1,346 @@
+use crate::image::{ColorFormat, Image, ImageBuf};
+use crate::network::dto::video::{RefreshRate, Resolution};
+use crate::schema::video::VideoCodec;
+use crate::util::{AsUsize, CursorShape, CursorState};
+use crate::video::decoder::jpeg::JpegDecoder;
+use crate::video::decoder::DecoderStage;
+use anyhow::{bail, ensure, Context, Result};
+use std::io::{ErrorKind, Read, Write};
+use std::time::Duration;
+
+/*
+A replay file is laid out as follows. Every number is little endian.
+
+header:
+    [8 bytes] MAGIC
+    [u32] width, [u32] height
+    [u32] refresh rate numerator, [u32] refresh rate denominator
+
+followed by any number of records:
+    [u64] microseconds since the first record
+    [u8] VideoCodec of the frame (Bgra8888, Rgb24 or Jpeg)
+    [u32] width, [u32] height
+    [u8] cursor flags (see CURSOR_*)
+    if CURSOR_PRESENT: [u32] pos_x, [u32] pos_y
+    if CURSOR_SHAPE: [f32] hotspot_x, [f32] hotspot_y, [u32] width, [u32] height,
+                     followed by width * height * 4 bytes of BGRA
+    [u32] frame length, followed by the frame data
+
+Raw frames are tightly packed (stride equals width times pixel size).
+ */
+
+const MAGIC: &[u8; 8] = b"TWLRPLY1";
+
+const CURSOR_PRESENT: u8 = 1 << 0;
+const CURSOR_VISIBLE: u8 = 1 << 1;
+const CURSOR_SHAPE: u8 = 1 << 2;
+const CURSOR_XOR: u8 = 1 << 3;
+
+/// Largest width or height of a frame, so that sizes read from a file can be trusted
+const MAX_DIMENSION: u32 = 16384;
+
+/// Largest cursor shape, in either direction
+const MAX_CURSOR_DIMENSION: u32 = 1024;
+
+/// No frame takes more than this many bytes per pixel, whatever the codec
+const MAX_BYTES_PER_PIXEL: usize = 8;
+
+/// Single frame read from a replay file
+#[derive(Debug, Clone)]
+pub struct ReplayFrame {
+    pub timestamp: Duration,
+    pub codec: VideoCodec,
+    pub width: u32,
+    pub height: u32,
+    pub cursor: Option<CursorState>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct ReplayReader<R: Read> {
+    inner: R,
+    resolution: Resolution,
+    refresh_rate: RefreshRate,
+}
+
+impl<R: Read> ReplayReader<R> {
+    pub fn new(mut inner: R) -> Result<Self> {
+        let mut magic = [0u8; 8];
+        inner
+            .read_exact(&mut magic)
+            .context("unable to read replay header")?;
+        ensure!(&magic == MAGIC, "not a replay file");
+
+        let width = read_u32(&mut inner)?;
+        let height = read_u32(&mut inner)?;
+        ensure_dimensions(width, height)?;
+        let num = read_u32(&mut inner)?;
+        let den = read_u32(&mut inner)?;
+
+        Ok(Self {
+            inner,
+            resolution: Resolution { width, height },
+            refresh_rate: RefreshRate { num, den },
+        })
+    }
+
+    pub fn resolution(&self) -> &Resolution {
+        &self.resolution
+    }
+
+    pub fn refresh_rate(&self) -> &RefreshRate {
+        &self.refresh_rate
+    }
+
+    /// Returns None on a clean end of file.
+    pub fn next_frame(&mut self) -> Result<Option<ReplayFrame>> {
+        let mut timestamp = [0u8; 8];
+        match self.inner.read_exact(&mut timestamp) {
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let timestamp = Duration::from_micros(u64::from_le_bytes(timestamp));
+
+        let r = &mut self.inner;
+
+        let codec = VideoCodec(read_u8(r)? as i8);
+        let width = read_u32(r)?;
+        let height = read_u32(r)?;
+        ensure_dimensions(width, height)?;
+
+        let flags = read_u8(r)?;
+        let cursor = if flags & CURSOR_PRESENT != 0 {
+            let pos_x = read_u32(r)?;
+            let pos_y = read_u32(r)?;
+
+            let shape = if flags & CURSOR_SHAPE != 0 {
+                let hotspot_x = f32::from_bits(read_u32(r)?);
+                let hotspot_y = f32::from_bits(read_u32(r)?);
+                let w = read_u32(r)?;
+                let h = read_u32(r)?;
+                ensure!(
+                    w <= MAX_CURSOR_DIMENSION && h <= MAX_CURSOR_DIMENSION,
+                    "cursor shape too large"
+                );
+
+                let mut data = vec![0u8; w.as_usize() * h.as_usize() * 4];
+                r.read_exact(&mut data)?;
+
+                Some(CursorShape {
+                    image: Image::new(w, h, w * 4, ColorFormat::Bgra8888, data.into()),
+                    xor: flags & CURSOR_XOR != 0,
+                    hotspot_x,
+                    hotspot_y,
+                })
+            } else {
+                None
+            };
+
+            Some(CursorState {
+                visible: flags & CURSOR_VISIBLE != 0,
+                pos_x,
+                pos_y,
+                shape,
+            })
+        } else {
+            None
+        };
+
+        let len = read_u32(r)?.as_usize();
+        ensure!(
+            len <= (width.as_usize() * height.as_usize()).max(1) * MAX_BYTES_PER_PIXEL,
+            "frame of {len} bytes is too large for {width}x{height}"
+        );
+
+        // grows as data arrives, so that a truncated file never allocates the whole length
+        let mut data = Vec::new();
+        r.take(len as u64).read_to_end(&mut data)?;
+        ensure!(
+            data.len() == len,
+            "replay file ended in the middle of a frame"
+        );
+
+        Ok(Some(ReplayFrame {
+            timestamp,
+            codec,
+            width,
+            height,
+            cursor,
+            data,
+        }))
+    }
+}
+
+#[derive(Debug)]
+pub struct ReplayWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> ReplayWriter<W> {
+    pub fn new(mut inner: W, resolution: &Resolution, refresh_rate: &RefreshRate) -> Result<Self> {
+        inner.write_all(MAGIC)?;
+        inner.write_all(&resolution.width.to_le_bytes())?;
+        inner.write_all(&resolution.height.to_le_bytes())?;
+        inner.write_all(&refresh_rate.num.to_le_bytes())?;
+        inner.write_all(&refresh_rate.den.to_le_bytes())?;
+
+        Ok(Self { inner })
+    }
+
+    /// Write an uncompressed frame. Only Bgra8888 and Rgb24 are supported.
+    pub fn write_image(
+        &mut self,
+        timestamp: Duration,
+        cursor: Option<&CursorState>,
+        img: Image<&[u8]>,
+    ) -> Result<()> {
+        let codec = img
+            .color_format
+            .into_video_codec()
+            .with_context(|| format!("{:?} cannot be recorded", img.color_format))?;
+
+        let line_len = (img.width * img.color_format.pixel_stride()).as_usize();
+        let stride = img.stride.as_usize();
+
+        let mut data = Vec::with_capacity(line_len * img.height.as_usize());
+        for i in 0..img.height.as_usize() {
+            data.extend_from_slice(&img.data[i * stride..i * stride + line_len]);
+        }
+
+        self.write_frame(timestamp, codec, img.width, img.height, cursor, &data)
+    }
+
+    /// Write an already encoded JPEG frame.
+    pub fn write_jpeg(
+        &mut self,
+        timestamp: Duration,
+        cursor: Option<&CursorState>,
+        width: u32,
+        height: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        self.write_frame(timestamp, VideoCodec::Jpeg, width, height, cursor, data)
+    }
+
+    fn write_frame(
+        &mut self,
+        timestamp: Duration,
+        codec: VideoCodec,
+        width: u32,
+        height: u32,
+        cursor: Option<&CursorState>,
+        data: &[u8],
+    ) -> Result<()> {
+        let w = &mut self.inner;
+
+        let timestamp: u64 = timestamp
+            .as_micros()
+            .try_into()
+            .context("timestamp too large")?;
+        let len: u32 = data.len().try_into().context("frame too large")?;
+
+        w.write_all(&timestamp.to_le_bytes())?;
+        w.write_all(&[codec.0 as u8])?;
+        w.write_all(&width.to_le_bytes())?;
+        w.write_all(&height.to_le_bytes())?;
+
+        match cursor {
+            Some(cursor) => {
+                let mut flags = CURSOR_PRESENT;
+                if cursor.visible {
+                    flags |= CURSOR_VISIBLE;
+                }
+                if let Some(shape) = cursor.shape.as_ref() {
+                    flags |= CURSOR_SHAPE;
+                    if shape.xor {
+                        flags |= CURSOR_XOR;
+                    }
+                }
+
+                w.write_all(&[flags])?;
+                w.write_all(&cursor.pos_x.to_le_bytes())?;
+                w.write_all(&cursor.pos_y.to_le_bytes())?;
+
+                if let Some(shape) = cursor.shape.as_ref() {
+                    let img = &shape.image;
+                    if img.color_format != ColorFormat::Bgra8888 {
+                        bail!("cursor shape must be in Bgra8888");
+                    }
+
+                    w.write_all(&shape.hotspot_x.to_bits().to_le_bytes())?;
+                    w.write_all(&shape.hotspot_y.to_bits().to_le_bytes())?;
+                    w.write_all(&img.width.to_le_bytes())?;
+                    w.write_all(&img.height.to_le_bytes())?;
+
+                    let line_len = (img.width * 4).as_usize();
+                    let stride = img.stride.as_usize();
+                    for i in 0..img.height.as_usize() {
+                        w.write_all(&img.data[i * stride..i * stride + line_len])?;
+                    }
+                }
+            }
+            None => w.write_all(&[0])?,
+        }
+
+        w.write_all(&len.to_le_bytes())?;
+        w.write_all(data)?;
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.inner.flush()?)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl ReplayFrame {
+    /// Decode frame into an image that can be fed into an encoder.
+    pub fn decode(&self) -> Result<ImageBuf> {
+        if self.codec == VideoCodec::Jpeg {
+            let mut decoder = JpegDecoder::new(self.width, self.height)?;
+            return decoder.decode(&self.data);
+        }
+
+        let color_format = ColorFormat::from_video_codec(self.codec)
+            .with_context(|| format!("unsupported codec {:?} in replay file", self.codec))?;
+
+        let stride = self.width * color_format.pixel_stride();
+        ensure!(
+            (stride * self.height).as_usize() <= self.data.len(),
+            "frame data is too short"
+        );
+
+        Ok(Image::new(
+            self.width,
+            self.height,
+            stride,
+            color_format,
+            self.data.clone().into(),
+        ))
+    }
+}
+
+fn ensure_dimensions(width: u32, height: u32) -> Result<()> {
+    ensure!(
+        width <= MAX_DIMENSION && height <= MAX_DIMENSION,
+        "frame of {width}x{height} is too large"
+    );
+    Ok(())
+}
+
+fn read_u8(r: &mut impl Read) -> Result<u8> {
+    let mut buf = [0u8; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32(r: &mut impl Read) -> Result<u32> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
@@ -1,35 +1,226 @@
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use std::sync::Arc;
+use std::time::Duration;
 
-use super::capture::CaptureFactoryWin32;
+use super::capture::{CaptureFactory, CaptureFactoryVirtual, CaptureStage};
 
-use crate::network::dto::video::Resolution;
-use crate::server::{normal_defaults, ServerConfig};
+use crate::network::dto::video::{DesktopCodec, RefreshRate, Resolution};
+use crate::server::{fallback_defaults, normal_defaults, DesktopCaptureMethod, ServerConfig};
 use crate::util::DesktopUpdate;
+#[cfg(feature = "av1")]
+use crate::video::encoder::av1::Av1Encoder;
+use crate::video::encoder::h264::H264Encoder;
+use crate::video::encoder::hybrid::HybridEncoder;
 use crate::video::encoder::jpeg::JpegEncoder;
-use crate::video::encoder::EncoderStage;
+use crate::video::encoder::lossless::LosslessEncoder;
+use crate::video::encoder::{EncodedFrame, EncoderStage, JpegRateControl};
+use crate::video::pacer::PacingStage;
+use crate::video::recorder::RecordStage;
+use crate::video::scaler::ScaleStage;
 
-pub type CapturePipelineOutput = (Resolution, flume::Receiver<DesktopUpdate<Vec<u8>>>);
+/// Encoded desktop, along with the stages producing it.
+/// The stages are stopped by `shutdown`, or once dropped.
+pub struct CapturePipelineOutput {
+    pub rx: flume::Receiver<DesktopUpdate<EncodedFrame>>,
+    pub capture: Arc<dyn CaptureStage>,
+    pub encoder: Arc<dyn EncoderStage>,
+}
 
-pub fn capture_pipeline(config: &ServerConfig) -> Result<CapturePipelineOutput> {
-    let mut capture_factory = CaptureFactoryWin32::new()?;
+impl CapturePipelineOutput {
+    /// Stop every stage and wait for their threads to finish, from the capture onwards.
+    /// Blocks, so call it off the async runtime.
+    pub fn shutdown(&self) {
+        self.capture.shutdown();
+        self.encoder.shutdown();
+    }
+}
 
-    let capture_method = config
-        .desktop_capture_method
-        .unwrap_or_else(|| normal_defaults().desktop_capture_method.unwrap());
+impl Drop for CapturePipelineOutput {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Encodes with `codec` if given, or the codec of the config otherwise.
+/// Downscales the desktop to fit in `target_resolution` if given.
+/// Paces frames at the lowest of `max_fps`, the config and the refresh rate of the monitor.
+///
+/// Falls back to the capture method of fallback profile,
+/// if the capture method of normal profile fails to start.
+pub fn capture_pipeline(
+    config: &ServerConfig,
+    monitor_id: &str,
+    codec: Option<DesktopCodec>,
+    target_resolution: Option<&Resolution>,
+    max_fps: Option<u32>,
+) -> Result<CapturePipelineOutput> {
+    let method = capture_method(config);
+    let codec = codec.unwrap_or(config.video.codec);
+    let max_fps = max_fps.map_or(config.video.pacing.max_fps, |x| {
+        x.min(config.video.pacing.max_fps)
+    });
+
+    ensure!(max_fps > 0, "frame rate must be positive");
+
+    if let Some(target) = target_resolution {
+        ensure!(
+            target.width > 0 && target.height > 0,
+            "target resolution must not be empty"
+        );
+    }
 
+    let err = match start_pipeline(
+        config,
+        method,
+        monitor_id,
+        codec,
+        target_resolution,
+        max_fps,
+    ) {
+        Ok(x) => return Ok(x),
+        Err(e) => e,
+    };
+
+    let normal = normal_defaults().desktop_capture_method.unwrap();
+    let fallback = fallback_defaults().desktop_capture_method.unwrap();
+
+    if method != normal || method == fallback {
+        return Err(err);
+    }
+
+    log::warn!("Capture method {method:?} failed to start. Falling back to {fallback:?}\n{err:?}");
+    start_pipeline(
+        config,
+        fallback,
+        monitor_id,
+        codec,
+        target_resolution,
+        max_fps,
+    )
+}
+
+fn start_pipeline(
+    config: &ServerConfig,
+    method: DesktopCaptureMethod,
+    monitor_id: &str,
+    codec: DesktopCodec,
+    target_resolution: Option<&Resolution>,
+    max_fps: u32,
+) -> Result<CapturePipelineOutput> {
     let (tx, rx) = flume::bounded(1);
 
-    let capture = capture_factory.start(capture_method, "")?;
-    let encode: Arc<dyn EncoderStage> = JpegEncoder::new(false)?;
+    let capture: Arc<dyn CaptureStage> =
+        capture_factory_for(config, method)?.start(method, monitor_id)?;
+    let encode: Arc<dyn EncoderStage> = match codec {
+        DesktopCodec::Jpeg => {
+            let jpeg = &config.video.jpeg;
+            JpegEncoder::new(JpegRateControl::new(
+                jpeg.min_quality,
+                jpeg.max_quality,
+                jpeg.yuv444,
+            ))?
+        }
+        DesktopCodec::H264 => H264Encoder::new(config.video.h264.bitrate_kbps)?,
+        #[cfg(feature = "av1")]
+        DesktopCodec::Av1 => {
+            let av1 = &config.video.av1;
+            Av1Encoder::new(av1.bitrate_kbps, av1.speed)?
+        }
+        #[cfg(not(feature = "av1"))]
+        DesktopCodec::Av1 => anyhow::bail!("AV1 support is not built in (enable `av1` feature)"),
+        DesktopCodec::Lossless => LosslessEncoder::new()?,
+        DesktopCodec::Hybrid => {
+            let jpeg = &config.video.jpeg;
+            HybridEncoder::new(JpegRateControl::new(
+                jpeg.min_quality,
+                jpeg.max_quality,
+                jpeg.yuv444,
+            ))?
+        }
+    };
+
+    let encode: Arc<dyn EncoderStage> = match target_resolution {
+        Some(target) => ScaleStage::new(encode, target.clone(), config.video.scale_filter)?,
+        None => encode,
+    };
+
+    // before downscaling, at the paced rate
+    let replay = &config.replay;
+    let encode: Arc<dyn EncoderStage> = match &replay.record_dir {
+        Some(dir) => RecordStage::new(
+            encode,
+            dir,
+            monitor_id,
+            RefreshRate {
+                num: max_fps,
+                den: 1,
+            },
+            replay.record_jpeg_quality,
+        )?,
+        None => encode,
+    };
+
+    let pacing = &config.video.pacing;
+    let pacer = PacingStage::new(
+        encode,
+        max_fps as f64,
+        Duration::from_millis(pacing.keepalive_ms.into()),
+        pacing.skip_unchanged,
+    )?;
+    let encode: Arc<dyn EncoderStage> = pacer.clone();
 
     capture.set_next_stage(Arc::clone(&encode))?;
     encode.set_output(tx);
 
-    Arc::clone(&capture).configure()?;
-    Arc::clone(&encode).configure()?;
+    // stages started so far are stopped along with the output if either fails
+    let output = CapturePipelineOutput {
+        rx,
+        capture,
+        encoder: encode,
+    };
+
+    Arc::clone(&output.capture).configure()?;
+    Arc::clone(&output.encoder).configure()?;
 
-    let resolution = capture.resolution()?;
+    match output.capture.refresh_rate() {
+        Ok(RefreshRate { num, den }) if num > 0 && den > 0 => {
+            pacer.cap_fps(num as f64 / den as f64)
+        }
+        _ => log::debug!("Refresh rate of the monitor is unknown, pacing at {max_fps} fps"),
+    }
+
+    Ok(output)
+}
+
+fn capture_method(config: &ServerConfig) -> DesktopCaptureMethod {
+    config
+        .desktop_capture_method
+        .unwrap_or_else(|| normal_defaults().desktop_capture_method.unwrap())
+}
+
+/// Create a capture factory which can start the configured capture method
+pub fn capture_factory(config: &ServerConfig) -> Result<Box<dyn CaptureFactory>> {
+    capture_factory_for(config, capture_method(config))
+}
+
+fn capture_factory_for(
+    config: &ServerConfig,
+    method: DesktopCaptureMethod,
+) -> Result<Box<dyn CaptureFactory>> {
+    match method {
+        method @ (DesktopCaptureMethod::Synthetic | DesktopCaptureMethod::Replay) => Ok(Box::new(
+            CaptureFactoryVirtual::new(method, config.synthetic.clone(), config.replay.clone())?,
+        )),
+        method => platform_capture_factory(method),
+    }
+}
+
+#[cfg(windows)]
+fn platform_capture_factory(_method: DesktopCaptureMethod) -> Result<Box<dyn CaptureFactory>> {
+    Ok(Box::new(super::capture::CaptureFactoryWin32::new()?))
+}
 
-    Ok((resolution, rx))
+#[cfg(not(windows))]
+fn platform_capture_factory(method: DesktopCaptureMethod) -> Result<Box<dyn CaptureFactory>> {
+    anyhow::bail!("capture method {method:?} is not available on this platform")
 }
@@ -0,0 +1,150 @@
+use crate::image::{convert_color, ColorFormat, ImageBuf};
+use crate::util::{AsUsize, FramePool};
+use crate::video::decoder::DecoderStage;
+use anyhow::{bail, Context, Result};
+use dav1d::{Decoder, Picture, PixelLayout, PlanarImageComponent, Settings};
+
+/// AV1 decoder by dav1d, outputting every frame as soon as it is received.
+pub struct Av1Decoder {
+    width: u32,
+    height: u32,
+    decoder: Decoder,
+    pool: FramePool,
+}
+
+impl std::fmt::Debug for Av1Decoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Av1Decoder")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Av1Decoder {
+    pub fn new(w: u32, h: u32) -> Result<Self> {
+        let mut settings = Settings::new();
+        settings.set_max_frame_delay(1);
+
+        let decoder = Decoder::with_settings(&settings).context("failed to create AV1 decoder")?;
+
+        Ok(Av1Decoder {
+            width: w,
+            height: h,
+            decoder,
+            pool: FramePool::default(),
+        })
+    }
+
+    /// Copies the planes out of dav1d and converts them to BGRA
+    fn convert(&self, pic: &Picture) -> Result<ImageBuf> {
+        if pic.pixel_layout() != PixelLayout::I420 || pic.bit_depth() != 8 {
+            bail!(
+                "unsupported AV1 picture format {:?} {}bit",
+                pic.pixel_layout(),
+                pic.bit_depth()
+            );
+        }
+
+        let w = pic.width();
+        let h = pic.height();
+
+        let (y_plane, u_plane, v_plane) = (
+            pic.plane(PlanarImageComponent::Y),
+            pic.plane(PlanarImageComponent::U),
+            pic.plane(PlanarImageComponent::V),
+        );
+        let (y_plane, u_plane, v_plane): (&[u8], &[u8], &[u8]) =
+            (y_plane.as_ref(), u_plane.as_ref(), v_plane.as_ref());
+        let y_stride = pic.stride(PlanarImageComponent::Y).as_usize();
+        let uv_stride = pic.stride(PlanarImageComponent::U).as_usize();
+
+        let mut yuv = ImageBuf::alloc_in(&self.pool, w, h, None, ColorFormat::I420);
+        let stride = yuv.stride.as_usize();
+        let (luma, chroma) = yuv.data.split_at_mut(stride * h.as_usize());
+        let (cb, cr) = chroma.split_at_mut(chroma.len() / 2);
+
+        let (chroma_width, chroma_height) = (w.div_ceil(2).as_usize(), h.div_ceil(2).as_usize());
+        copy_plane(y_plane, y_stride, luma, stride, w.as_usize(), h.as_usize());
+        copy_plane(
+            u_plane,
+            uv_stride,
+            cb,
+            stride.div_ceil(2),
+            chroma_width,
+            chroma_height,
+        );
+        copy_plane(
+            v_plane,
+            uv_stride,
+            cr,
+            stride.div_ceil(2),
+            chroma_width,
+            chroma_height,
+        );
+
+        // BT.601 limited range, matching the encoder
+        let mut img = ImageBuf::alloc_in(&self.pool, w, h, Some(w * 4), ColorFormat::Bgra8888);
+        convert_color(&yuv, &mut img);
+
+        Ok(img)
+    }
+}
+
+impl DecoderStage for Av1Decoder {
+    fn resolution(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Every frame is expected to complete a picture.
+    fn decode(&mut self, data: &[u8]) -> Result<ImageBuf> {
+        self.decode_frame(data)?
+            .context("AV1 frame did not complete a picture")
+    }
+
+    /// Callers should request a keyframe if it fails, as following frames depend on it.
+    /// Frames not completing a picture yet leave the previous one shown.
+    fn decode_frame(&mut self, data: &[u8]) -> Result<Option<ImageBuf>> {
+        // kept pending inside the decoder until pictures are taken out
+        let mut pending = is_pending(self.decoder.send_data(data.to_vec(), None, None, None))?;
+
+        // only the latest picture is shown
+        let mut latest = None;
+        loop {
+            match self.decoder.get_picture() {
+                Ok(x) => latest = Some(x),
+                Err(dav1d::Error::Again) => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            if !pending {
+                break;
+            }
+            pending = is_pending(self.decoder.send_pending_data())?;
+        }
+
+        latest.map(|x| self.convert(&x)).transpose()
+    }
+}
+
+/// Whether the data was not consumed yet, which is not an error
+fn is_pending(result: Result<(), dav1d::Error>) -> Result<bool> {
+    match result {
+        Ok(()) => Ok(false),
+        Err(dav1d::Error::Again) => Ok(true),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn copy_plane(
+    src: &[u8],
+    src_stride: usize,
+    dst: &mut [u8],
+    dst_stride: usize,
+    width: usize,
+    rows: usize,
+) {
+    for y in 0..rows {
+        dst[y * dst_stride..][..width].copy_from_slice(&src[y * src_stride..][..width]);
+    }
+}
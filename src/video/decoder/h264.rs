@@ -0,0 +1,73 @@
+use crate::image::{ColorFormat, Image, ImageBuf};
+use crate::util::FramePool;
+use crate::video::decoder::DecoderStage;
+use anyhow::{Context, Result};
+use openh264::decoder::Decoder;
+use openh264::formats::YUVSource;
+
+/// Software H.264 decoder, running entirely on CPU.
+pub struct H264Decoder {
+    width: u32,
+    height: u32,
+    decoder: Decoder,
+    pool: FramePool,
+}
+
+impl std::fmt::Debug for H264Decoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("H264Decoder")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
+
+impl H264Decoder {
+    pub fn new(w: u32, h: u32) -> Result<Self> {
+        let decoder = Decoder::new().context("failed to create H.264 decoder")?;
+
+        Ok(H264Decoder {
+            width: w,
+            height: h,
+            decoder,
+            pool: FramePool::default(),
+        })
+    }
+}
+
+impl DecoderStage for H264Decoder {
+    fn resolution(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Every frame is expected to complete a picture.
+    fn decode(&mut self, data: &[u8]) -> Result<ImageBuf> {
+        self.decode_frame(data)?
+            .context("H.264 frame did not complete a picture")
+    }
+
+    /// Callers should request a keyframe if it fails, as following frames depend on it.
+    /// Frames not completing a picture, e.g. parameter sets alone, leave the previous one shown.
+    fn decode_frame(&mut self, data: &[u8]) -> Result<Option<ImageBuf>> {
+        let Some(yuv) = self.decoder.decode(data)? else {
+            return Ok(None);
+        };
+
+        let (w, h) = yuv.dimensions();
+        let mut img = self.pool.buffer(w * h * 4);
+        yuv.write_rgba8(&mut img);
+
+        // viewer uploads BGRA as is
+        for px in img.chunks_exact_mut(4) {
+            px.swap(0, 2);
+        }
+
+        Ok(Some(Image::new(
+            w as u32,
+            h as u32,
+            w as u32 * 4,
+            ColorFormat::Bgra8888,
+            img,
+        )))
+    }
+}
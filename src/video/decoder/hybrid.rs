@@ -0,0 +1,34 @@
+use crate::image::ImageBuf;
+use crate::video::decoder::DecoderStage;
+use crate::video::hybrid::HybridFrameDecoder;
+use anyhow::Result;
+
+/// Decoder of the hybrid codec, compositing lossless and JPEG tiles into one image.
+#[derive(Debug)]
+pub struct HybridDecoder {
+    width: u32,
+    height: u32,
+    decoder: HybridFrameDecoder,
+}
+
+impl HybridDecoder {
+    pub fn new(w: u32, h: u32) -> Result<Self> {
+        Ok(HybridDecoder {
+            width: w,
+            height: h,
+            decoder: HybridFrameDecoder::new(),
+        })
+    }
+}
+
+impl DecoderStage for HybridDecoder {
+    fn resolution(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Frames other than key frames only patch the previous one.
+    /// Callers should request a keyframe if it fails.
+    fn decode(&mut self, data: &[u8]) -> Result<ImageBuf> {
+        self.decoder.decode(data)
+    }
+}
@@ -1,6 +1,9 @@
 use crate::image::{ColorFormat, Image, ImageBuf};
+use crate::util::FramePool;
 use crate::video::decoder::DecoderStage;
-use anyhow::{ensure, Result};
+use crate::video::encoder::jpeg::TILE_POOL_SIZE;
+use anyhow::{bail, ensure, Context, Result};
+use rayon::prelude::*;
 use zune_jpeg::zune_core::colorspace::ColorSpace;
 use zune_jpeg::zune_core::options::DecoderOptions;
 use zune_jpeg::JpegDecoder as JDecoder;
@@ -9,6 +12,7 @@ use zune_jpeg::JpegDecoder as JDecoder;
 pub struct JpegDecoder {
     width: u16,
     height: u16,
+    pool: FramePool,
 }
 
 impl JpegDecoder {
@@ -21,6 +25,7 @@ impl JpegDecoder {
         Ok(JpegDecoder {
             width: w as u16,
             height: h as u16,
+            pool: FramePool::new(TILE_POOL_SIZE),
         })
     }
 }
@@ -30,34 +35,55 @@ impl DecoderStage for JpegDecoder {
         (self.width as u32, self.height as u32)
     }
 
+    /// Frames are decoded in their own resolution, which may differ from `resolution`
+    /// while the stream is being re-announced.
     fn decode(&mut self, data: &[u8]) -> Result<ImageBuf> {
-        let opts = DecoderOptions::new_fast().jpeg_set_out_colorspace(ColorSpace::BGRA);
-        let mut decoder = JDecoder::new_with_options(data, opts);
+        decode_img(data, ColorFormat::Bgra8888, &self.pool)
+    }
 
-        decoder.decode_headers()?;
+    /// Every tile is a JPEG image of its own, decoded on the thread pool.
+    fn decode_tiles(&mut self, tiles: &[&[u8]]) -> Result<Vec<ImageBuf>> {
+        tiles
+            .par_iter()
+            .map(|x| decode_img(x, ColorFormat::Bgra8888, &self.pool))
+            .collect()
+    }
+}
 
-        let (w, h) = decoder.dimensions().expect("headers already decoded");
+/// Decode into a tightly packed image of `color_format`, in a buffer of `pool`.
+pub(crate) fn decode_img(
+    data: &[u8],
+    color_format: ColorFormat,
+    pool: &FramePool,
+) -> Result<ImageBuf> {
+    let colorspace = match color_format {
+        ColorFormat::Bgra8888 => ColorSpace::BGRA,
+        ColorFormat::Rgba8888 => ColorSpace::RGBA,
+        ColorFormat::Rgb24 => ColorSpace::RGB,
+        other => bail!("unsupported color format {other:?}"),
+    };
 
-        ensure!(w < u16::MAX as usize, "image width(={}) too large", w);
-        ensure!(h < u16::MAX as usize, "image height(={}) too large", h);
+    let opts = DecoderOptions::new_fast().jpeg_set_out_colorspace(colorspace);
+    let mut decoder = JDecoder::new_with_options(data, opts);
 
-        ensure!(
-            self.width == w as u16 && self.height == h as u16,
-            "image resolution changed from {}x{} to {}x{}",
-            self.width,
-            self.height,
-            w,
-            h
-        );
+    decoder.decode_headers()?;
 
-        let img = decoder.decode()?;
+    let (w, h) = decoder.dimensions().expect("headers already decoded");
 
-        Ok(Image::new(
-            self.width as u32,
-            self.height as u32,
-            self.width as u32 * 4,
-            ColorFormat::Bgra8888,
-            img,
-        ))
-    }
+    ensure!(w < u16::MAX as usize, "image width(={}) too large", w);
+    ensure!(h < u16::MAX as usize, "image height(={}) too large", h);
+
+    let len = decoder
+        .output_buffer_size()
+        .context("unknown output size")?;
+    let mut img = pool.buffer(len);
+    decoder.decode_into(&mut img)?;
+
+    Ok(Image::new(
+        w as u32,
+        h as u32,
+        w as u32 * color_format.pixel_stride(),
+        color_format,
+        img,
+    ))
 }
@@ -0,0 +1,34 @@
+use crate::image::ImageBuf;
+use crate::video::decoder::DecoderStage;
+use crate::video::zstd_delta::ZstdDeltaDecoder;
+use anyhow::Result;
+
+/// Decoder of the lossless codec, outputting exactly the pixels captured by the server.
+#[derive(Debug)]
+pub struct LosslessDecoder {
+    width: u32,
+    height: u32,
+    decoder: ZstdDeltaDecoder,
+}
+
+impl LosslessDecoder {
+    pub fn new(w: u32, h: u32) -> Result<Self> {
+        Ok(LosslessDecoder {
+            width: w,
+            height: h,
+            decoder: ZstdDeltaDecoder::new(),
+        })
+    }
+}
+
+impl DecoderStage for LosslessDecoder {
+    fn resolution(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Delta frames depend on the previous one.
+    /// Callers should request a keyframe if it fails.
+    fn decode(&mut self, data: &[u8]) -> Result<ImageBuf> {
+        self.decoder.decode(data)
+    }
+}
@@ -1,4 +1,9 @@
+#[cfg(feature = "av1")]
+pub mod av1;
+pub mod h264;
+pub mod hybrid;
 pub mod jpeg;
+pub mod lossless;
 mod stage;
 
 pub use stage::DecoderStage;
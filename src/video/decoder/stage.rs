@@ -6,6 +6,18 @@ pub trait DecoderStage: Send + Debug {
     fn resolution(&self) -> (u32, u32);
     fn decode(&mut self, data: &[u8]) -> Result<ImageBuf>;
 
+    /// Decode a frame of a stream.
+    /// None if it yields no new picture, so the previous one stays on screen.
+    fn decode_frame(&mut self, data: &[u8]) -> Result<Option<ImageBuf>> {
+        self.decode(data).map(Some)
+    }
+
+    /// Decode the tiles of a frame, in order.
+    /// Decoders of self-contained tiles may decode them in parallel.
+    fn decode_tiles(&mut self, tiles: &[&[u8]]) -> Result<Vec<ImageBuf>> {
+        tiles.iter().map(|x| self.decode(x)).collect()
+    }
+
     fn width(&self) -> u32 {
         self.resolution().0
     }
@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{bail, ensure, Context as _, Result};
+use rav1e::prelude::*;
+
+use crate::image::{convert_color, ColorFormat, Image, ImageBuf};
+use crate::network::dto::video::Resolution;
+use crate::schema::video::{ChromaSubsampling, VideoCodec};
+use crate::util::{AsUsize, DesktopUpdate, StageWorker};
+use crate::video::encoder::rate_control::DeliveryReport;
+use crate::video::encoder::stage::{EncodedFrame, EncoderStage};
+
+/// AV1 encoder by rav1e, configured for realtime use.
+#[derive(Debug)]
+pub struct Av1Encoder {
+    input: flume::Sender<DesktopUpdate<ImageBuf>>,
+    output: flume::Sender<flume::Sender<DesktopUpdate<EncodedFrame>>>,
+    keyframe_requested: Arc<AtomicBool>,
+    worker: StageWorker,
+}
+
+impl Av1Encoder {
+    /// `speed` is the rav1e speed preset from 0 (slowest) to 10 (fastest).
+    pub fn new(bitrate_kbps: u32, speed: u8) -> Result<Arc<Self>> {
+        let (tx, rx) = flume::bounded::<DesktopUpdate<ImageBuf>>(1);
+        let (output_tx, output_rx) = flume::bounded(1);
+        let keyframe_requested = Arc::new(AtomicBool::new(false));
+        let worker_keyframe = Arc::clone(&keyframe_requested);
+
+        let worker = StageWorker::new("av1_encoder");
+        worker.start(move |stop| {
+            // given by set_output
+            let tx: flume::Sender<_> = match stop.recv(&output_rx) {
+                Some(x) => x,
+                None => return Ok(()),
+            };
+
+            // recreated whenever the resolution changes, starting with a keyframe
+            let mut encoder: Option<(Resolution, Context<u8>)> = None;
+
+            // updates sent to rav1e, waiting for their packets in order
+            let mut pending: VecDeque<DesktopUpdate<()>> = VecDeque::new();
+
+            while let Some(mut update) = stop.recv(&rx) {
+                update.timings.encode_begin = update.timings.elapsed_since_capture().unwrap();
+                let (update, img) = update.split();
+
+                let resolution = Resolution {
+                    width: img.width,
+                    height: img.height,
+                };
+
+                let ctx = match &mut encoder {
+                    Some((res, ctx)) if *res == resolution => ctx,
+                    _ => {
+                        pending.clear();
+                        let created = create_context(&resolution, bitrate_kbps, speed)?;
+                        &mut encoder.insert((resolution.clone(), created)).1
+                    }
+                };
+
+                let mut frame = ctx.new_frame();
+                fill_frame(&mut frame, img.as_data_ref())?;
+
+                let params = FrameParameters {
+                    frame_type_override: if worker_keyframe.swap(false, Ordering::Relaxed) {
+                        FrameTypeOverride::Key
+                    } else {
+                        FrameTypeOverride::No
+                    },
+                    ..Default::default()
+                };
+
+                ctx.send_frame((frame, params))
+                    .context("failed to send frame to AV1 encoder")?;
+                pending.push_back(update);
+
+                loop {
+                    let packet = match ctx.receive_packet() {
+                        Ok(x) => x,
+                        Err(EncoderStatus::Encoded) => continue,
+                        Err(EncoderStatus::NeedMoreData) => break,
+                        Err(e) => bail!("AV1 encoder failed: {e}"),
+                    };
+
+                    let mut update = pending
+                        .pop_front()
+                        .expect("every packet has its input frame");
+                    update.timings.encode_end = update.timings.elapsed_since_capture().unwrap();
+
+                    let encoded = EncodedFrame {
+                        codec: VideoCodec::Av1,
+                        resolution: resolution.clone(),
+                        tiles: None,
+                        quality: 0,
+                        subsampling: ChromaSubsampling::Yuv420,
+                        data: packet.data,
+                    };
+
+                    if !stop.send(&tx, update.with_desktop(encoded)) {
+                        return Ok(());
+                    }
+                }
+            }
+
+            Ok(())
+        })?;
+
+        Ok(Arc::new(Av1Encoder {
+            input: tx,
+            output: output_tx,
+            keyframe_requested,
+            worker,
+        }))
+    }
+}
+
+impl EncoderStage for Av1Encoder {
+    fn configured(&self) -> bool {
+        true
+    }
+
+    fn configure(self: Arc<Self>) -> Result<()> {
+        Ok(())
+    }
+
+    fn input(&self) -> flume::Sender<DesktopUpdate<ImageBuf>> {
+        self.input.clone()
+    }
+
+    fn set_output(&self, tx: flume::Sender<DesktopUpdate<EncodedFrame>>) {
+        // taken once by the worker
+        let _ = self.output.try_send(tx);
+    }
+
+    fn request_keyframe(&self) {
+        self.keyframe_requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Bitrate is fixed by the config.
+    fn report_delivery(&self, _report: &DeliveryReport) {}
+
+    fn shutdown(&self) {
+        self.worker.stop();
+    }
+}
+
+/// Emits a packet for every frame as soon as possible.
+/// Keyframes are only sent at start and on request.
+fn create_context(resolution: &Resolution, bitrate_kbps: u32, speed: u8) -> Result<Context<u8>> {
+    let mut speed_settings = SpeedSettings::from_preset(speed);
+    speed_settings.rdo_lookahead_frames = 1;
+    speed_settings.scene_detection_mode = SceneDetectionSpeed::None;
+
+    let enc = EncoderConfig {
+        width: resolution.width.as_usize(),
+        height: resolution.height.as_usize(),
+        time_base: Rational::new(1, 60),
+        chroma_sampling: ChromaSampling::Cs420,
+        pixel_range: PixelRange::Limited,
+        low_latency: true,
+        min_key_frame_interval: 0,
+        max_key_frame_interval: u64::MAX,
+        bitrate: bitrate_kbps
+            .saturating_mul(1000)
+            .try_into()
+            .context("bitrate too high")?,
+        speed_settings,
+        ..EncoderConfig::with_speed_preset(speed)
+    };
+
+    Config::new()
+        .with_encoder_config(enc)
+        .new_context()
+        .context("failed to create AV1 encoder")
+}
+
+/// Converts to BT.601 limited range YUV 4:2:0.
+fn fill_frame(frame: &mut Frame<u8>, img: Image<&[u8]>) -> Result<()> {
+    ensure!(
+        !img.color_format.is_planar(),
+        "unsupported color format {:?}",
+        img.color_format
+    );
+
+    let mut yuv = ImageBuf::alloc(img.width, img.height, None, ColorFormat::I420);
+    convert_color(&img, &mut yuv);
+
+    let stride = yuv.stride.as_usize();
+    let (luma, chroma) = yuv.data.split_at(stride * img.height.as_usize());
+    let (cb, cr) = chroma.split_at(chroma.len() / 2);
+
+    frame.planes[0].copy_from_raw_u8(luma, stride, 1);
+    frame.planes[1].copy_from_raw_u8(cb, stride.div_ceil(2), 1);
+    frame.planes[2].copy_from_raw_u8(cr, stride.div_ceil(2), 1);
+
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{bail, ensure, Context, Result};
+use openh264::encoder::{Encoder, EncoderConfig, UsageType};
+use openh264::formats::{BgraSliceU8, RgbSliceU8, RgbaSliceU8, YUVBuffer};
+use openh264::OpenH264API;
+
+use crate::image::{ColorFormat, ImageBuf, Rect};
+use crate::network::dto::video::Resolution;
+use crate::schema::video::{ChromaSubsampling, VideoCodec};
+use crate::util::{DesktopUpdate, StageWorker};
+use crate::video::encoder::rate_control::DeliveryReport;
+use crate::video::encoder::stage::{EncodedFrame, EncoderStage};
+
+/// Software H.264 encoder, running entirely on CPU.
+#[derive(Debug)]
+pub struct H264Encoder {
+    input: flume::Sender<DesktopUpdate<ImageBuf>>,
+    output: flume::Sender<flume::Sender<DesktopUpdate<EncodedFrame>>>,
+    keyframe_requested: Arc<AtomicBool>,
+    worker: StageWorker,
+}
+
+impl H264Encoder {
+    pub fn new(bitrate_kbps: u32) -> Result<Arc<Self>> {
+        let (tx, rx) = flume::bounded::<DesktopUpdate<ImageBuf>>(1);
+        let (output_tx, output_rx) = flume::bounded(1);
+        let keyframe_requested = Arc::new(AtomicBool::new(false));
+        let worker_keyframe = Arc::clone(&keyframe_requested);
+
+        let worker = StageWorker::new("h264_encoder");
+        worker.start(move |stop| {
+            // given by set_output
+            let tx: flume::Sender<_> = match stop.recv(&output_rx) {
+                Some(x) => x,
+                None => return Ok(()),
+            };
+
+            // recreated whenever the resolution changes, starting with a keyframe
+            let mut encoder: Option<(Resolution, Encoder)> = None;
+
+            while let Some(mut update) = stop.recv(&rx) {
+                update.timings.encode_begin = update.timings.elapsed_since_capture().unwrap();
+
+                let resolution = Resolution {
+                    width: update.desktop.width,
+                    height: update.desktop.height,
+                };
+
+                let encoder = match &mut encoder {
+                    Some((res, encoder)) if *res == resolution => encoder,
+                    _ => {
+                        let created = create_encoder(&resolution, bitrate_kbps)?;
+                        &mut encoder.insert((resolution.clone(), created)).1
+                    }
+                };
+
+                if worker_keyframe.swap(false, Ordering::Relaxed) {
+                    encoder.force_intra_frame();
+                }
+
+                let data = encode_img(encoder, &update.desktop)?;
+                if data.is_empty() {
+                    // skipped by the encoder, nothing to show
+                    continue;
+                }
+
+                let encoded = EncodedFrame {
+                    codec: VideoCodec::H264,
+                    resolution,
+                    tiles: None,
+                    quality: 0,
+                    subsampling: ChromaSubsampling::Yuv420,
+                    data,
+                };
+                update.timings.encode_end = update.timings.elapsed_since_capture().unwrap();
+
+                if !stop.send(&tx, update.with_desktop(encoded)) {
+                    return Ok(());
+                }
+            }
+
+            Ok(())
+        })?;
+
+        Ok(Arc::new(H264Encoder {
+            input: tx,
+            output: output_tx,
+            keyframe_requested,
+            worker,
+        }))
+    }
+}
+
+impl EncoderStage for H264Encoder {
+    fn configured(&self) -> bool {
+        true
+    }
+
+    fn configure(self: Arc<Self>) -> Result<()> {
+        Ok(())
+    }
+
+    fn input(&self) -> flume::Sender<DesktopUpdate<ImageBuf>> {
+        self.input.clone()
+    }
+
+    fn set_output(&self, tx: flume::Sender<DesktopUpdate<EncodedFrame>>) {
+        // taken once by the worker
+        let _ = self.output.try_send(tx);
+    }
+
+    fn request_keyframe(&self) {
+        self.keyframe_requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Bitrate is fixed by the config.
+    fn report_delivery(&self, _report: &DeliveryReport) {}
+
+    fn shutdown(&self) {
+        self.worker.stop();
+    }
+}
+
+fn create_encoder(resolution: &Resolution, bitrate_kbps: u32) -> Result<Encoder> {
+    ensure!(
+        resolution.width.is_multiple_of(2) && resolution.height.is_multiple_of(2),
+        "H.264 requires even resolution (got {}x{})",
+        resolution.width,
+        resolution.height
+    );
+
+    // every captured frame is sent, so the client never waits on a skipped one
+    let config = EncoderConfig::new()
+        .set_bitrate_bps(bitrate_kbps.saturating_mul(1000))
+        .enable_skip_frame(false)
+        .usage_type(UsageType::ScreenContentRealTime);
+
+    Encoder::with_api_config(OpenH264API::from_source(), config)
+        .context("failed to create H.264 encoder")
+}
+
+fn encode_img(encoder: &mut Encoder, img: &ImageBuf) -> Result<Vec<u8>> {
+    let pixel = img.color_format.pixel_stride();
+
+    // source slices are assumed to be tightly packed
+    let packed;
+    let img = if img.stride == img.width * pixel {
+        img
+    } else {
+        packed = img.crop(&Rect::new(0, 0, img.width, img.height));
+        &packed
+    };
+
+    let dimensions = (img.width as usize, img.height as usize);
+    let yuv = match img.color_format {
+        ColorFormat::Bgra8888 => {
+            YUVBuffer::from_rgb_source(BgraSliceU8::new(&img.data, dimensions))
+        }
+        ColorFormat::Rgba8888 => {
+            YUVBuffer::from_rgb_source(RgbaSliceU8::new(&img.data, dimensions))
+        }
+        ColorFormat::Rgb24 => YUVBuffer::from_rgb_source(RgbSliceU8::new(&img.data, dimensions)),
+        other => bail!("unsupported color format {other:?}"),
+    };
+
+    let bitstream = encoder.encode(&yuv)?;
+
+    Ok(bitstream.to_vec())
+}
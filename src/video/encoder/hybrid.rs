@@ -0,0 +1,118 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+
+use crate::image::ImageBuf;
+use crate::network::dto::video::Resolution;
+use crate::schema::video::{ChromaSubsampling, VideoCodec};
+use crate::util::{DesktopUpdate, StageWorker};
+use crate::video::encoder::rate_control::{DeliveryReport, JpegRateControl};
+use crate::video::encoder::stage::{EncodedFrame, EncoderStage};
+use crate::video::hybrid::HybridFrameEncoder;
+
+/// Sends text and line art losslessly, and the rest of the desktop as JPEG.
+///
+/// The JPEG quality adapts to the link like `JpegEncoder`.
+#[derive(Debug)]
+pub struct HybridEncoder {
+    input: flume::Sender<DesktopUpdate<ImageBuf>>,
+    output: flume::Sender<flume::Sender<DesktopUpdate<EncodedFrame>>>,
+    keyframe_requested: Arc<AtomicBool>,
+    rate: Arc<Mutex<JpegRateControl>>,
+    worker: StageWorker,
+}
+
+impl HybridEncoder {
+    pub fn new(rate: JpegRateControl) -> Result<Arc<Self>> {
+        let (tx, rx) = flume::bounded::<DesktopUpdate<ImageBuf>>(1);
+        let (output_tx, output_rx) = flume::bounded(1);
+        let keyframe_requested = Arc::new(AtomicBool::new(false));
+        let rate = Arc::new(Mutex::new(rate));
+        let worker_keyframe = Arc::clone(&keyframe_requested);
+        let worker_rate = Arc::clone(&rate);
+
+        let worker = StageWorker::new("hybrid_encoder");
+        worker.start(move |stop| {
+            // given by set_output
+            let tx: flume::Sender<_> = match stop.recv(&output_rx) {
+                Some(x) => x,
+                None => return Ok(()),
+            };
+
+            let mut encoder = HybridFrameEncoder::new();
+
+            while let Some(mut update) = stop.recv(&rx) {
+                update.timings.encode_begin = update.timings.elapsed_since_capture().unwrap();
+                let (mut update, img) = update.split();
+
+                let key = worker_keyframe.swap(false, Ordering::Relaxed);
+                let params = worker_rate.lock().unwrap().params();
+                let data = encoder.encode(&img, update.damage.as_deref(), key, params)?;
+
+                let encoded = EncodedFrame {
+                    codec: VideoCodec::Hybrid,
+                    resolution: Resolution {
+                        width: img.width,
+                        height: img.height,
+                    },
+                    tiles: None,
+                    quality: params.quality,
+                    subsampling: if params.yuv444 {
+                        ChromaSubsampling::Yuv444
+                    } else {
+                        ChromaSubsampling::Yuv420
+                    },
+                    data,
+                };
+                update.timings.encode_end = update.timings.elapsed_since_capture().unwrap();
+
+                if !stop.send(&tx, update.with_desktop(encoded)) {
+                    return Ok(());
+                }
+            }
+
+            Ok(())
+        })?;
+
+        Ok(Arc::new(HybridEncoder {
+            input: tx,
+            output: output_tx,
+            keyframe_requested,
+            rate,
+            worker,
+        }))
+    }
+}
+
+impl EncoderStage for HybridEncoder {
+    fn configured(&self) -> bool {
+        true
+    }
+
+    fn configure(self: Arc<Self>) -> Result<()> {
+        Ok(())
+    }
+
+    fn input(&self) -> flume::Sender<DesktopUpdate<ImageBuf>> {
+        self.input.clone()
+    }
+
+    fn set_output(&self, tx: flume::Sender<DesktopUpdate<EncodedFrame>>) {
+        // taken once by the worker
+        let _ = self.output.try_send(tx);
+    }
+
+    fn request_keyframe(&self) {
+        self.keyframe_requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Adapts JPEG tiles only, as lossless tiles have nothing to trade for bandwidth.
+    fn report_delivery(&self, report: &DeliveryReport) {
+        self.rate.lock().unwrap().on_delivery(report);
+    }
+
+    fn shutdown(&self) {
+        self.worker.stop();
+    }
+}
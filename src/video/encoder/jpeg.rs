@@ -1,69 +1,92 @@
-use std::io::Cursor;
-use std::sync::{Arc, Condvar, Mutex};
-use std::thread::JoinHandle;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 use anyhow::{Context, Result};
 use jpeg_encoder::{ColorType, Encoder, SamplingFactor};
+use rayon::prelude::*;
 
-use crate::image::{ColorFormat, Image, ImageBuf};
-use crate::util::DesktopUpdate;
-use crate::video::encoder::stage::EncoderStage;
+use crate::image::{ColorFormat, Image, ImageBuf, Rect};
+use crate::network::dto::video::Resolution;
+use crate::schema::video::{ChromaSubsampling, VideoCodec};
+use crate::util::{DesktopUpdate, FramePool, PooledBuf, StageWorker};
+use crate::video::encoder::rate_control::{DeliveryReport, JpegParams, JpegRateControl};
+use crate::video::encoder::stage::{EncodedFrame, EncodedTile, EncoderStage};
+use crate::video::encoder::tiles::changed_tiles;
+
+/// Edge length of the tiles compared against the previous frame
+const TILE_SIZE: u32 = 64;
+
+/// Send the whole frame once this fraction of its area has changed
+const FULL_FRAME_RATIO: f64 = 0.5;
+
+/// Height of the strips a whole frame is split into, to be encoded and decoded in parallel.
+/// A multiple of 16 keeps chroma blocks of 4:2:0 from straddling strips.
+pub const STRIP_HEIGHT: u32 = 128;
+
+/// Buffers kept for tiles and their output, enough for a 4K frame in strips
+pub const TILE_POOL_SIZE: usize = 64;
 
 #[derive(Debug)]
 pub struct JpegEncoder {
     input: flume::Sender<DesktopUpdate<ImageBuf>>,
-    output: Mutex<Option<flume::Sender<DesktopUpdate<Vec<u8>>>>>,
-    configured: Condvar,
-    _worker: JoinHandle<Result<()>>,
+    output: flume::Sender<flume::Sender<DesktopUpdate<EncodedFrame>>>,
+    keyframe_requested: Arc<AtomicBool>,
+    rate: Arc<Mutex<JpegRateControl>>,
+    worker: StageWorker,
 }
 
 impl JpegEncoder {
-    pub fn new(yuv444: bool) -> Result<Arc<Self>> {
+    pub fn new(rate: JpegRateControl) -> Result<Arc<Self>> {
         let (tx, rx) = flume::bounded::<DesktopUpdate<ImageBuf>>(1);
-
-        let instance = Arc::new_cyclic(move |ptr_outer| {
-            let ptr = ptr_outer.clone();
-
-            let worker = std::thread::spawn(move || {
-                let this: Arc<Self> = loop {
-                    match ptr.upgrade() {
-                        Some(x) => break x,
-                        None => std::thread::sleep(Duration::from_millis(10)),
-                    }
-                };
-
-                let tx = {
-                    let mut guard = this.output.lock().unwrap();
-                    while guard.is_none() {
-                        guard = this.configured.wait(guard).unwrap();
-                    }
-
-                    guard.as_ref().expect("checked above").clone()
-                };
-
-                while let Ok(mut update) = rx.recv() {
-                    update.timings.encode_begin = update.timings.elapsed_since_capture().unwrap();
-                    let encoded = encode_img(update.desktop.as_data_ref(), yuv444)?;
-                    update.timings.encode_end = update.timings.elapsed_since_capture().unwrap();
-
-                    if tx.send(update.with_desktop(encoded)).is_err() {
-                        return Ok(());
-                    }
+        let (output_tx, output_rx) = flume::bounded(1);
+        let keyframe_requested = Arc::new(AtomicBool::new(false));
+        let rate = Arc::new(Mutex::new(rate));
+        let worker_keyframe = Arc::clone(&keyframe_requested);
+        let worker_rate = Arc::clone(&rate);
+
+        let worker = StageWorker::new("jpeg_encoder");
+        worker.start(move |stop| {
+            // given by set_output
+            let tx: flume::Sender<_> = match stop.recv(&output_rx) {
+                Some(x) => x,
+                None => return Ok(()),
+            };
+
+            let mut prev: Option<ImageBuf> = None;
+            // for the tiles cropped out of frames and their encoded output
+            let pool = FramePool::new(TILE_POOL_SIZE);
+
+            while let Some(mut update) = stop.recv(&rx) {
+                update.timings.encode_begin = update.timings.elapsed_since_capture().unwrap();
+                let (mut update, img) = update.split();
+
+                // whole frame is a keyframe
+                if worker_keyframe.swap(false, Ordering::Relaxed) {
+                    prev = None;
                 }
 
-                Ok(())
-            });
+                let params = worker_rate.lock().unwrap().params();
+                let encoded =
+                    encode_frame(&img, update.damage.as_deref(), prev.as_ref(), params, &pool)?;
+                prev = Some(img);
+                update.timings.encode_end = update.timings.elapsed_since_capture().unwrap();
 
-            JpegEncoder {
-                input: tx,
-                output: Mutex::new(None),
-                configured: Condvar::new(),
-                _worker: worker,
+                if !stop.send(&tx, update.with_desktop(encoded)) {
+                    break;
+                }
             }
-        });
 
-        Ok(instance)
+            log::debug!("Frame pool of JPEG encoder: {:?}", pool.stats());
+            Ok(())
+        })?;
+
+        Ok(Arc::new(JpegEncoder {
+            input: tx,
+            output: output_tx,
+            keyframe_requested,
+            rate,
+            worker,
+        }))
     }
 }
 
@@ -80,15 +103,114 @@ impl EncoderStage for JpegEncoder {
         self.input.clone()
     }
 
-    fn set_output(&self, tx: flume::Sender<DesktopUpdate<Vec<u8>>>) {
-        *self.output.lock().unwrap() = Some(tx);
-        self.configured.notify_all();
+    fn set_output(&self, tx: flume::Sender<DesktopUpdate<EncodedFrame>>) {
+        // taken once by the worker
+        let _ = self.output.try_send(tx);
+    }
+
+    fn request_keyframe(&self) {
+        self.keyframe_requested.store(true, Ordering::Relaxed);
+    }
+
+    fn report_delivery(&self, report: &DeliveryReport) {
+        self.rate.lock().unwrap().on_delivery(report);
+    }
+
+    fn shutdown(&self) {
+        self.worker.stop();
+    }
+}
+
+/// Encode the tiles changed since `prev`, or the whole frame in strips if there is no usable
+/// previous frame or most of it has changed.
+fn encode_frame(
+    img: &ImageBuf,
+    damage: Option<&[Rect]>,
+    prev: Option<&ImageBuf>,
+    params: JpegParams,
+    pool: &FramePool,
+) -> Result<EncodedFrame> {
+    let resolution = Resolution {
+        width: img.width,
+        height: img.height,
+    };
+
+    let tiles = prev
+        .filter(|prev| {
+            prev.width == img.width
+                && prev.height == img.height
+                && prev.color_format == img.color_format
+        })
+        .map(|prev| changed_tiles(img.as_data_ref(), prev.as_data_ref(), damage, TILE_SIZE));
+
+    let changed_area: u64 = tiles
+        .iter()
+        .flatten()
+        .map(|x| x.width as u64 * x.height as u64)
+        .sum();
+    let total_area = img.width as u64 * img.height as u64;
+
+    let subsampling = if params.yuv444 {
+        ChromaSubsampling::Yuv444
+    } else {
+        ChromaSubsampling::Yuv420
+    };
+
+    let rects = match tiles {
+        Some(tiles) if (changed_area as f64) < total_area as f64 * FULL_FRAME_RATIO => tiles,
+        _ => strips(img.width, img.height, STRIP_HEIGHT),
+    };
+
+    let encoded = encode_tiles(img, &rects, params, pool)?;
+
+    let mut data = Vec::with_capacity(encoded.iter().map(|x| x.len()).sum());
+    let mut encoded_tiles = Vec::with_capacity(rects.len());
+
+    for (rect, tile) in rects.into_iter().zip(encoded) {
+        encoded_tiles.push(EncodedTile {
+            rect,
+            len: tile.len().try_into().context("tile too large")?,
+        });
+        data.extend_from_slice(&tile);
     }
 
-    fn shutdown(&self) {}
+    Ok(EncodedFrame {
+        codec: VideoCodec::Jpeg,
+        resolution,
+        tiles: Some(encoded_tiles),
+        quality: params.quality,
+        subsampling,
+        data,
+    })
 }
 
-fn encode_img(img: Image<&[u8]>, yuv444: bool) -> Result<Vec<u8>> {
+/// Full width strips covering the frame from top to bottom
+pub fn strips(width: u32, height: u32, strip_height: u32) -> Vec<Rect> {
+    (0..height)
+        .step_by(strip_height as usize)
+        .map(|y| Rect::new(0, y, width, strip_height.min(height - y)))
+        .collect()
+}
+
+/// Encode areas of `img` as separate JPEG images on the thread pool, in the order given.
+/// Buffers of the tiles are taken from `pool`.
+pub fn encode_tiles(
+    img: &ImageBuf,
+    rects: &[Rect],
+    params: JpegParams,
+    pool: &FramePool,
+) -> Result<Vec<PooledBuf>> {
+    rects
+        .par_iter()
+        .map(|rect| encode_img(img.crop_in(pool, rect).as_data_ref(), params, pool))
+        .collect()
+}
+
+pub(crate) fn encode_img(
+    img: Image<&[u8]>,
+    params: JpegParams,
+    pool: &FramePool,
+) -> Result<PooledBuf> {
     let width: u16 = img
         .width
         .try_into()
@@ -98,13 +220,13 @@ fn encode_img(img: Image<&[u8]>, yuv444: bool) -> Result<Vec<u8>> {
         .try_into()
         .context("image dimension must not exceed 65535")?;
 
-    let buf_len = max_buffer_size(width, height);
-    let buf = vec![0u8; buf_len];
-    let mut cursor = Cursor::new(buf);
+    // sized for a typical image rather than the worst case, as the pool keeps the buffer once
+    // it is sent. Few images take more than a byte per pixel, and the buffer grows for those.
+    let mut buf = pool.with_capacity(width as usize * height as usize);
 
-    let mut encoder = Encoder::new(&mut cursor, 90);
+    let mut encoder = Encoder::new(&mut buf, params.quality);
 
-    encoder.set_sampling_factor(if yuv444 {
+    encoder.set_sampling_factor(if params.yuv444 {
         SamplingFactor::R_4_4_4
     } else {
         SamplingFactor::R_4_2_0
@@ -115,10 +237,6 @@ fn encode_img(img: Image<&[u8]>, yuv444: bool) -> Result<Vec<u8>> {
 
     encoder.encode(img.data, width, height, color_type)?;
 
-    let output_len = cursor.position() as usize;
-    let mut buf = cursor.into_inner();
-    buf.truncate(output_len);
-
     Ok(buf)
 }
 
@@ -130,10 +248,3 @@ fn color_format_to_color_type(fmt: ColorFormat) -> Option<ColorType> {
         _ => None,
     }
 }
-
-fn max_buffer_size(width: u16, height: u16) -> usize {
-    let padded_w = (width as usize).next_multiple_of(16);
-    let padded_h = (height as usize).next_multiple_of(16);
-
-    padded_w * padded_h * 6 + 2048
-}
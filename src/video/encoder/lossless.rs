@@ -0,0 +1,102 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::image::ImageBuf;
+use crate::network::dto::video::Resolution;
+use crate::schema::video::{ChromaSubsampling, VideoCodec};
+use crate::util::{DesktopUpdate, StageWorker};
+use crate::video::encoder::rate_control::DeliveryReport;
+use crate::video::encoder::stage::{EncodedFrame, EncoderStage};
+use crate::video::zstd_delta::ZstdDeltaEncoder;
+
+/// Lossless encoder, sending zstd compressed XOR deltas against the previous frame.
+#[derive(Debug)]
+pub struct LosslessEncoder {
+    input: flume::Sender<DesktopUpdate<ImageBuf>>,
+    output: flume::Sender<flume::Sender<DesktopUpdate<EncodedFrame>>>,
+    keyframe_requested: Arc<AtomicBool>,
+    worker: StageWorker,
+}
+
+impl LosslessEncoder {
+    pub fn new() -> Result<Arc<Self>> {
+        let (tx, rx) = flume::bounded::<DesktopUpdate<ImageBuf>>(1);
+        let (output_tx, output_rx) = flume::bounded(1);
+        let keyframe_requested = Arc::new(AtomicBool::new(false));
+        let worker_keyframe = Arc::clone(&keyframe_requested);
+
+        let worker = StageWorker::new("lossless_encoder");
+        worker.start(move |stop| {
+            // given by set_output
+            let tx: flume::Sender<_> = match stop.recv(&output_rx) {
+                Some(x) => x,
+                None => return Ok(()),
+            };
+
+            let mut encoder = ZstdDeltaEncoder::new();
+
+            while let Some(mut update) = stop.recv(&rx) {
+                update.timings.encode_begin = update.timings.elapsed_since_capture().unwrap();
+
+                let key = worker_keyframe.swap(false, Ordering::Relaxed);
+                let encoded = EncodedFrame {
+                    codec: VideoCodec::ZstdDelta,
+                    resolution: Resolution {
+                        width: update.desktop.width,
+                        height: update.desktop.height,
+                    },
+                    tiles: None,
+                    quality: 0,
+                    subsampling: ChromaSubsampling::Yuv444,
+                    data: encoder.encode(update.desktop.as_data_ref(), key)?,
+                };
+                update.timings.encode_end = update.timings.elapsed_since_capture().unwrap();
+
+                if !stop.send(&tx, update.with_desktop(encoded)) {
+                    return Ok(());
+                }
+            }
+
+            Ok(())
+        })?;
+
+        Ok(Arc::new(LosslessEncoder {
+            input: tx,
+            output: output_tx,
+            keyframe_requested,
+            worker,
+        }))
+    }
+}
+
+impl EncoderStage for LosslessEncoder {
+    fn configured(&self) -> bool {
+        true
+    }
+
+    fn configure(self: Arc<Self>) -> Result<()> {
+        Ok(())
+    }
+
+    fn input(&self) -> flume::Sender<DesktopUpdate<ImageBuf>> {
+        self.input.clone()
+    }
+
+    fn set_output(&self, tx: flume::Sender<DesktopUpdate<EncodedFrame>>) {
+        // taken once by the worker
+        let _ = self.output.try_send(tx);
+    }
+
+    fn request_keyframe(&self) {
+        self.keyframe_requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Nothing to trade for bandwidth without losing pixels.
+    fn report_delivery(&self, _report: &DeliveryReport) {}
+
+    fn shutdown(&self) {
+        self.worker.stop();
+    }
+}
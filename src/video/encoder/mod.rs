@@ -1,4 +1,13 @@
+#[cfg(feature = "av1")]
+pub mod av1;
+pub mod h264;
+pub mod hybrid;
 pub mod jpeg;
+pub mod lossless;
+mod rate_control;
 mod stage;
+mod tiles;
 
-pub use stage::EncoderStage;
+pub use rate_control::{DeliveryReport, JpegParams, JpegRateControl};
+pub use stage::{EncodedFrame, EncodedTile, EncoderStage};
+pub use tiles::changed_tiles;
@@ -0,0 +1,192 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Delivery of a single frame, measured by the sender
+#[derive(Debug, Clone)]
+pub struct DeliveryReport {
+    /// Encoded size of the frame
+    pub bytes: usize,
+
+    /// Time from sending the frame until the client acknowledged it
+    pub rtt: Duration,
+
+    /// When the acknowledgement arrived
+    pub acked_at: Instant,
+
+    /// Frames sent after this one and not acknowledged yet
+    pub in_flight: u32,
+}
+
+/// Parameters chosen for the next JPEG frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JpegParams {
+    /// From 1 to 100
+    pub quality: u8,
+    pub yuv444: bool,
+}
+
+/// Frames in flight beyond this means they are queueing up somewhere
+const MAX_IN_FLIGHT: u32 = 2;
+
+/// Round trip time allowed on top of the idle link, before it is considered congested
+const RTT_TOLERANCE: Duration = Duration::from_millis(30);
+
+/// Longest time a frame may take to transfer at the estimated link capacity
+const FRAME_BUDGET: Duration = Duration::from_millis(50);
+
+/// Acknowledgements the delivery rate is measured over
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(1);
+
+/// Minimum interval between decreases, so frames sent before a decrease can drain
+const DECREASE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Minimum interval between increases after any change
+const INCREASE_INTERVAL: Duration = Duration::from_secs(1);
+
+const QUALITY_STEP_UP: u8 = 5;
+
+/// Adjusts JPEG quality and chroma subsampling to the measured link.
+///
+/// Quality drops quickly once frames pile up, take longer to be acknowledged than on an idle
+/// link, or would not fit in the link capacity estimated from recent deliveries.
+/// It recovers slowly while the link keeps up.
+#[derive(Debug, Clone)]
+pub struct JpegRateControl {
+    min_quality: u8,
+    max_quality: u8,
+    allow_yuv444: bool,
+
+    params: JpegParams,
+
+    /// Lowest round trip time seen, as the baseline of an idle link
+    min_rtt: Option<Duration>,
+
+    /// Bytes per second the link delivered at best, slowly decaying
+    capacity: Option<f64>,
+
+    /// Time and size of the frames acknowledged within `THROUGHPUT_WINDOW`
+    acked: VecDeque<(Instant, usize)>,
+
+    last_change: Option<Instant>,
+}
+
+impl JpegRateControl {
+    /// Starts at 4:2:0 with `max_quality`.
+    /// 4:4:4 is used only if allowed and the link keeps up at `max_quality`.
+    pub fn new(min_quality: u8, max_quality: u8, allow_yuv444: bool) -> Self {
+        let max_quality = max_quality.clamp(1, 100);
+        let min_quality = min_quality.clamp(1, max_quality);
+
+        Self {
+            min_quality,
+            max_quality,
+            allow_yuv444,
+            params: JpegParams {
+                quality: max_quality,
+                yuv444: false,
+            },
+            min_rtt: None,
+            capacity: None,
+            acked: VecDeque::new(),
+            last_change: None,
+        }
+    }
+
+    pub fn params(&self) -> JpegParams {
+        self.params
+    }
+
+    pub fn on_delivery(&mut self, report: &DeliveryReport) {
+        let now = report.acked_at;
+
+        let min_rtt = *self
+            .min_rtt
+            .insert(self.min_rtt.map_or(report.rtt, |x| x.min(report.rtt)));
+
+        // frames delivered before the next one is sent show the link keeping up, whatever the rate
+        let too_large = match self.delivery_rate(report) {
+            Some(rate) if report.in_flight > 0 => {
+                let capacity = *self
+                    .capacity
+                    .insert(self.capacity.map_or(rate, |x| rate.max(x * 0.99)));
+                report.bytes as f64 / capacity > FRAME_BUDGET.as_secs_f64()
+            }
+            _ => false,
+        };
+
+        let congested = report.in_flight > MAX_IN_FLIGHT
+            || report.rtt > min_rtt * 2 + RTT_TOLERANCE
+            || too_large;
+
+        let since_change = self.last_change.map_or(Duration::MAX, |x| now - x);
+        let before = self.params;
+
+        if congested {
+            if since_change < DECREASE_INTERVAL {
+                return;
+            }
+
+            if self.params.yuv444 {
+                self.params.yuv444 = false;
+            } else {
+                let lowered = (self.params.quality as u32 * 3 / 4) as u8;
+                self.params.quality = lowered.max(self.min_quality);
+            }
+        } else {
+            if since_change < INCREASE_INTERVAL {
+                return;
+            }
+
+            if self.params.quality < self.max_quality {
+                self.params.quality = self
+                    .params
+                    .quality
+                    .saturating_add(QUALITY_STEP_UP)
+                    .min(self.max_quality);
+            } else if self.allow_yuv444 {
+                self.params.yuv444 = true;
+            }
+        }
+
+        if self.params == before {
+            return;
+        }
+
+        log::debug!(
+            "JPEG quality {} yuv444={} (rtt={:?}, in_flight={})",
+            self.params.quality,
+            self.params.yuv444,
+            report.rtt,
+            report.in_flight
+        );
+        self.last_change = Some(now);
+    }
+
+    /// Bytes per second delivered over the recent acknowledgements, including `report`.
+    /// None until the window holds more than one of them.
+    ///
+    /// Both it and the rate of the single frame are lower bounds of the link capacity, and the
+    /// larger one is taken. The rate of a single frame is bound by latency rather than the link,
+    /// which the window is not once frames overlap.
+    fn delivery_rate(&mut self, report: &DeliveryReport) -> Option<f64> {
+        let now = report.acked_at;
+        self.acked.push_back((now, report.bytes));
+        while let Some(&(acked_at, _)) = self.acked.front() {
+            if now.saturating_duration_since(acked_at) <= THROUGHPUT_WINDOW {
+                break;
+            }
+            self.acked.pop_front();
+        }
+
+        // the first frame of the window was sent before it starts, so only later ones count
+        let (first, _) = self.acked[0];
+        let elapsed = now.saturating_duration_since(first).as_secs_f64();
+        if elapsed <= 0.0 {
+            return None;
+        }
+        let bytes: usize = self.acked.iter().skip(1).map(|&(_, x)| x).sum();
+
+        let single = report.bytes as f64 / report.rtt.as_secs_f64().max(0.001);
+        Some(single.max(bytes as f64 / elapsed))
+    }
+}
@@ -1,3 +1,7 @@
+use crate::image::Rect;
+use crate::network::dto::video::Resolution;
+use crate::schema::video::{ChromaSubsampling, VideoCodec};
+use crate::video::encoder::rate_control::DeliveryReport;
 use crate::{image::ImageBuf, util::DesktopUpdate};
 use anyhow::Result;
 use std::{fmt::Debug, sync::Arc};
@@ -7,7 +11,37 @@ pub trait EncoderStage: Debug + Send + Sync {
     fn configure(self: Arc<Self>) -> Result<()>;
 
     fn input(&self) -> flume::Sender<DesktopUpdate<ImageBuf>>;
-    fn set_output(&self, tx: flume::Sender<DesktopUpdate<Vec<u8>>>);
+    fn set_output(&self, tx: flume::Sender<DesktopUpdate<EncodedFrame>>);
+
+    /// Make the next frame decodable without any previous frame.
+    fn request_keyframe(&self);
+
+    /// Called for every frame acknowledged by clients.
+    /// Encoders targeting a fixed bitrate may ignore it.
+    fn report_delivery(&self, report: &DeliveryReport);
 
     fn shutdown(&self);
 }
+
+/// Output of an encoder stage.
+/// Codec and resolution may change between frames, and the stream is re-announced if so.
+#[derive(Debug, Clone)]
+pub struct EncodedFrame {
+    pub codec: VideoCodec,
+    pub resolution: Resolution,
+    /// None if `data` is the whole frame.
+    /// Otherwise `data` is the tiles concatenated in order, which patch the previous frame.
+    pub tiles: Option<Vec<EncodedTile>>,
+    /// From 1 to 100, or 0 if the codec has no such setting
+    pub quality: u8,
+    pub subsampling: ChromaSubsampling,
+    pub data: Vec<u8>,
+}
+
+/// Placement of a tile in `EncodedFrame::data`
+#[derive(Debug, Clone)]
+pub struct EncodedTile {
+    pub rect: Rect,
+    /// Length of the encoded tile in bytes
+    pub len: u32,
+}
@@ -0,0 +1,57 @@
+use crate::image::{Image, Rect};
+use crate::util::AsUsize;
+
+/// Tiles of `img` which differ from `prev`, with adjacent tiles in a row merged together.
+/// If `damage` is given, tiles outside of it are assumed to be unchanged.
+pub fn changed_tiles(
+    img: Image<&[u8]>,
+    prev: Image<&[u8]>,
+    damage: Option<&[Rect]>,
+    tile_size: u32,
+) -> Vec<Rect> {
+    assert!(
+        img.width == prev.width && img.height == prev.height,
+        "image size must match"
+    );
+    assert_eq!(
+        img.color_format, prev.color_format,
+        "color format must match"
+    );
+
+    let mut output = Vec::new();
+
+    for y in (0..img.height).step_by(tile_size.as_usize()) {
+        let height = tile_size.min(img.height - y);
+        let mut run: Option<Rect> = None;
+
+        for x in (0..img.width).step_by(tile_size.as_usize()) {
+            let tile = Rect::new(x, y, tile_size.min(img.width - x), height);
+
+            let damaged = damage.is_none_or(|damage| damage.iter().any(|r| r.intersects(&tile)));
+            if damaged && tile_differs(&img, &prev, &tile) {
+                match run.as_mut() {
+                    Some(run) => run.width += tile.width,
+                    None => run = Some(tile),
+                }
+            } else if let Some(run) = run.take() {
+                output.push(run);
+            }
+        }
+
+        output.extend(run);
+    }
+
+    output
+}
+
+fn tile_differs(a: &Image<&[u8]>, b: &Image<&[u8]>, tile: &Rect) -> bool {
+    let pixel = a.color_format.pixel_stride();
+    let start = (tile.x * pixel).as_usize();
+    let len = (tile.width * pixel).as_usize();
+
+    (tile.y..tile.bottom()).any(|i| {
+        let a_start = i.as_usize() * a.stride.as_usize() + start;
+        let b_start = i.as_usize() * b.stride.as_usize() + start;
+        a.data[a_start..a_start + len] != b.data[b_start..b_start + len]
+    })
+}
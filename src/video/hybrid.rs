@@ -0,0 +1,449 @@
+//! Bitstream of the `Hybrid` codec, choosing per tile between a lossless palette and JPEG.
+//!
+//! Every frame is `[u8 kind][u8 color format][u32le width][u32le height][u32le tile count]`
+//! followed by the tiles. Each tile is `[u32le x][u32le y][u32le width][u32le height]
+//! [u8 tile kind][u32le length]` followed by its data. Key frames cover the whole frame, and
+//! delta frames carry only the tiles changed since the previous frame, which must have the same
+//! header.
+//!
+//! Palette tiles are `[u8 colors - 1][colors in the frame color format]` followed by zstd
+//! compressed indices of the pixels, one byte each. JPEG tiles are a baseline JPEG image.
+
+use anyhow::{bail, ensure, Context, Result};
+use rustc_hash::FxHashMap;
+
+use crate::image::{ColorFormat, Image, ImageBuf, Rect};
+use crate::util::{AsUsize, FramePool};
+use crate::video::decoder::jpeg::decode_img;
+use crate::video::encoder::jpeg::encode_img;
+use crate::video::encoder::{changed_tiles, JpegParams};
+
+const HEADER_LEN: usize = 14;
+const TILE_HEADER_LEN: usize = 21;
+
+const KIND_KEY: u8 = 0;
+const KIND_DELTA: u8 = 1;
+
+const TILE_PALETTE: u8 = 0;
+const TILE_JPEG: u8 = 1;
+
+/// Edge length of the tiles, which are classified on their own
+const TILE_SIZE: u32 = 64;
+
+/// Tiles with at most this many colors are always sent lossless
+const FEW_COLORS: usize = 16;
+
+/// Most colors a palette tile can hold
+const MAX_PALETTE: usize = 256;
+
+/// Difference of a channel between neighboring pixels counted as a sharp edge
+const SHARP_EDGE_STEP: u8 = 48;
+
+/// Fraction of sharp edges among all edges, from which a tile is treated as text or line art
+const SHARP_EDGE_RATIO: f64 = 0.3;
+
+/// Fast enough to keep up with the desktop, indices of text compress well anyway.
+const COMPRESSION_LEVEL: i32 = 1;
+
+/// Encodes frames, keeping the previous one to send only the changed tiles.
+#[derive(Debug, Default)]
+pub struct HybridFrameEncoder {
+    prev: Option<ImageBuf>,
+    /// For the tiles cropped out of frames and their encoded output
+    pool: FramePool,
+}
+
+impl HybridFrameEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encode a key frame if `key` is set or the format has changed, or the tiles changed since
+    /// the previous frame otherwise. If `damage` is given, areas outside of it are assumed to be
+    /// unchanged.
+    pub fn encode(
+        &mut self,
+        img: &ImageBuf,
+        damage: Option<&[Rect]>,
+        key: bool,
+        params: JpegParams,
+    ) -> Result<Vec<u8>> {
+        let header = Header {
+            color_format: img.color_format,
+            width: img.width,
+            height: img.height,
+        };
+
+        let prev = self.prev.as_ref().filter(|prev| {
+            !key && prev.width == img.width
+                && prev.height == img.height
+                && prev.color_format == img.color_format
+        });
+
+        let (kind, tiles) = match prev {
+            Some(prev) => (
+                KIND_DELTA,
+                changed_tiles(img.as_data_ref(), prev.as_data_ref(), damage, TILE_SIZE)
+                    .iter()
+                    .flat_map(split_run)
+                    .collect(),
+            ),
+            None => (
+                KIND_KEY,
+                split_frame(&Rect::new(0, 0, img.width, img.height)),
+            ),
+        };
+
+        let mut output = Vec::new();
+        header.write(kind, tiles.len(), &mut output)?;
+
+        for rect in tiles {
+            let tile = img.crop_in(&self.pool, &rect);
+            let (tile_kind, data) = match palette(&tile) {
+                Some((colors, indices)) if is_lossless(&tile, colors.len()) => (
+                    TILE_PALETTE,
+                    encode_palette(&tile, &colors, &indices)?.into(),
+                ),
+                _ => (
+                    TILE_JPEG,
+                    encode_img(tile.as_data_ref(), params, &self.pool)?,
+                ),
+            };
+
+            output.extend_from_slice(&rect.x.to_le_bytes());
+            output.extend_from_slice(&rect.y.to_le_bytes());
+            output.extend_from_slice(&rect.width.to_le_bytes());
+            output.extend_from_slice(&rect.height.to_le_bytes());
+            output.push(tile_kind);
+            let len: u32 = data.len().try_into().context("tile too large")?;
+            output.extend_from_slice(&len.to_le_bytes());
+            output.extend_from_slice(&data);
+        }
+
+        match &mut self.prev {
+            Some(prev) => img.copy_into(prev),
+            None => self.prev = Some(img.copied()),
+        }
+
+        Ok(output)
+    }
+}
+
+/// Decodes frames, compositing the tiles over the previous frame.
+#[derive(Debug, Default)]
+pub struct HybridFrameDecoder {
+    framebuffer: Option<(Header, ImageBuf)>,
+    /// For decoded JPEG tiles and the output
+    pool: FramePool,
+}
+
+impl HybridFrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Output is tightly packed, in the color format of the encoded image.
+    pub fn decode(&mut self, data: &[u8]) -> Result<ImageBuf> {
+        let (kind, header, tile_count) = Header::read(data)?;
+
+        let mut framebuffer = match kind {
+            KIND_KEY => {
+                // each tile takes a header, so a key frame is no larger than its data allows
+                let tiles = header.tile_count()?;
+                ensure!(
+                    tile_count.as_usize() == tiles,
+                    "key frame has {tile_count} tiles, expected {tiles}"
+                );
+                ensure!(
+                    tiles <= (data.len() - HEADER_LEN) / TILE_HEADER_LEN,
+                    "key frame too short for its tiles"
+                );
+
+                let stride = header
+                    .width
+                    .checked_mul(header.color_format.pixel_stride())
+                    .context("frame too large")?;
+                Image::new(
+                    header.width,
+                    header.height,
+                    stride,
+                    header.color_format,
+                    vec![0; header.data_len()?].into(),
+                )
+            }
+            KIND_DELTA => match self.framebuffer.take() {
+                Some((prev_header, framebuffer)) if prev_header == header => framebuffer,
+                Some(_) => bail!("delta frame does not match the previous frame"),
+                None => bail!("delta frame without a previous frame"),
+            },
+            other => bail!("unknown frame kind {other}"),
+        };
+
+        let bounds = Rect::new(0, 0, header.width, header.height);
+        let mut rest = &data[HEADER_LEN..];
+
+        for _ in 0..tile_count {
+            ensure!(
+                rest.len() >= TILE_HEADER_LEN,
+                "tile header exceeds the frame"
+            );
+
+            let rect = Rect::new(
+                read_u32(&rest[0..]),
+                read_u32(&rest[4..]),
+                read_u32(&rest[8..]),
+                read_u32(&rest[12..]),
+            );
+            let tile_kind = rest[16];
+            let len = read_u32(&rest[17..]).as_usize();
+            rest = &rest[TILE_HEADER_LEN..];
+
+            ensure!(len <= rest.len(), "tile exceeds the frame");
+            ensure!(
+                !rect.is_empty() && bounds.contains(&rect),
+                "tile {rect:?} out of the frame"
+            );
+            let (tile_data, next) = rest.split_at(len);
+            rest = next;
+
+            let tile = match tile_kind {
+                TILE_PALETTE => decode_palette(tile_data, &rect, header.color_format)?,
+                TILE_JPEG => decode_img(tile_data, header.color_format, &self.pool)?,
+                other => bail!("unknown tile kind {other}"),
+            };
+            ensure!(
+                (tile.width, tile.height) == (rect.width, rect.height),
+                "tile is {}x{}, expected {}x{}",
+                tile.width,
+                tile.height,
+                rect.width,
+                rect.height
+            );
+
+            tile.copy_to(&mut framebuffer, rect.x, rect.y);
+        }
+
+        let output = framebuffer.copied_in(&self.pool);
+        self.framebuffer = Some((header, framebuffer));
+
+        Ok(output)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Header {
+    color_format: ColorFormat,
+    width: u32,
+    height: u32,
+}
+
+impl Header {
+    fn write(&self, kind: u8, tile_count: usize, output: &mut Vec<u8>) -> Result<()> {
+        let color_format = match self.color_format {
+            ColorFormat::Rgba8888 => 0,
+            ColorFormat::Bgra8888 => 1,
+            ColorFormat::Rgb24 => 2,
+            other => bail!("unsupported color format {other:?}"),
+        };
+        let tile_count: u32 = tile_count.try_into().context("too many tiles")?;
+
+        output.push(kind);
+        output.push(color_format);
+        output.extend_from_slice(&self.width.to_le_bytes());
+        output.extend_from_slice(&self.height.to_le_bytes());
+        output.extend_from_slice(&tile_count.to_le_bytes());
+
+        Ok(())
+    }
+
+    fn read(data: &[u8]) -> Result<(u8, Self, u32)> {
+        ensure!(data.len() >= HEADER_LEN, "frame too short");
+
+        let color_format = match data[1] {
+            0 => ColorFormat::Rgba8888,
+            1 => ColorFormat::Bgra8888,
+            2 => ColorFormat::Rgb24,
+            other => bail!("unknown color format {other}"),
+        };
+
+        let header = Header {
+            color_format,
+            width: read_u32(&data[2..]),
+            height: read_u32(&data[6..]),
+        };
+
+        Ok((data[0], header, read_u32(&data[10..])))
+    }
+
+    fn data_len(&self) -> Result<usize> {
+        self.width
+            .as_usize()
+            .checked_mul(self.height.as_usize())
+            .and_then(|x| x.checked_mul(self.color_format.pixel_stride().as_usize()))
+            .context("frame too large")
+    }
+
+    /// Number of tiles covering the whole frame
+    fn tile_count(&self) -> Result<usize> {
+        self.width
+            .div_ceil(TILE_SIZE)
+            .as_usize()
+            .checked_mul(self.height.div_ceil(TILE_SIZE).as_usize())
+            .context("frame too large")
+    }
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    u32::from_le_bytes(data[..4].try_into().unwrap())
+}
+
+/// Split a run of changed tiles back into the tiles.
+fn split_run(run: &Rect) -> Vec<Rect> {
+    (run.x..run.right())
+        .step_by(TILE_SIZE.as_usize())
+        .map(|x| Rect::new(x, run.y, TILE_SIZE.min(run.right() - x), run.height))
+        .collect()
+}
+
+fn split_frame(frame: &Rect) -> Vec<Rect> {
+    (0..frame.height)
+        .step_by(TILE_SIZE.as_usize())
+        .flat_map(|y| {
+            split_run(&Rect::new(
+                0,
+                y,
+                frame.width,
+                TILE_SIZE.min(frame.height - y),
+            ))
+        })
+        .collect()
+}
+
+fn pixel_key(pixel: &[u8]) -> u32 {
+    let mut key = [0u8; 4];
+    key[..pixel.len()].copy_from_slice(pixel);
+    u32::from_le_bytes(key)
+}
+
+/// Colors of a tightly packed tile and the index of every pixel,
+/// or None if there are more colors than a palette can hold.
+fn palette(tile: &ImageBuf) -> Option<(Vec<u32>, Vec<u8>)> {
+    let pixel = tile.color_format.pixel_stride().as_usize();
+
+    let mut colors = Vec::new();
+    let mut lookup = FxHashMap::default();
+    let mut indices = Vec::with_capacity(tile.data.len() / pixel);
+
+    for px in tile.data.chunks_exact(pixel) {
+        let key = pixel_key(px);
+        let index = match lookup.get(&key) {
+            Some(&x) => x,
+            None => {
+                if colors.len() == MAX_PALETTE {
+                    return None;
+                }
+                let index = colors.len() as u8;
+                colors.push(key);
+                lookup.insert(key, index);
+                index
+            }
+        };
+        indices.push(index);
+    }
+
+    Some((colors, indices))
+}
+
+/// Text and line art are mostly sharp edges between a few colors, while photos and video are
+/// gradients that JPEG reproduces well in fewer bytes.
+fn is_lossless(tile: &ImageBuf, colors: usize) -> bool {
+    if colors <= FEW_COLORS {
+        return true;
+    }
+
+    let pixel = tile.color_format.pixel_stride().as_usize();
+    let stride = tile.stride.as_usize();
+    let channels = pixel.min(3);
+
+    let mut edges = 0u32;
+    let mut sharp = 0u32;
+    let mut count = |a: &[u8], b: &[u8]| {
+        let step = a[..channels]
+            .iter()
+            .zip(&b[..channels])
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap_or(0);
+
+        if step > 0 {
+            edges += 1;
+            if step >= SHARP_EDGE_STEP {
+                sharp += 1;
+            }
+        }
+    };
+
+    for y in 0..tile.height.as_usize() {
+        for x in 0..tile.width.as_usize() {
+            let i = y * stride + x * pixel;
+            let px = &tile.data[i..i + pixel];
+
+            if x + 1 < tile.width.as_usize() {
+                count(px, &tile.data[i + pixel..i + pixel * 2]);
+            }
+            if y + 1 < tile.height.as_usize() {
+                count(px, &tile.data[i + stride..i + stride + pixel]);
+            }
+        }
+    }
+
+    edges > 0 && sharp as f64 >= edges as f64 * SHARP_EDGE_RATIO
+}
+
+fn encode_palette(tile: &ImageBuf, colors: &[u32], indices: &[u8]) -> Result<Vec<u8>> {
+    let pixel = tile.color_format.pixel_stride().as_usize();
+
+    let mut output = Vec::with_capacity(1 + colors.len() * pixel + indices.len() / 4);
+    output.push((colors.len() - 1) as u8);
+    for color in colors {
+        output.extend_from_slice(&color.to_le_bytes()[..pixel]);
+    }
+    output.extend_from_slice(&zstd::bulk::compress(indices, COMPRESSION_LEVEL)?);
+
+    Ok(output)
+}
+
+fn decode_palette(data: &[u8], rect: &Rect, color_format: ColorFormat) -> Result<ImageBuf> {
+    let pixel = color_format.pixel_stride().as_usize();
+
+    ensure!(!data.is_empty(), "palette tile too short");
+    let colors = data[0].as_usize() + 1;
+    ensure!(data.len() > colors * pixel, "palette tile too short");
+    let palette = &data[1..1 + colors * pixel];
+
+    let len = rect.width.as_usize() * rect.height.as_usize();
+    let indices = zstd::bulk::decompress(&data[1 + colors * pixel..], len)?;
+    ensure!(
+        indices.len() == len,
+        "palette tile has {} pixels, expected {len}",
+        indices.len()
+    );
+
+    let mut pixels = Vec::with_capacity(len * pixel);
+    for &index in &indices {
+        let index = index.as_usize();
+        ensure!(
+            index < colors,
+            "palette index {index} out of {colors} colors"
+        );
+        pixels.extend_from_slice(&palette[index * pixel..(index + 1) * pixel]);
+    }
+
+    Ok(Image::new(
+        rect.width,
+        rect.height,
+        rect.width * color_format.pixel_stride(),
+        color_format,
+        pixels.into(),
+    ))
+}
@@ -2,5 +2,10 @@ pub mod capture;
 mod capture_pipeline;
 pub mod decoder;
 pub mod encoder;
+pub mod hybrid;
+pub mod pacer;
+pub mod recorder;
+pub mod scaler;
+pub mod zstd_delta;
 
-pub use capture_pipeline::capture_pipeline;
+pub use capture_pipeline::{capture_factory, capture_pipeline, CapturePipelineOutput};
@@ -0,0 +1,251 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::image::ImageBuf;
+use crate::util::{CursorState, DesktopUpdate, StageWorker, Timings};
+use crate::video::encoder::{DeliveryReport, EncodedFrame, EncoderStage};
+
+/// Limits the frames reaching the encoder to a target frame rate.
+///
+/// Placed between the capture stage and the encoder, and seen as the encoder by the capture
+/// stage. Frames arriving faster than the target are merged into the next one instead of queued.
+/// Frames with neither the desktop nor the cursor changed are skipped, and the last frame is
+/// repeated as a keepalive while nothing changes.
+#[derive(Debug)]
+pub struct PacingStage {
+    input: flume::Sender<DesktopUpdate<ImageBuf>>,
+    refresh: flume::Sender<()>,
+    /// Least time between frames in nanoseconds
+    interval: Arc<AtomicU64>,
+    encoder: Arc<dyn EncoderStage>,
+    worker: StageWorker,
+}
+
+/// What the worker of `PacingStage` woke up for
+enum Event {
+    /// Boxed, as it is much larger than the other events
+    Frame(Box<DesktopUpdate<ImageBuf>>),
+    Refresh,
+    Timeout,
+    /// Stopped, or the input is dropped
+    Closed,
+}
+
+impl PacingStage {
+    /// Sends at most `max_fps` frames per second, and at least one every `keepalive`.
+    /// Unchanged frames are sent along if `skip_unchanged` is false.
+    pub fn new(
+        encoder: Arc<dyn EncoderStage>,
+        max_fps: f64,
+        keepalive: Duration,
+        skip_unchanged: bool,
+    ) -> Result<Arc<Self>> {
+        let (tx, rx) = flume::bounded::<DesktopUpdate<ImageBuf>>(1);
+        let (refresh_tx, refresh_rx) = flume::bounded::<()>(1);
+        let next = encoder.input();
+        let interval = Arc::new(AtomicU64::new(fps_to_interval(max_fps)));
+        let worker_interval = Arc::clone(&interval);
+
+        let worker = StageWorker::new("pacer");
+        worker.start(move |stop| {
+            let mut pending: Option<DesktopUpdate<ImageBuf>> = None;
+            let mut last = Last::default();
+            let mut next_frame = Instant::now();
+            let mut last_sent = Instant::now();
+
+            loop {
+                let deadline = match pending {
+                    Some(_) => next_frame,
+                    None => last_sent + keepalive,
+                };
+
+                let event = flume::Selector::new()
+                    .recv(&rx, |x| {
+                        x.map_or(Event::Closed, |x| Event::Frame(Box::new(x)))
+                    })
+                    .recv(&refresh_rx, |x| x.map_or(Event::Closed, |_| Event::Refresh))
+                    .recv(stop.receiver(), |_| Event::Closed)
+                    .wait_deadline(deadline)
+                    .unwrap_or(Event::Timeout);
+
+                let mut refresh = false;
+                match event {
+                    Event::Frame(update) => {
+                        let mut update = *update;
+                        if let Some(prev) = pending.take() {
+                            update.collapse_from(prev);
+                        }
+                        pending = Some(update);
+
+                        if Instant::now() < next_frame {
+                            continue;
+                        }
+                    }
+                    Event::Refresh => refresh = true,
+                    Event::Timeout => {}
+                    Event::Closed => return Ok(()),
+                }
+
+                let now = Instant::now();
+                let idle = now >= last_sent + keepalive;
+
+                let update = match pending.take() {
+                    Some(x) if skip_unchanged && !refresh && !idle && last.matches(&x) => {
+                        continue;
+                    }
+                    Some(x) => x,
+                    None if refresh || idle => match last.repeat() {
+                        Some(x) => x,
+                        None => {
+                            last_sent = now;
+                            continue;
+                        }
+                    },
+                    None => continue,
+                };
+
+                next_frame += Duration::from_nanos(worker_interval.load(Ordering::Relaxed));
+                // don't try to catch up if we fell behind
+                if next_frame < now {
+                    next_frame = now;
+                }
+                last_sent = now;
+
+                last.update(&update);
+
+                if !stop.send(&next, update) {
+                    return Ok(());
+                }
+            }
+        })?;
+
+        Ok(Arc::new(PacingStage {
+            input: tx,
+            refresh: refresh_tx,
+            interval,
+            encoder,
+            worker,
+        }))
+    }
+
+    /// Lower the frame rate to `fps` if it is lower, e.g. to the refresh rate of the monitor.
+    pub fn cap_fps(&self, fps: f64) {
+        self.interval
+            .fetch_max(fps_to_interval(fps), Ordering::Relaxed);
+    }
+}
+
+impl EncoderStage for PacingStage {
+    fn configured(&self) -> bool {
+        self.encoder.configured()
+    }
+
+    fn configure(self: Arc<Self>) -> Result<()> {
+        Arc::clone(&self.encoder).configure()
+    }
+
+    fn input(&self) -> flume::Sender<DesktopUpdate<ImageBuf>> {
+        self.input.clone()
+    }
+
+    fn set_output(&self, tx: flume::Sender<DesktopUpdate<EncodedFrame>>) {
+        self.encoder.set_output(tx);
+    }
+
+    /// Also resends the last frame, so that a keyframe follows even if the desktop is idle.
+    fn request_keyframe(&self) {
+        self.encoder.request_keyframe();
+        let _ = self.refresh.try_send(());
+    }
+
+    fn report_delivery(&self, report: &DeliveryReport) {
+        self.encoder.report_delivery(report);
+    }
+
+    /// Stops its own worker first, so that nothing reaches the encoder while it stops.
+    fn shutdown(&self) {
+        self.worker.stop();
+        self.encoder.shutdown();
+    }
+}
+
+/// In nanoseconds. Zero, i.e. unpaced, if `fps` is not positive.
+fn fps_to_interval(fps: f64) -> u64 {
+    if fps > 0.0 {
+        (1e9 / fps) as u64
+    } else {
+        0
+    }
+}
+
+/// Last frame sent to the encoder
+#[derive(Default)]
+struct Last {
+    desktop: Option<ImageBuf>,
+    scale: f32,
+    /// Without shape, kept from the last update with the cursor
+    cursor: Option<CursorState>,
+}
+
+impl Last {
+    fn update(&mut self, update: &DesktopUpdate<ImageBuf>) {
+        // into the buffer of the previous frame, not to take one from the pool every frame
+        match &mut self.desktop {
+            Some(prev) => update.desktop.copy_into(prev),
+            None => self.desktop = Some(update.desktop.clone()),
+        }
+        self.scale = update.scale;
+
+        if let Some(cursor) = &update.cursor {
+            self.cursor = Some(CursorState {
+                shape: None,
+                ..cursor.clone()
+            });
+        }
+    }
+
+    /// Neither the desktop nor the cursor of `update` changed since the last frame
+    fn matches(&self, update: &DesktopUpdate<ImageBuf>) -> bool {
+        let prev = match &self.desktop {
+            Some(x) => x,
+            None => return false,
+        };
+
+        let cursor_unchanged = match (&update.cursor, &self.cursor) {
+            (None, _) => true,
+            (Some(x), Some(y)) => {
+                x.shape.is_none() && (x.visible, x.pos_x, x.pos_y) == (y.visible, y.pos_x, y.pos_y)
+            }
+            (Some(_), None) => false,
+        };
+
+        let img = &update.desktop;
+        let desktop_unchanged = img.width == prev.width
+            && img.height == prev.height
+            && img.color_format == prev.color_format
+            && update.scale == self.scale
+            && match update.damage.as_deref() {
+                Some([]) => true,
+                _ => img.stride == prev.stride && img.data == prev.data,
+            };
+
+        cursor_unchanged && desktop_unchanged
+    }
+
+    /// The last frame again, marked as captured now and with nothing changed
+    fn repeat(&self) -> Option<DesktopUpdate<ImageBuf>> {
+        let mut timings = Timings::new();
+        timings.capture = Instant::now().into();
+
+        Some(DesktopUpdate {
+            cursor: None,
+            timings,
+            damage: Some(Vec::new()),
+            scale: self.scale,
+            desktop: self.desktop.clone()?,
+        })
+    }
+}
@@ -0,0 +1,194 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+
+use crate::image::ImageBuf;
+use crate::network::dto::video::{RefreshRate, Resolution};
+use crate::util::{DesktopUpdate, FramePool, StageWorker};
+use crate::video::capture::ReplayWriter;
+use crate::video::encoder::jpeg::encode_img;
+use crate::video::encoder::{DeliveryReport, EncodedFrame, EncoderStage, JpegParams};
+
+/// Writes every frame into a replay file before encoding,
+/// so that the desktop can be played back by the replay capture backend.
+///
+/// Placed between the capture stage and the encoder, like the scaler.
+/// Frames pass through unchanged, and recording stops at the first error without
+/// affecting the stream.
+#[derive(Debug)]
+pub struct RecordStage {
+    input: flume::Sender<DesktopUpdate<ImageBuf>>,
+    encoder: Arc<dyn EncoderStage>,
+    worker: StageWorker,
+}
+
+impl RecordStage {
+    /// Records into a new file in `dir`, named after the monitor and the time.
+    /// `refresh_rate` is the rate frames arrive at, which the replay follows.
+    /// Frames are stored as JPEG of `jpeg_quality` if given, uncompressed otherwise.
+    pub fn new(
+        encoder: Arc<dyn EncoderStage>,
+        dir: &Path,
+        monitor_id: &str,
+        refresh_rate: RefreshRate,
+        jpeg_quality: Option<u8>,
+    ) -> Result<Arc<Self>> {
+        let path = record_path(dir, monitor_id);
+        let (tx, rx) = flume::bounded::<DesktopUpdate<ImageBuf>>(1);
+        let next = encoder.input();
+
+        let worker = StageWorker::new("recorder");
+        worker.start(move |stop| {
+            let mut recorder = Some(Recorder {
+                path,
+                refresh_rate,
+                jpeg_quality,
+                writer: None,
+                start: None,
+                pool: FramePool::default(),
+            });
+
+            while let Some(update) = stop.recv(&rx) {
+                if let Some(x) = &mut recorder {
+                    if let Err(e) = x.write(&update) {
+                        log::warn!("Stopped recording to {:?}: {e:?}", x.path);
+                        recorder = None;
+                    }
+                }
+
+                if !stop.send(&next, update) {
+                    break;
+                }
+            }
+
+            if let Some(mut x) = recorder {
+                x.finish()?;
+            }
+            Ok(())
+        })?;
+
+        Ok(Arc::new(RecordStage {
+            input: tx,
+            encoder,
+            worker,
+        }))
+    }
+}
+
+impl EncoderStage for RecordStage {
+    fn configured(&self) -> bool {
+        self.encoder.configured()
+    }
+
+    fn configure(self: Arc<Self>) -> Result<()> {
+        Arc::clone(&self.encoder).configure()
+    }
+
+    fn input(&self) -> flume::Sender<DesktopUpdate<ImageBuf>> {
+        self.input.clone()
+    }
+
+    fn set_output(&self, tx: flume::Sender<DesktopUpdate<EncodedFrame>>) {
+        self.encoder.set_output(tx);
+    }
+
+    fn request_keyframe(&self) {
+        self.encoder.request_keyframe();
+    }
+
+    fn report_delivery(&self, report: &DeliveryReport) {
+        self.encoder.report_delivery(report);
+    }
+
+    fn shutdown(&self) {
+        self.worker.stop();
+        self.encoder.shutdown();
+    }
+}
+
+struct Recorder {
+    path: PathBuf,
+    refresh_rate: RefreshRate,
+    jpeg_quality: Option<u8>,
+    /// Created on the first frame, which gives the resolution of the header
+    writer: Option<ReplayWriter<BufWriter<File>>>,
+    /// Capture time of the first frame, from which timestamps count
+    start: Option<Instant>,
+    pool: FramePool,
+}
+
+impl Recorder {
+    fn write(&mut self, update: &DesktopUpdate<ImageBuf>) -> Result<()> {
+        let img = &update.desktop;
+        let captured = update
+            .timings
+            .capture
+            .as_local()
+            .copied()
+            .unwrap_or_else(Instant::now);
+        let timestamp = captured.duration_since(*self.start.get_or_insert(captured));
+
+        let writer = match &mut self.writer {
+            Some(x) => x,
+            None => {
+                if let Some(dir) = self.path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let file = File::create(&self.path)
+                    .with_context(|| format!("unable to create replay file {:?}", self.path))?;
+                let resolution = Resolution {
+                    width: img.width,
+                    height: img.height,
+                };
+                log::info!("Recording the desktop to {:?}", self.path);
+
+                let writer =
+                    ReplayWriter::new(BufWriter::new(file), &resolution, &self.refresh_rate)?;
+                self.writer.insert(writer)
+            }
+        };
+
+        match self.jpeg_quality {
+            Some(quality) => {
+                let params = JpegParams {
+                    quality,
+                    yuv444: true,
+                };
+                let jpeg = encode_img(img.as_data_ref(), params, &self.pool)?;
+                writer.write_jpeg(
+                    timestamp,
+                    update.cursor.as_ref(),
+                    img.width,
+                    img.height,
+                    &jpeg,
+                )
+            }
+            None => writer.write_image(timestamp, update.cursor.as_ref(), img.as_data_ref()),
+        }
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        match &mut self.writer {
+            Some(x) => x.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `<monitor>-<unix time in ms>.replay`, keeping only characters safe in file names
+fn record_path(dir: &Path, monitor_id: &str) -> PathBuf {
+    let monitor: String = monitor_id
+        .chars()
+        .map(|x| if x.is_ascii_alphanumeric() { x } else { '_' })
+        .collect();
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    dir.join(format!("{monitor}-{time}.replay"))
+}
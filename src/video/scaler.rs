@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::image::{resize, resize_in, ImageBuf, Rect, ScaleFilter};
+use crate::network::dto::video::Resolution;
+use crate::util::{CursorState, DesktopUpdate, FramePool, StageWorker};
+use crate::video::encoder::{DeliveryReport, EncodedFrame, EncoderStage};
+
+/// Downscales the desktop to fit in a target resolution before encoding.
+///
+/// Placed between the capture stage and the encoder, and seen as the encoder by the capture
+/// stage. Positions of the cursor and damaged areas are scaled along, and the scale is set on
+/// every update.
+#[derive(Debug)]
+pub struct ScaleStage {
+    input: flume::Sender<DesktopUpdate<ImageBuf>>,
+    encoder: Arc<dyn EncoderStage>,
+    worker: StageWorker,
+}
+
+impl ScaleStage {
+    pub fn new(
+        encoder: Arc<dyn EncoderStage>,
+        target: Resolution,
+        filter: ScaleFilter,
+    ) -> Result<Arc<Self>> {
+        let (tx, rx) = flume::bounded::<DesktopUpdate<ImageBuf>>(1);
+        let next = encoder.input();
+
+        let worker = StageWorker::new("scaler");
+        worker.start(move |stop| {
+            let pool = FramePool::default();
+
+            while let Some(update) = stop.recv(&rx) {
+                let update = scale_update(update, &target, filter, &pool);
+
+                if !stop.send(&next, update) {
+                    break;
+                }
+            }
+
+            log::debug!("Frame pool of scaler: {:?}", pool.stats());
+            Ok(())
+        })?;
+
+        Ok(Arc::new(ScaleStage {
+            input: tx,
+            encoder,
+            worker,
+        }))
+    }
+}
+
+impl EncoderStage for ScaleStage {
+    fn configured(&self) -> bool {
+        self.encoder.configured()
+    }
+
+    fn configure(self: Arc<Self>) -> Result<()> {
+        Arc::clone(&self.encoder).configure()
+    }
+
+    fn input(&self) -> flume::Sender<DesktopUpdate<ImageBuf>> {
+        self.input.clone()
+    }
+
+    fn set_output(&self, tx: flume::Sender<DesktopUpdate<EncodedFrame>>) {
+        self.encoder.set_output(tx);
+    }
+
+    fn request_keyframe(&self) {
+        self.encoder.request_keyframe();
+    }
+
+    fn report_delivery(&self, report: &DeliveryReport) {
+        self.encoder.report_delivery(report);
+    }
+
+    fn shutdown(&self) {
+        self.worker.stop();
+        self.encoder.shutdown();
+    }
+}
+
+/// Largest size fitting in `target` with the aspect ratio of `source`. Never scales up.
+/// Scaled sizes are rounded down to even numbers, as required by 4:2:0 codecs.
+pub fn fit_resolution(source: &Resolution, target: &Resolution) -> Resolution {
+    let scale = (target.width as f64 / source.width as f64)
+        .min(target.height as f64 / source.height as f64);
+
+    if scale >= 1.0 {
+        return source.clone();
+    }
+
+    let scaled = |x: u32| ((x as f64 * scale) as u32 & !1).max(2);
+    Resolution {
+        width: scaled(source.width),
+        height: scaled(source.height),
+    }
+}
+
+fn scale_update(
+    update: DesktopUpdate<ImageBuf>,
+    target: &Resolution,
+    filter: ScaleFilter,
+    pool: &FramePool,
+) -> DesktopUpdate<ImageBuf> {
+    let source = Resolution {
+        width: update.desktop.width,
+        height: update.desktop.height,
+    };
+    let resolution = fit_resolution(&source, target);
+
+    if resolution == source {
+        return update;
+    }
+
+    let (mut update, img) = update.split();
+    let scale_x = resolution.width as f32 / source.width as f32;
+    let scale_y = resolution.height as f32 / source.height as f32;
+
+    // the aspect ratio is kept, apart from rounding
+    update.scale = scale_x;
+    update.damage = update.damage.map(|damage| {
+        damage
+            .iter()
+            .map(|rect| scale_rect(rect, scale_x, scale_y, &resolution))
+            .filter(|rect| !rect.is_empty())
+            .collect()
+    });
+    update.cursor = update
+        .cursor
+        .map(|cursor| scale_cursor(cursor, scale_x, scale_y));
+
+    update.with_desktop(resize_in(
+        pool,
+        &img,
+        resolution.width,
+        resolution.height,
+        filter,
+    ))
+}
+
+/// Rounded outwards, so that every pixel touched by the source area is included.
+fn scale_rect(rect: &Rect, scale_x: f32, scale_y: f32, bounds: &Resolution) -> Rect {
+    let x0 = ((rect.x as f32 * scale_x).floor() as u32).min(bounds.width);
+    let y0 = ((rect.y as f32 * scale_y).floor() as u32).min(bounds.height);
+    let x1 = ((rect.right() as f32 * scale_x).ceil() as u32).min(bounds.width);
+    let y1 = ((rect.bottom() as f32 * scale_y).ceil() as u32).min(bounds.height);
+
+    Rect::new(x0, y0, x1.saturating_sub(x0), y1.saturating_sub(y0))
+}
+
+fn scale_cursor(mut cursor: CursorState, scale_x: f32, scale_y: f32) -> CursorState {
+    cursor.pos_x = (cursor.pos_x as f32 * scale_x).round() as u32;
+    cursor.pos_y = (cursor.pos_y as f32 * scale_y).round() as u32;
+
+    // keeps the size relative to the desktop
+    if let Some(shape) = cursor.shape.as_mut().filter(|x| !x.image.data.is_empty()) {
+        let width = ((shape.image.width as f32 * scale_x).round() as u32).max(1);
+        let height = ((shape.image.height as f32 * scale_y).round() as u32).max(1);
+
+        shape.image = resize(&shape.image, width, height, ScaleFilter::Area);
+        shape.hotspot_x *= scale_x;
+        shape.hotspot_y *= scale_y;
+    }
+
+    cursor
+}
@@ -0,0 +1,181 @@
+//! Bitstream of the lossless `ZstdDelta` codec.
+//!
+//! Every frame is `[u8 kind][u8 color format][u32le width][u32le height]` followed by zstd
+//! compressed pixels, tightly packed row by row. Key frames compress the pixels as is, and
+//! delta frames compress them XORed with the previous frame, which must have the same header.
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::image::{ColorFormat, Image, ImageBuf};
+use crate::util::AsUsize;
+
+const HEADER_LEN: usize = 10;
+
+const KIND_KEY: u8 = 0;
+const KIND_DELTA: u8 = 1;
+
+/// Fast enough to keep up with the desktop, unchanged areas are zero anyway.
+const COMPRESSION_LEVEL: i32 = 1;
+
+/// Encodes frames, keeping the previous one to send deltas against.
+#[derive(Debug, Default)]
+pub struct ZstdDeltaEncoder {
+    prev: Option<(Header, Vec<u8>)>,
+}
+
+impl ZstdDeltaEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encode a key frame if `key` is set or the format has changed, or a delta otherwise.
+    pub fn encode(&mut self, img: Image<&[u8]>, key: bool) -> Result<Vec<u8>> {
+        let header = Header {
+            color_format: img.color_format,
+            width: img.width,
+            height: img.height,
+        };
+        let pixels = packed_pixels(&img)?;
+
+        let (kind, payload) = match &self.prev {
+            Some((prev_header, prev)) if !key && *prev_header == header => {
+                let mut delta = pixels.clone();
+                xor_into(&mut delta, prev);
+                (KIND_DELTA, delta)
+            }
+            _ => (KIND_KEY, pixels.clone()),
+        };
+
+        let mut output = Vec::with_capacity(HEADER_LEN + payload.len() / 4);
+        header.write(kind, &mut output)?;
+        output.extend_from_slice(&zstd::bulk::compress(&payload, COMPRESSION_LEVEL)?);
+
+        self.prev = Some((header, pixels));
+
+        Ok(output)
+    }
+}
+
+/// Decodes frames, keeping the previous one to apply deltas to.
+#[derive(Debug, Default)]
+pub struct ZstdDeltaDecoder {
+    prev: Option<(Header, Vec<u8>)>,
+}
+
+impl ZstdDeltaDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Output is tightly packed, with the same pixels as the encoded image.
+    pub fn decode(&mut self, data: &[u8]) -> Result<ImageBuf> {
+        let (kind, header) = Header::read(data)?;
+        let len = header.data_len()?;
+
+        let mut pixels = zstd::bulk::decompress(&data[HEADER_LEN..], len)?;
+        ensure!(
+            pixels.len() == len,
+            "frame has {} bytes, expected {len}",
+            pixels.len()
+        );
+
+        match kind {
+            KIND_KEY => {}
+            KIND_DELTA => {
+                let prev = match &self.prev {
+                    Some((prev_header, prev)) if *prev_header == header => prev,
+                    Some(_) => bail!("delta frame does not match the previous frame"),
+                    None => bail!("delta frame without a previous frame"),
+                };
+                xor_into(&mut pixels, prev);
+            }
+            other => bail!("unknown frame kind {other}"),
+        }
+
+        self.prev = Some((header.clone(), pixels.clone()));
+
+        Ok(ImageBuf::new(
+            header.width,
+            header.height,
+            header.width * header.color_format.pixel_stride(),
+            header.color_format,
+            pixels.into(),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Header {
+    color_format: ColorFormat,
+    width: u32,
+    height: u32,
+}
+
+impl Header {
+    fn write(&self, kind: u8, output: &mut Vec<u8>) -> Result<()> {
+        let color_format = match self.color_format {
+            ColorFormat::Rgba8888 => 0,
+            ColorFormat::Bgra8888 => 1,
+            ColorFormat::Rgb24 => 2,
+            other => bail!("unsupported color format {other:?}"),
+        };
+
+        output.push(kind);
+        output.push(color_format);
+        output.extend_from_slice(&self.width.to_le_bytes());
+        output.extend_from_slice(&self.height.to_le_bytes());
+
+        Ok(())
+    }
+
+    fn read(data: &[u8]) -> Result<(u8, Self)> {
+        ensure!(data.len() >= HEADER_LEN, "frame too short");
+
+        let color_format = match data[1] {
+            0 => ColorFormat::Rgba8888,
+            1 => ColorFormat::Bgra8888,
+            2 => ColorFormat::Rgb24,
+            other => bail!("unknown color format {other}"),
+        };
+
+        let header = Header {
+            color_format,
+            width: u32::from_le_bytes(data[2..6].try_into().unwrap()),
+            height: u32::from_le_bytes(data[6..10].try_into().unwrap()),
+        };
+
+        Ok((data[0], header))
+    }
+
+    fn data_len(&self) -> Result<usize> {
+        self.width
+            .as_usize()
+            .checked_mul(self.height.as_usize())
+            .and_then(|x| x.checked_mul(self.color_format.pixel_stride().as_usize()))
+            .context("frame too large")
+    }
+}
+
+fn packed_pixels(img: &Image<&[u8]>) -> Result<Vec<u8>> {
+    let line_len = (img.width * img.color_format.pixel_stride()).as_usize();
+    let stride = img.stride.as_usize();
+
+    ensure!(line_len <= stride, "stride shorter than a row");
+    ensure!(
+        img.height == 0 || img.data.len() >= stride * (img.height.as_usize() - 1) + line_len,
+        "image data too short"
+    );
+
+    let mut output = Vec::with_capacity(line_len * img.height.as_usize());
+    for row in img.data.chunks(stride).take(img.height.as_usize()) {
+        output.extend_from_slice(&row[..line_len]);
+    }
+
+    Ok(output)
+}
+
+fn xor_into(target: &mut [u8], other: &[u8]) {
+    for (a, b) in target.iter_mut().zip(other) {
+        *a ^= b;
+    }
+}
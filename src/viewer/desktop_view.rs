@@ -3,6 +3,7 @@ use crate::viewer::compose_renderable::ComposeRenderable;
 use crate::viewer::display_state::DisplayState;
 use crate::{image::ImageBuf, util::PerformanceMonitor};
 use wgpu::{BindGroup, RenderPipeline, Texture};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
 
 pub struct DesktopView {
     composer: ComposeRenderable,
@@ -153,13 +154,17 @@ impl DesktopView {
         }
     }
 
+    pub fn resolution(&self) -> (u32, u32) {
+        (self.compose_texture.width(), self.compose_texture.height())
+    }
+
     pub fn update(&mut self, mut update: DesktopUpdate<ImageBuf>) {
         let next_width = update.desktop.width;
         let next_height = update.desktop.height;
         assert!(
             self.compose_texture.width() == next_width
                 && self.compose_texture.height() == next_height,
-            "image size must not change"
+            "image size must match the view, rebuild the view instead"
         );
 
         if let Some(prev) = self.next_update.take() {
@@ -169,6 +174,29 @@ impl DesktopView {
         self.next_update = Some(update);
     }
 
+    /// Map a position inside the window into desktop coordinates.
+    /// Positions outside of the window are clamped to the nearest edge.
+    pub fn desktop_position(
+        &self,
+        window_size: PhysicalSize<u32>,
+        pos: PhysicalPosition<f64>,
+    ) -> Option<(u32, u32)> {
+        if window_size.width == 0 || window_size.height == 0 {
+            return None;
+        }
+
+        // The desktop is stretched to fill the whole window
+        let map = |pos: f64, window: u32, desktop: u32| {
+            let x = pos * desktop as f64 / window as f64;
+            x.clamp(0.0, (desktop - 1) as f64) as u32
+        };
+
+        Some((
+            map(pos.x, window_size.width, self.compose_texture.width()),
+            map(pos.y, window_size.height, self.compose_texture.height()),
+        ))
+    }
+
     pub fn render(&mut self, state: &DisplayState) -> Result<(), wgpu::SurfaceError> {
         let output = state.surface.get_current_texture()?;
         let output_view = output.texture.create_view(&Default::default());
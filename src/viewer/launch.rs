@@ -1,14 +1,35 @@
-use crate::client::{ClientLaunchArgs, TwilightClient};
+use crate::client::{ClientLaunchArgs, EventCb, TwilightClient};
+use crate::input::InputEvent;
 use crate::viewer::viewer_app::ViewerApp;
 
+use std::path::PathBuf;
 use std::rc::Rc;
 use tokio::runtime::Handle;
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc, oneshot};
 use tokio::task::LocalSet;
 
 pub fn launch(rt: Handle, args: ClientLaunchArgs) -> ! {
+    launch_with(rt, move |callback, input| {
+        TwilightClient::new(callback, args, input)
+    })
+}
+
+/// Launch the viewer to play back a recorded session. Does not need a server.
+pub fn launch_replay(rt: Handle, path: PathBuf) -> ! {
+    // Input is meaningless without a server. Dropping the receiver discards it.
+    launch_with(rt, move |callback, _input| {
+        TwilightClient::replay(callback, path)
+    })
+}
+
+fn launch_with<F>(rt: Handle, create_client: F) -> !
+where
+    F: FnOnce(EventCb, mpsc::UnboundedReceiver<InputEvent>) -> TwilightClient + Send + 'static,
+{
     let mut viewer_app = ViewerApp::build(rt.clone());
     let proxy = viewer_app.create_proxy();
+    let (input_tx, input_rx) = mpsc::unbounded_channel();
+    viewer_app.set_input_sender(input_tx);
     let (quit_tx, quit_rx) = oneshot::channel();
 
     let worker = std::thread::spawn(move || {
@@ -23,7 +44,7 @@ pub fn launch(rt: Handle, args: ClientLaunchArgs) -> ! {
 
         rt.block_on(async move {
             let _guard = local.enter();
-            let client = TwilightClient::new(Rc::new(callback), args);
+            let client = create_client(Rc::new(callback), input_rx);
 
             tokio::select! {
                 biased;
@@ -1,7 +1,8 @@
 mod compose_renderable;
 mod desktop_view;
 mod display_state;
+mod keymap;
 mod launch;
 mod viewer_app;
 
-pub use launch::launch;
+pub use launch::{launch, launch_replay};
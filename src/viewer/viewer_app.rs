@@ -1,27 +1,38 @@
 use crate::client::TwilightClientEvent;
+use crate::input::{InputEvent, MouseButton, WHEEL_DELTA};
 use crate::util::{NonSend, PerformanceStats};
 use crate::viewer::desktop_view::DesktopView;
 use crate::viewer::display_state::DisplayState;
+use crate::viewer::keymap::keycode_to_hid;
 use anyhow::Result;
 use log::{error, info};
+use rustc_hash::FxHashSet;
 use std::io::Write;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 use tokio::runtime::Handle;
+use tokio::sync::mpsc;
 use winit::application::ApplicationHandler;
-use winit::event::WindowEvent;
+use winit::event::{ElementState, MouseScrollDelta, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy};
+use winit::keyboard::PhysicalKey;
 use winit::window::{Window, WindowId};
 
+/// Wheel movement in pixels that is treated as a single notch
+const PIXELS_PER_WHEEL_NOTCH: f64 = 40.0;
+
 pub struct ViewerAppBuilder {
     event_loop: EventLoop<TwilightClientEvent>,
     on_exit: Option<Box<dyn FnOnce() -> Result<()>>>,
+    input: Option<mpsc::UnboundedSender<InputEvent>>,
     _guard: NonSend,
 }
 
 pub struct ViewerApp {
     window: Option<Rc<Window>>,
     on_exit: Option<Box<dyn FnOnce() -> Result<()>>>,
+    input: Option<mpsc::UnboundedSender<InputEvent>>,
+    pressed_keys: FxHashSet<u16>,
     display_state: Option<DisplayState>,
     desktop_view: Option<DesktopView>,
     last_log_print: Instant,
@@ -39,6 +50,7 @@ impl ViewerApp {
                 .build()
                 .unwrap(),
             on_exit: None,
+            input: None,
             _guard: Default::default(),
         }
     }
@@ -49,6 +61,11 @@ impl ViewerAppBuilder {
         self.on_exit = Some(callback);
     }
 
+    /// Input events captured from the window are sent here.
+    pub fn set_input_sender(&mut self, sender: mpsc::UnboundedSender<InputEvent>) {
+        self.input = Some(sender);
+    }
+
     pub fn create_proxy(&self) -> EventLoopProxy<TwilightClientEvent> {
         self.event_loop.create_proxy()
     }
@@ -57,6 +74,8 @@ impl ViewerAppBuilder {
         let mut app = Box::new(ViewerApp {
             window: None,
             on_exit: self.on_exit,
+            input: self.input,
+            pressed_keys: Default::default(),
             display_state: None,
             desktop_view: None,
             last_log_print: Instant::now(),
@@ -74,6 +93,36 @@ impl ViewerAppBuilder {
     }
 }
 
+impl ViewerApp {
+    fn send_input(&self, event: InputEvent) {
+        // Input is only meaningful after the desktop is shown
+        if self.desktop_view.is_none() {
+            return;
+        }
+
+        if let Some(input) = self.input.as_ref() {
+            // Error means the client is gone, which is reported by TwilightClientEvent::Closed
+            let _ = input.send(event);
+        }
+    }
+
+    /// Replace the desktop view, as the desktop resolution has changed.
+    fn rebuild_desktop_view(&mut self, width: u32, height: u32) {
+        let state = self.display_state.as_mut().unwrap();
+        self.desktop_view = Some(DesktopView::new(state, width, height));
+    }
+
+    /// Release every key still held, so that none of them gets stuck on the server.
+    fn release_all_keys(&mut self) {
+        for usage in std::mem::take(&mut self.pressed_keys) {
+            self.send_input(InputEvent::Key {
+                usage,
+                pressed: false,
+            });
+        }
+    }
+}
+
 impl ApplicationHandler<TwilightClientEvent> for ViewerApp {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         event_loop.set_control_flow(ControlFlow::Wait);
@@ -88,7 +137,7 @@ impl ApplicationHandler<TwilightClientEvent> for ViewerApp {
             self.window = Some(window);
         }
 
-        let window = Rc::clone(&self.window.as_ref().expect("assigned just above"));
+        let window = Rc::clone(self.window.as_ref().expect("assigned just above"));
 
         //FIXME: web needs real await
         self.display_state = Some(pollster::block_on(DisplayState::new(window)).unwrap());
@@ -118,6 +167,77 @@ impl ApplicationHandler<TwilightClientEvent> for ViewerApp {
             WindowEvent::ScaleFactorChanged { .. } => {
                 window.request_redraw();
             }
+            WindowEvent::Focused(false) => {
+                self.release_all_keys();
+            }
+            WindowEvent::KeyboardInput {
+                event,
+                is_synthetic: false,
+                ..
+            } => {
+                let usage = match event.physical_key {
+                    PhysicalKey::Code(code) => keycode_to_hid(code),
+                    PhysicalKey::Unidentified(_) => None,
+                };
+
+                if let Some(usage) = usage {
+                    let pressed = event.state == ElementState::Pressed;
+                    if pressed {
+                        self.pressed_keys.insert(usage);
+                    } else {
+                        self.pressed_keys.remove(&usage);
+                    }
+
+                    self.send_input(InputEvent::Key { usage, pressed });
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let pos = self
+                    .desktop_view
+                    .as_ref()
+                    .and_then(|view| view.desktop_position(state.size, position));
+
+                if let Some((x, y)) = pos {
+                    self.send_input(InputEvent::PointerMove { x, y });
+                }
+            }
+            WindowEvent::MouseInput {
+                state: button_state,
+                button,
+                ..
+            } => {
+                let button = match button {
+                    winit::event::MouseButton::Left => MouseButton::Left,
+                    winit::event::MouseButton::Right => MouseButton::Right,
+                    winit::event::MouseButton::Middle => MouseButton::Middle,
+                    winit::event::MouseButton::Back => MouseButton::Back,
+                    winit::event::MouseButton::Forward => MouseButton::Forward,
+                    winit::event::MouseButton::Other(_) => return,
+                };
+
+                self.send_input(InputEvent::PointerButton {
+                    button,
+                    pressed: button_state == ElementState::Pressed,
+                });
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                // winit uses positive x for scrolling left
+                let (delta_x, delta_y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (
+                        -x as f64 * WHEEL_DELTA as f64,
+                        y as f64 * WHEEL_DELTA as f64,
+                    ),
+                    MouseScrollDelta::PixelDelta(pos) => (
+                        -pos.x * WHEEL_DELTA as f64 / PIXELS_PER_WHEEL_NOTCH,
+                        pos.y * WHEEL_DELTA as f64 / PIXELS_PER_WHEEL_NOTCH,
+                    ),
+                };
+
+                self.send_input(InputEvent::PointerWheel {
+                    delta_x: delta_x.round() as i32,
+                    delta_y: delta_y.round() as i32,
+                });
+            }
             WindowEvent::RedrawRequested => {
                 let state = self.display_state.as_mut().unwrap();
 
@@ -165,22 +285,30 @@ impl ApplicationHandler<TwilightClientEvent> for ViewerApp {
     fn user_event(&mut self, event_loop: &ActiveEventLoop, event: TwilightClientEvent) {
         match event {
             TwilightClientEvent::Connected(info) => {
+                // sent again whenever the stream is re-announced
                 info!("Connected to {info:?}");
 
-                let width = info.resolution.width;
-                let height = info.resolution.height;
-
-                let state = self.display_state.as_mut().unwrap();
-                self.desktop_view = Some(DesktopView::new(state, width, height));
+                self.rebuild_desktop_view(info.resolution.width, info.resolution.height);
             }
             TwilightClientEvent::NextFrame(update) => {
+                if self.window.is_none() {
+                    return;
+                }
+
+                let (width, height) = (update.desktop.width, update.desktop.height);
+                let view_resolution = self.desktop_view.as_ref().map(|x| x.resolution());
+                if view_resolution != Some((width, height)) {
+                    log::warn!("Frame of {width}x{height} arrived without announcement");
+                    self.rebuild_desktop_view(width, height);
+                }
+
                 if let Some(window) = self.window.as_ref() {
                     window.request_redraw();
-                    self.desktop_view
-                        .as_mut()
-                        .expect("resolution not set before render")
-                        .update(update);
                 }
+                self.desktop_view
+                    .as_mut()
+                    .expect("rebuilt above")
+                    .update(update);
             }
             TwilightClientEvent::Closed(r) => {
                 if let Err(e) = r {
@@ -0,0 +1,94 @@
+#![cfg(feature = "av1")]
+
+use std::time::{Duration, Instant};
+
+use twilight::image::{ColorFormat, ImageBuf};
+use twilight::util::{DesktopUpdate, Timings};
+use twilight::video::decoder::av1::Av1Decoder;
+use twilight::video::decoder::DecoderStage;
+use twilight::video::encoder::av1::Av1Encoder;
+use twilight::video::encoder::EncoderStage;
+
+const WIDTH: u32 = 96;
+const HEIGHT: u32 = 64;
+
+/// Smooth gradient, which any codec reproduces closely
+fn gradient() -> ImageBuf {
+    let mut img = ImageBuf::alloc(WIDTH, HEIGHT, Some(WIDTH * 4), ColorFormat::Bgra8888);
+    for (i, pixel) in img.data.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i as u32 % WIDTH, i as u32 / WIDTH);
+        pixel.copy_from_slice(&[(x * 2) as u8, (y * 3) as u8, 128, 255]);
+    }
+    img
+}
+
+fn update(img: ImageBuf) -> DesktopUpdate<ImageBuf> {
+    let mut timings = Timings::new();
+    timings.capture = Instant::now().into();
+
+    DesktopUpdate {
+        cursor: None,
+        timings,
+        damage: None,
+        scale: 1.0,
+        desktop: img,
+    }
+}
+
+fn mean_abs_diff(a: &ImageBuf, b: &ImageBuf) -> f64 {
+    let sum: u64 = a
+        .data
+        .chunks_exact(4)
+        .zip(b.data.chunks_exact(4))
+        .flat_map(|(a, b)| (0..3).map(move |i| a[i].abs_diff(b[i]) as u64))
+        .sum();
+    sum as f64 / (WIDTH * HEIGHT * 3) as f64
+}
+
+#[test]
+fn encoded_frames_decode_close_to_source() {
+    let encoder = Av1Encoder::new(2000, 10).unwrap();
+    let (tx, rx) = flume::unbounded();
+    encoder.set_output(tx);
+
+    // rav1e may hold a few frames back before emitting the first packet
+    let input = encoder.input();
+    let sender = std::thread::spawn(move || {
+        for _ in 0..30 {
+            if input.send(update(gradient())).is_err() {
+                break;
+            }
+        }
+    });
+
+    let source = gradient();
+    let mut decoder = Av1Decoder::new(WIDTH, HEIGHT).unwrap();
+    let mut pictures = 0;
+    for _ in 0..10 {
+        let packet = rx.recv_timeout(Duration::from_secs(60)).unwrap();
+
+        // every packet is fed, including those which complete no picture yet
+        let Some(img) = decoder.decode_frame(&packet.desktop.data).unwrap() else {
+            continue;
+        };
+        pictures += 1;
+
+        assert_eq!((img.width, img.height), (WIDTH, HEIGHT));
+        assert_eq!(img.color_format, ColorFormat::Bgra8888);
+        let diff = mean_abs_diff(&img, &source);
+        assert!(diff < 8.0, "mean difference {diff}");
+    }
+
+    assert!(pictures > 0);
+
+    encoder.shutdown();
+    drop(rx);
+    sender.join().unwrap();
+}
+
+#[test]
+fn garbage_is_an_error() {
+    let mut decoder = Av1Decoder::new(WIDTH, HEIGHT).unwrap();
+
+    assert!(decoder.decode(&[0xFF; 64]).is_err());
+}
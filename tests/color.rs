@@ -0,0 +1,295 @@
+mod common;
+
+use common::noise;
+use twilight::image::{
+    convert_color, convert_color_with, ColorFormat, Image, ImageBuf, YuvMatrix, YuvParams, YuvRange,
+};
+
+/// Odd on purpose, to cover partial SIMD lanes and chroma blocks clipped at the edges
+const WIDTH: u32 = 37;
+const HEIGHT: u32 = 23;
+
+const RGB_FORMATS: [ColorFormat; 3] = [
+    ColorFormat::Rgba8888,
+    ColorFormat::Bgra8888,
+    ColorFormat::Rgb24,
+];
+
+const YUV_FORMATS: [ColorFormat; 2] = [ColorFormat::Nv12, ColorFormat::I420];
+
+fn all_params() -> Vec<YuvParams> {
+    let mut params = Vec::new();
+    for matrix in [YuvMatrix::Bt601, YuvMatrix::Bt709] {
+        for range in [YuvRange::Limited, YuvRange::Full] {
+            params.push(YuvParams { matrix, range });
+        }
+    }
+    params
+}
+
+/// Image with `padding` bytes at the end of every row
+fn random_image(color_format: ColorFormat, padding: u32, seed: u32) -> ImageBuf {
+    let stride = WIDTH * color_format.pixel_stride() + padding;
+    let len = color_format.buffer_len(stride, HEIGHT);
+    Image::new(WIDTH, HEIGHT, stride, color_format, noise(seed, len).into())
+}
+
+/// Floating point conversion straight from the definitions of the standards
+struct Reference {
+    kr: f64,
+    kb: f64,
+    y_range: f64,
+    c_range: f64,
+    y_offset: f64,
+}
+
+impl Reference {
+    fn new(params: YuvParams) -> Self {
+        let (kr, kb) = match params.matrix {
+            YuvMatrix::Bt601 => (0.299, 0.114),
+            YuvMatrix::Bt709 => (0.2126, 0.0722),
+        };
+        let (y_range, c_range, y_offset) = match params.range {
+            YuvRange::Limited => (219.0, 224.0, 16.0),
+            YuvRange::Full => (255.0, 255.0, 0.0),
+        };
+
+        Reference {
+            kr,
+            kb,
+            y_range: y_range / 255.0,
+            c_range: c_range / 255.0,
+            y_offset,
+        }
+    }
+
+    fn kg(&self) -> f64 {
+        1.0 - self.kr - self.kb
+    }
+
+    fn yuv(&self, [r, g, b]: [f64; 3]) -> [f64; 3] {
+        let y = self.kr * r + self.kg() * g + self.kb * b;
+        [
+            self.y_offset + self.y_range * y,
+            128.0 + self.c_range * (b - y) / (2.0 * (1.0 - self.kb)),
+            128.0 + self.c_range * (r - y) / (2.0 * (1.0 - self.kr)),
+        ]
+    }
+
+    fn rgb(&self, [y, u, v]: [f64; 3]) -> [f64; 3] {
+        let y = (y - self.y_offset) / self.y_range;
+        let u = (u - 128.0) / self.c_range;
+        let v = (v - 128.0) / self.c_range;
+
+        let r = y + 2.0 * (1.0 - self.kr) * v;
+        let b = y + 2.0 * (1.0 - self.kb) * u;
+        let g = (y - self.kr * r - self.kb * b) / self.kg();
+
+        [r, g, b].map(|x| x.clamp(0.0, 255.0))
+    }
+}
+
+/// Red, green and blue of a packed pixel
+fn rgb_at(img: &ImageBuf, x: u32, y: u32) -> [u8; 3] {
+    let i = (y * img.stride + x * img.color_format.pixel_stride()) as usize;
+    let px = &img.data[i..];
+    match img.color_format {
+        ColorFormat::Bgra8888 => [px[2], px[1], px[0]],
+        _ => [px[0], px[1], px[2]],
+    }
+}
+
+/// Luma and chroma of a planar pixel
+fn yuv_at(img: &ImageBuf, x: u32, y: u32) -> [u8; 3] {
+    let luma_len = (img.stride * img.height) as usize;
+    let luma = img.data[(y * img.stride + x) as usize];
+    let (cx, cy) = ((x / 2) as usize, (y / 2) as usize);
+
+    match img.color_format {
+        ColorFormat::Nv12 => {
+            let i = luma_len + cy * img.stride as usize + cx * 2;
+            [luma, img.data[i], img.data[i + 1]]
+        }
+        ColorFormat::I420 => {
+            let stride = img.stride.div_ceil(2) as usize;
+            let plane = stride * img.height.div_ceil(2) as usize;
+            let i = luma_len + cy * stride + cx;
+            [luma, img.data[i], img.data[i + plane]]
+        }
+        other => panic!("{other:?} is not planar"),
+    }
+}
+
+fn assert_close(actual: [u8; 3], expected: [f64; 3], what: &str) {
+    for (a, e) in actual.into_iter().zip(expected) {
+        assert!(
+            (a as f64 - e).abs() <= 1.0,
+            "{what}: {actual:?} is not close to {expected:?}"
+        );
+    }
+}
+
+#[test]
+fn rgb_to_yuv_matches_reference() {
+    for params in all_params() {
+        let reference = Reference::new(params);
+
+        for (i, &src_format) in RGB_FORMATS.iter().enumerate() {
+            let src = random_image(src_format, 5, i as u32 + 1);
+
+            for dst_format in YUV_FORMATS {
+                let mut dst = ImageBuf::alloc(WIDTH, HEIGHT, None, dst_format);
+                convert_color_with(&src, &mut dst, params);
+
+                for y in 0..HEIGHT {
+                    for x in 0..WIDTH {
+                        let [luma, ..] = yuv_at(&dst, x, y);
+                        let [expected, ..] = reference.yuv(rgb_at(&src, x, y).map(f64::from));
+                        assert!(
+                            (luma as f64 - expected).abs() <= 1.0,
+                            "{params:?} {src_format:?} to {dst_format:?} luma at ({x}, {y})"
+                        );
+                    }
+                }
+
+                // chroma is of the 2x2 block average, rounded like the converter does
+                for cy in 0..HEIGHT.div_ceil(2) {
+                    for cx in 0..WIDTH.div_ceil(2) {
+                        let mut sum = [0u32; 3];
+                        let mut count = 0;
+                        for y in (cy * 2)..(cy * 2 + 2).min(HEIGHT) {
+                            for x in (cx * 2)..(cx * 2 + 2).min(WIDTH) {
+                                let px = rgb_at(&src, x, y);
+                                for (s, p) in sum.iter_mut().zip(px) {
+                                    *s += p as u32;
+                                }
+                                count += 1;
+                            }
+                        }
+                        let average = sum.map(|x| ((x + count / 2) / count) as f64);
+
+                        let [_, u, v] = yuv_at(&dst, cx * 2, cy * 2);
+                        let [_, eu, ev] = reference.yuv(average);
+                        assert_close(
+                            [0, u, v],
+                            [0.0, eu, ev],
+                            &format!("{params:?} {src_format:?} to {dst_format:?} chroma"),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn yuv_to_rgb_matches_reference() {
+    for params in all_params() {
+        let reference = Reference::new(params);
+
+        for (i, &src_format) in YUV_FORMATS.iter().enumerate() {
+            let src = random_image(src_format, 3, i as u32 + 10);
+
+            for dst_format in RGB_FORMATS {
+                let mut dst = ImageBuf::alloc(WIDTH, HEIGHT, None, dst_format);
+                convert_color_with(&src, &mut dst, params);
+
+                for y in 0..HEIGHT {
+                    for x in 0..WIDTH {
+                        let expected = reference.rgb(yuv_at(&src, x, y).map(f64::from));
+                        assert_close(
+                            rgb_at(&dst, x, y),
+                            expected,
+                            &format!("{params:?} {src_format:?} to {dst_format:?} at ({x}, {y})"),
+                        );
+                    }
+                }
+
+                if dst_format != ColorFormat::Rgb24 {
+                    let stride = dst.stride as usize;
+                    for y in 0..HEIGHT as usize {
+                        let row = &dst.data[y * stride..][..WIDTH as usize * 4];
+                        assert!(row.chunks(4).all(|px| px[3] == 255), "alpha is opaque");
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn flat_colors_survive_round_trip() {
+    let colors = [
+        [0, 0, 0],
+        [255, 255, 255],
+        [255, 0, 0],
+        [0, 255, 0],
+        [0, 0, 255],
+        [30, 144, 255],
+        [128, 128, 128],
+    ];
+
+    for params in all_params() {
+        for color in colors {
+            let mut src = ImageBuf::alloc(WIDTH, HEIGHT, None, ColorFormat::Rgb24);
+            let stride = src.stride as usize;
+            for y in 0..HEIGHT as usize {
+                for px in src.data[y * stride..][..WIDTH as usize * 3].chunks_mut(3) {
+                    px.copy_from_slice(&color);
+                }
+            }
+
+            for yuv_format in YUV_FORMATS {
+                let mut yuv = ImageBuf::alloc(WIDTH, HEIGHT, None, yuv_format);
+                let mut back = ImageBuf::alloc(WIDTH, HEIGHT, None, ColorFormat::Rgb24);
+                convert_color_with(&src, &mut yuv, params);
+                convert_color_with(&yuv, &mut back, params);
+
+                for y in 0..HEIGHT {
+                    for x in 0..WIDTH {
+                        let actual = rgb_at(&back, x, y);
+                        assert!(
+                            actual.iter().zip(&color).all(|(a, c)| a.abs_diff(*c) <= 2),
+                            "{params:?} {yuv_format:?}: {color:?} became {actual:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn nv12_and_i420_convert_losslessly() {
+    let src = random_image(ColorFormat::Nv12, 7, 20);
+    let mut i420 = ImageBuf::alloc(WIDTH, HEIGHT, None, ColorFormat::I420);
+    let mut nv12 = ImageBuf::alloc(WIDTH, HEIGHT, None, ColorFormat::Nv12);
+
+    convert_color(&src, &mut i420);
+    convert_color(&i420, &mut nv12);
+
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            assert_eq!(yuv_at(&i420, x, y), yuv_at(&src, x, y));
+            assert_eq!(yuv_at(&nv12, x, y), yuv_at(&src, x, y));
+        }
+    }
+}
+
+#[test]
+fn packed_formats_swap_channels() {
+    for (i, &src_format) in RGB_FORMATS.iter().enumerate() {
+        let src = random_image(src_format, 2, i as u32 + 30);
+
+        for dst_format in RGB_FORMATS {
+            let mut dst = ImageBuf::alloc(WIDTH, HEIGHT, None, dst_format);
+            convert_color(&src, &mut dst);
+
+            for y in 0..HEIGHT {
+                for x in 0..WIDTH {
+                    assert_eq!(rgb_at(&dst, x, y), rgb_at(&src, x, y));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use twilight::util::{FramePool, PoolStats, PooledBuf};
+
+#[test]
+fn returned_buffer_is_reused() {
+    let pool = FramePool::new(4);
+
+    let buf = pool.buffer(1000);
+    assert_eq!(buf.len(), 1000);
+    assert!(buf.is_pooled());
+    let capacity = buf.capacity();
+    drop(buf);
+
+    let buf = pool.buffer(500);
+    assert_eq!(buf.len(), 500);
+    assert_eq!(buf.capacity(), capacity);
+    drop(buf);
+
+    assert_eq!(
+        pool.stats(),
+        PoolStats {
+            allocated: 1,
+            reused: 1,
+            returned: 2,
+            discarded: 0,
+            idle: 1,
+            idle_bytes: capacity,
+        }
+    );
+    assert_eq!(pool.stats().hit_rate(), 0.5);
+}
+
+#[test]
+fn smallest_fitting_buffer_is_taken() {
+    let pool = FramePool::new(4);
+
+    let bufs: Vec<PooledBuf> = [100, 1000, 10000]
+        .into_iter()
+        .map(|x| pool.with_capacity(x))
+        .collect();
+    let capacities: Vec<usize> = bufs.iter().map(|x| x.capacity()).collect();
+    drop(bufs);
+
+    let buf = pool.with_capacity(500);
+    assert!(buf.is_empty());
+    assert_eq!(buf.capacity(), capacities[1]);
+
+    // none is large enough
+    let large = pool.with_capacity(capacities[2] + 1);
+    assert_eq!(pool.stats().allocated, 4);
+    assert_eq!(pool.stats().reused, 1);
+    drop(large);
+}
+
+#[test]
+fn full_pool_keeps_largest_buffers() {
+    let pool = FramePool::new(2);
+
+    let bufs: Vec<PooledBuf> = [1000, 10, 100]
+        .into_iter()
+        .map(|x| pool.buffer(x))
+        .collect();
+    let capacities: Vec<usize> = bufs.iter().map(|x| x.capacity()).collect();
+    drop(bufs);
+
+    // the third one replaces the smallest
+    let stats = pool.stats();
+    assert_eq!(stats.returned, 3);
+    assert_eq!(stats.discarded, 1);
+    assert_eq!(stats.idle, 2);
+    assert_eq!(stats.idle_bytes, capacities[0] + capacities[2]);
+
+    // smaller than every buffer kept, so dropped for good
+    let large = pool.buffer(1000);
+    let medium = pool.buffer(100);
+    let small = pool.buffer(1);
+    drop((large, medium, small));
+
+    let stats = pool.stats();
+    assert_eq!(stats.returned, 5);
+    assert_eq!(stats.discarded, 2);
+    assert_eq!(stats.idle_bytes, capacities[0] + capacities[2]);
+}
+
+#[test]
+fn buffer_outliving_pool_is_freed() {
+    let pool = FramePool::new(4);
+    let buf = pool.buffer(10);
+
+    drop(pool);
+    assert!(!buf.is_pooled());
+}
+
+#[test]
+fn detached_buffer_never_returns() {
+    let pool = FramePool::new(4);
+
+    let vec = pool.buffer(10).into_vec();
+    assert_eq!(vec.len(), 10);
+    assert_eq!(pool.stats().returned, 0);
+
+    let buf = PooledBuf::from(vec);
+    assert!(!buf.is_pooled());
+    drop(buf);
+    assert_eq!(pool.stats().idle, 0);
+}
+
+#[test]
+fn clone_is_taken_from_same_pool() {
+    let pool = FramePool::new(4);
+
+    let mut buf = pool.buffer(3);
+    buf.copy_from_slice(&[1, 2, 3]);
+
+    let copy = buf.clone();
+    assert_eq!(copy, buf);
+    assert!(copy.is_pooled());
+    assert_eq!(pool.stats().allocated, 2);
+}
@@ -0,0 +1,151 @@
+use twilight::image::{ColorFormat, Image, ImageBuf, Rect};
+use twilight::video::encoder::JpegParams;
+use twilight::video::hybrid::{HybridFrameDecoder, HybridFrameEncoder};
+
+/// Not a multiple of the tile size, so edge tiles are smaller
+const WIDTH: u32 = 150;
+const HEIGHT: u32 = 90;
+
+const PARAMS: JpegParams = JpegParams {
+    quality: 90,
+    yuv444: false,
+};
+
+/// Stripes of a few colors with sharp edges, like text, which is always sent lossless
+fn text_like(color_format: ColorFormat, shift: u32) -> ImageBuf {
+    let colors = [[0, 0, 0, 255], [255, 255, 255, 255], [30, 90, 200, 255]];
+    let pixel = color_format.pixel_stride();
+
+    let mut data = Vec::new();
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let color = colors[((x / 3 + y / 5 + shift) % 3) as usize];
+            data.extend_from_slice(&color[..pixel as usize]);
+        }
+    }
+
+    Image::new(WIDTH, HEIGHT, WIDTH * pixel, color_format, data.into())
+}
+
+fn tile_count(frame: &[u8]) -> u32 {
+    u32::from_le_bytes(frame[10..14].try_into().unwrap())
+}
+
+fn assert_identical(decoded: &ImageBuf, expected: &ImageBuf) {
+    assert_eq!(
+        (decoded.width, decoded.height, decoded.color_format),
+        (expected.width, expected.height, expected.color_format)
+    );
+    assert_eq!(decoded.stride, expected.stride);
+    assert!(decoded.data[..] == expected.data[..], "pixels differ");
+}
+
+#[test]
+fn palette_tiles_are_bit_identical() {
+    for color_format in [
+        ColorFormat::Bgra8888,
+        ColorFormat::Rgba8888,
+        ColorFormat::Rgb24,
+    ] {
+        let frame = text_like(color_format, 0);
+
+        let data = HybridFrameEncoder::new()
+            .encode(&frame, None, false, PARAMS)
+            .unwrap();
+        assert_eq!(data[0], 0, "frame kind");
+        assert_eq!(tile_count(&data), 3 * 2);
+
+        let decoded = HybridFrameDecoder::new().decode(&data).unwrap();
+        assert_identical(&decoded, &frame);
+    }
+}
+
+#[test]
+fn delta_frame_patches_only_changed_tiles() {
+    let mut encoder = HybridFrameEncoder::new();
+    let mut decoder = HybridFrameDecoder::new();
+
+    let mut frame = text_like(ColorFormat::Bgra8888, 0);
+    let key = encoder.encode(&frame, None, false, PARAMS).unwrap();
+    decoder.decode(&key).unwrap();
+
+    // a few pixels in the middle tile of the bottom row
+    let stride = frame.stride as usize;
+    for y in 70..73 {
+        frame.data[y * stride + 80 * 4..][..4 * 4].copy_from_slice(&[0x40; 16]);
+    }
+
+    let delta = encoder.encode(&frame, None, false, PARAMS).unwrap();
+    assert_eq!(delta[0], 1, "frame kind");
+    assert_eq!(tile_count(&delta), 1);
+    assert!(delta.len() < key.len());
+
+    assert_identical(&decoder.decode(&delta).unwrap(), &frame);
+
+    // nothing changed
+    let unchanged = encoder.encode(&frame, None, false, PARAMS).unwrap();
+    assert_eq!(tile_count(&unchanged), 0);
+    assert_identical(&decoder.decode(&unchanged).unwrap(), &frame);
+}
+
+#[test]
+fn damage_limits_the_changed_tiles() {
+    let mut encoder = HybridFrameEncoder::new();
+    encoder
+        .encode(&text_like(ColorFormat::Bgra8888, 0), None, false, PARAMS)
+        .unwrap();
+
+    // every tile changed, but only the damaged ones are looked at
+    let damage = [Rect::new(0, 0, 10, 10)];
+    let delta = encoder
+        .encode(
+            &text_like(ColorFormat::Bgra8888, 1),
+            Some(&damage),
+            false,
+            PARAMS,
+        )
+        .unwrap();
+    assert_eq!(delta[0], 1, "frame kind");
+    assert_eq!(tile_count(&delta), 1);
+}
+
+#[test]
+fn mismatched_delta_is_rejected() {
+    let mut encoder = HybridFrameEncoder::new();
+    encoder
+        .encode(&text_like(ColorFormat::Bgra8888, 0), None, false, PARAMS)
+        .unwrap();
+    let delta = encoder
+        .encode(&text_like(ColorFormat::Bgra8888, 1), None, false, PARAMS)
+        .unwrap();
+    assert_eq!(delta[0], 1, "frame kind");
+
+    // no previous frame
+    assert!(HybridFrameDecoder::new().decode(&delta).is_err());
+
+    // previous frame in another format
+    let mut decoder = HybridFrameDecoder::new();
+    let other = HybridFrameEncoder::new()
+        .encode(&text_like(ColorFormat::Rgb24, 0), None, false, PARAMS)
+        .unwrap();
+    decoder.decode(&other).unwrap();
+    assert!(decoder.decode(&delta).is_err());
+}
+
+#[test]
+fn key_frame_larger_than_its_tiles_is_rejected() {
+    let mut data = HybridFrameEncoder::new()
+        .encode(&text_like(ColorFormat::Bgra8888, 0), None, false, PARAMS)
+        .unwrap();
+
+    // claims a huge frame, which would not be allocated
+    data[2..6].copy_from_slice(&u32::MAX.to_le_bytes());
+    data[6..10].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(HybridFrameDecoder::new().decode(&data).is_err());
+
+    // or the tile count of such a frame, without the data for them
+    data[2..6].copy_from_slice(&(64u32 << 16).to_le_bytes());
+    data[6..10].copy_from_slice(&(64u32 << 15).to_le_bytes());
+    data[10..14].copy_from_slice(&(1u32 << 31).to_le_bytes());
+    assert!(HybridFrameDecoder::new().decode(&data).is_err());
+}
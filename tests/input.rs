@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use flatbuffers::FlatBufferBuilder;
+use twilight::input::injector::RecordingInjector;
+use twilight::input::{InputEvent, MouseButton, WHEEL_DELTA};
+use twilight::server::{ServerConfig, TwilightServer};
+
+fn events() -> Vec<InputEvent> {
+    let mut events = vec![
+        InputEvent::Key {
+            usage: 0x04,
+            pressed: true,
+        },
+        InputEvent::Key {
+            usage: 0xE7,
+            pressed: false,
+        },
+        InputEvent::PointerMove { x: 0, y: 0 },
+        InputEvent::PointerMove { x: 3839, y: 2159 },
+        InputEvent::PointerWheel {
+            delta_x: -WHEEL_DELTA,
+            delta_y: WHEEL_DELTA * 3,
+        },
+    ];
+
+    for button in [
+        MouseButton::Left,
+        MouseButton::Right,
+        MouseButton::Middle,
+        MouseButton::Back,
+        MouseButton::Forward,
+    ] {
+        for pressed in [true, false] {
+            events.push(InputEvent::PointerButton { button, pressed });
+        }
+    }
+
+    events
+}
+
+/// Size prefixed, as sent by the client
+fn message(event: &InputEvent) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::new();
+    let frame = event.build(&mut builder);
+    builder.finish_size_prefixed(frame, None);
+    builder.finished_data().to_vec()
+}
+
+#[test]
+fn events_survive_round_trip() {
+    for event in events() {
+        assert_eq!(InputEvent::parse(&message(&event)).unwrap(), event);
+    }
+}
+
+#[test]
+fn short_messages_are_rejected() {
+    let msg = message(&InputEvent::PointerMove { x: 1, y: 2 });
+
+    assert!(InputEvent::parse(&msg[..3]).is_err());
+    assert!(InputEvent::parse(&msg[..msg.len() - 1]).is_err());
+}
+
+#[test]
+fn server_injects_events_of_input_channel() {
+    let injector = Arc::new(RecordingInjector::new());
+    let server = TwilightServer::with_injector(ServerConfig::default(), injector.clone());
+    let channel = server.write().create_channel();
+    channel.set_accept_input(true);
+
+    for event in events() {
+        server.read().recv_message(&channel, &message(&event));
+    }
+
+    assert_eq!(injector.take(), events());
+}
+
+#[test]
+fn server_ignores_events_of_other_channels() {
+    let injector = Arc::new(RecordingInjector::new());
+    let server = TwilightServer::with_injector(ServerConfig::default(), injector.clone());
+    let channel = server.write().create_channel();
+
+    let event = InputEvent::PointerMove { x: 1, y: 2 };
+    server.read().recv_message(&channel, &message(&event));
+    assert!(injector.events().is_empty());
+
+    channel.set_accept_input(true);
+    server.read().recv_message(&channel, &message(&event));
+    assert_eq!(injector.events(), [event]);
+}
+
+#[test]
+fn server_skips_invalid_messages() {
+    let injector = Arc::new(RecordingInjector::new());
+    let server = TwilightServer::with_injector(ServerConfig::default(), injector.clone());
+    let channel = server.write().create_channel();
+    channel.set_accept_input(true);
+
+    let first = InputEvent::Key {
+        usage: 0x04,
+        pressed: true,
+    };
+    let second = InputEvent::Key {
+        usage: 0x04,
+        pressed: false,
+    };
+
+    server.read().recv_message(&channel, &message(&first));
+    server.read().recv_message(&channel, &[1, 0, 0, 0, 0xFF]);
+    server.read().recv_message(&channel, &[]);
+    server.read().recv_message(&channel, &message(&second));
+
+    assert_eq!(injector.events(), [first, second]);
+}
@@ -0,0 +1,114 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use twilight::input::injector::NoopInjector;
+use twilight::network::dto::video::{DesktopCodec, RefreshRate, Resolution};
+use twilight::server::{
+    DesktopCaptureMethod, PacingConfig, ServerConfig, SyntheticCaptureConfig, TwilightServer,
+    VideoConfig,
+};
+use twilight::util::StageWorker;
+use twilight::video::capture_pipeline;
+
+const MONITOR: &str = "synthetic";
+
+/// Stage threads are counted for the whole process, so tests starting them take turns
+static SERIAL: Mutex<()> = Mutex::new(());
+
+const CODECS: [DesktopCodec; 4] = [
+    DesktopCodec::Jpeg,
+    DesktopCodec::H264,
+    DesktopCodec::Lossless,
+    DesktopCodec::Hybrid,
+];
+
+fn config() -> ServerConfig {
+    let defaults = ServerConfig::default();
+
+    ServerConfig {
+        desktop_capture_method: Some(DesktopCaptureMethod::Synthetic),
+        synthetic: SyntheticCaptureConfig {
+            resolution: Resolution {
+                width: 320,
+                height: 240,
+            },
+            refresh_rate: RefreshRate { num: 120, den: 1 },
+            ..defaults.synthetic
+        },
+        video: VideoConfig {
+            pacing: PacingConfig {
+                max_fps: 120,
+                ..defaults.video.pacing
+            },
+            ..defaults.video
+        },
+        ..defaults
+    }
+}
+
+#[test]
+fn shutdown_joins_every_stage() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let half = Resolution {
+        width: 160,
+        height: 120,
+    };
+
+    for codec in CODECS {
+        for target in [None, Some(&half)] {
+            let output = capture_pipeline(&config(), MONITOR, Some(codec), target, None).unwrap();
+
+            // capture, pacer and encoder, along with the scaler if downscaling
+            let stages = if target.is_some() { 4 } else { 3 };
+            assert_eq!(StageWorker::running(), stages, "{codec:?} {target:?}");
+
+            output
+                .rx
+                .recv_timeout(Duration::from_secs(10))
+                .expect("no frame was encoded");
+
+            // frames keep coming, and nobody receives them while it stops
+            output.shutdown();
+            assert_eq!(StageWorker::running(), 0, "{codec:?} {target:?}");
+        }
+    }
+}
+
+#[test]
+fn drop_stops_every_stage() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+
+    for codec in CODECS {
+        let output = capture_pipeline(&config(), MONITOR, Some(codec), None, None).unwrap();
+        drop(output);
+
+        assert_eq!(StageWorker::running(), 0, "{codec:?}");
+    }
+}
+
+#[test]
+fn closed_channel_ends_session() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    runtime.block_on(async {
+        let server = TwilightServer::with_injector(config(), Arc::new(NoopInjector));
+        let channel = server.write().create_channel();
+
+        server
+            .write()
+            .subscribe_desktop(MONITOR, Arc::clone(&channel), None, None, None)
+            .unwrap();
+        assert_eq!(StageWorker::running(), 3);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        channel.close();
+
+        // the session stops its stages by itself
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while StageWorker::running() > 0 {
+            assert!(Instant::now() < deadline, "stage threads leaked");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    });
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use common::noise;
+use twilight::image::{ColorFormat, Image, ImageBuf};
+use twilight::video::decoder::lossless::LosslessDecoder;
+use twilight::video::decoder::DecoderStage;
+use twilight::video::zstd_delta::{ZstdDeltaDecoder, ZstdDeltaEncoder};
+
+const WIDTH: u32 = 67;
+const HEIGHT: u32 = 45;
+
+/// Frame with `padding` bytes at the end of every row, as captured from a GPU surface
+fn captured(color_format: ColorFormat, padding: u32, seed: u32) -> ImageBuf {
+    let stride = WIDTH * color_format.pixel_stride() + padding;
+    Image::new(
+        WIDTH,
+        HEIGHT,
+        stride,
+        color_format,
+        noise(seed, (stride * HEIGHT) as usize).into(),
+    )
+}
+
+fn rows(img: &ImageBuf) -> Vec<&[u8]> {
+    let line_len = (img.width * img.color_format.pixel_stride()) as usize;
+    img.data
+        .chunks(img.stride as usize)
+        .take(img.height as usize)
+        .map(|row| &row[..line_len])
+        .collect()
+}
+
+fn assert_identical(decoded: &ImageBuf, captured: &ImageBuf) {
+    assert_eq!(decoded.width, captured.width);
+    assert_eq!(decoded.height, captured.height);
+    assert_eq!(decoded.color_format, captured.color_format);
+    assert_eq!(rows(decoded), rows(captured));
+}
+
+#[test]
+fn key_and_delta_frames_are_bit_identical() {
+    for color_format in [
+        ColorFormat::Bgra8888,
+        ColorFormat::Rgba8888,
+        ColorFormat::Rgb24,
+    ] {
+        let mut encoder = ZstdDeltaEncoder::new();
+        let mut decoder = ZstdDeltaDecoder::new();
+
+        let mut frame = captured(color_format, 12, 1);
+        for i in 0..5 {
+            let data = encoder.encode(frame.as_data_ref(), false).unwrap();
+            assert_eq!(data[0], if i == 0 { 0 } else { 1 }, "frame kind");
+
+            let decoded = decoder.decode(&data).unwrap();
+            assert_identical(&decoded, &frame);
+
+            // change a few rows and keep the rest, like a typing user
+            let stride = frame.stride as usize;
+            let changed = noise(i + 2, stride * 3);
+            frame.data[stride * (i as usize * 7)..][..changed.len()].copy_from_slice(&changed);
+        }
+    }
+}
+
+#[test]
+fn unchanged_frame_is_bit_identical() {
+    let mut encoder = ZstdDeltaEncoder::new();
+    let mut decoder = ZstdDeltaDecoder::new();
+    let frame = captured(ColorFormat::Bgra8888, 0, 3);
+
+    for _ in 0..3 {
+        let data = encoder.encode(frame.as_data_ref(), false).unwrap();
+        assert_identical(&decoder.decode(&data).unwrap(), &frame);
+    }
+}
+
+#[test]
+fn key_frame_decodes_without_previous_frames() {
+    let mut encoder = ZstdDeltaEncoder::new();
+    let first = captured(ColorFormat::Bgra8888, 4, 4);
+    let second = captured(ColorFormat::Bgra8888, 4, 5);
+
+    encoder.encode(first.as_data_ref(), false).unwrap();
+    let delta = encoder.encode(second.as_data_ref(), false).unwrap();
+    assert!(ZstdDeltaDecoder::new().decode(&delta).is_err());
+
+    let key = encoder.encode(second.as_data_ref(), true).unwrap();
+    let decoded = ZstdDeltaDecoder::new().decode(&key).unwrap();
+    assert_identical(&decoded, &second);
+}
+
+#[test]
+fn resolution_change_starts_with_key_frame() {
+    let mut encoder = ZstdDeltaEncoder::new();
+    let mut decoder = LosslessDecoder::new(WIDTH, HEIGHT).unwrap();
+
+    let frame = captured(ColorFormat::Bgra8888, 0, 6);
+    let data = encoder.encode(frame.as_data_ref(), false).unwrap();
+    assert_identical(&decoder.decode(&data).unwrap(), &frame);
+
+    let smaller = Image::new(
+        WIDTH - 1,
+        HEIGHT,
+        frame.stride,
+        frame.color_format,
+        frame.data.clone(),
+    );
+    let data = encoder.encode(smaller.as_data_ref(), false).unwrap();
+    assert_eq!(data[0], 0, "frame kind");
+    assert_identical(&decoder.decode(&data).unwrap(), &smaller);
+}
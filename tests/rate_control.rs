@@ -0,0 +1,140 @@
+use std::time::{Duration, Instant};
+
+use twilight::video::encoder::{DeliveryReport, JpegParams, JpegRateControl};
+
+const FRAME_INTERVAL: Duration = Duration::from_millis(33);
+
+/// Acknowledge `frames` frames of `bytes` each, one every `FRAME_INTERVAL` after `start`,
+/// with a round trip of `rtt_ms` milliseconds.
+/// Returns when the last one was acknowledged.
+fn deliver(
+    rate: &mut JpegRateControl,
+    start: Instant,
+    frames: u32,
+    bytes: usize,
+    rtt_ms: u64,
+    in_flight: u32,
+) -> Instant {
+    let mut acked_at = start;
+    for i in 0..frames {
+        acked_at = start + FRAME_INTERVAL * i;
+        rate.on_delivery(&DeliveryReport {
+            bytes,
+            rtt: Duration::from_millis(rtt_ms),
+            acked_at,
+            in_flight,
+        });
+    }
+    acked_at
+}
+
+#[test]
+fn starts_at_max_quality() {
+    let rate = JpegRateControl::new(30, 90, true);
+
+    assert_eq!(
+        rate.params(),
+        JpegParams {
+            quality: 90,
+            yuv444: false
+        }
+    );
+}
+
+#[test]
+fn fast_link_enables_yuv444() {
+    let mut rate = JpegRateControl::new(30, 90, true);
+
+    // 100 KB every frame on a link of about 10 MB/s
+    deliver(&mut rate, Instant::now(), 90, 100_000, 10, 0);
+
+    assert_eq!(
+        rate.params(),
+        JpegParams {
+            quality: 90,
+            yuv444: true
+        }
+    );
+}
+
+#[test]
+fn yuv444_needs_to_be_allowed() {
+    let mut rate = JpegRateControl::new(30, 90, false);
+
+    deliver(&mut rate, Instant::now(), 90, 100_000, 10, 0);
+
+    assert!(!rate.params().yuv444);
+}
+
+#[test]
+fn high_latency_alone_is_not_congestion() {
+    let mut rate = JpegRateControl::new(30, 90, true);
+
+    // each frame takes 100ms to be acknowledged, but frames overlap and the link keeps up
+    deliver(&mut rate, Instant::now(), 90, 100_000, 100, 2);
+
+    assert_eq!(rate.params().quality, 90);
+    assert!(rate.params().yuv444);
+}
+
+#[test]
+fn frames_too_large_for_the_link_lower_quality() {
+    let mut rate = JpegRateControl::new(30, 90, false);
+    let start = Instant::now();
+
+    // about 5 MB/s at best, where a small frame fits in its budget
+    let last = deliver(&mut rate, start, 30, 100_000, 20, 1);
+    assert_eq!(rate.params().quality, 90);
+
+    // a frame 10 times larger does not, even though its round trip is within tolerance
+    rate.on_delivery(&DeliveryReport {
+        bytes: 1_000_000,
+        rtt: Duration::from_millis(70),
+        acked_at: last + FRAME_INTERVAL,
+        in_flight: 1,
+    });
+    assert!(rate.params().quality < 90);
+}
+
+#[test]
+fn large_frames_keeping_up_are_not_congestion() {
+    let mut rate = JpegRateControl::new(30, 90, false);
+
+    // acknowledged before the next frame is sent, so the link is waiting on the encoder
+    deliver(&mut rate, Instant::now(), 90, 1_000_000, 30, 0);
+
+    assert_eq!(rate.params().quality, 90);
+}
+
+#[test]
+fn frames_piling_up_lower_quality_down_to_min() {
+    let mut rate = JpegRateControl::new(30, 90, true);
+    let start = Instant::now();
+
+    // first decrease is immediate, later ones wait for frames sent before it to drain
+    deliver(&mut rate, start, 1, 10_000, 10, 5);
+    let first = rate.params().quality;
+    assert!(first < 90);
+
+    let last = deliver(&mut rate, start + FRAME_INTERVAL, 2, 10_000, 10, 5);
+    assert_eq!(rate.params().quality, first);
+
+    deliver(&mut rate, last + FRAME_INTERVAL, 300, 10_000, 10, 5);
+    assert_eq!(rate.params().quality, 30);
+}
+
+#[test]
+fn quality_recovers_once_link_keeps_up() {
+    let mut rate = JpegRateControl::new(30, 90, false);
+    let start = Instant::now();
+
+    let last = deliver(&mut rate, start, 300, 10_000, 10, 5);
+    assert_eq!(rate.params().quality, 30);
+
+    // one step per second at most
+    let last = deliver(&mut rate, last + FRAME_INTERVAL, 31, 10_000, 10, 0);
+    assert_eq!(rate.params().quality, 35);
+
+    deliver(&mut rate, last + FRAME_INTERVAL, 600, 10_000, 10, 0);
+    assert_eq!(rate.params().quality, 90);
+}
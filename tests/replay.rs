@@ -0,0 +1,211 @@
+use std::io::Cursor;
+use std::time::Duration;
+
+use twilight::image::{ColorFormat, Image, ImageBuf};
+use twilight::network::dto::video::{DesktopCodec, RefreshRate, Resolution};
+use twilight::schema::video::VideoCodec;
+use twilight::server::{
+    DesktopCaptureMethod, ReplayCaptureConfig, ServerConfig, SyntheticCaptureConfig,
+};
+use twilight::util::{CursorShape, CursorState};
+use twilight::video::capture::{ReplayReader, ReplayWriter};
+use twilight::video::capture_pipeline;
+
+const RESOLUTION: Resolution = Resolution {
+    width: 5,
+    height: 3,
+};
+
+const REFRESH_RATE: RefreshRate = RefreshRate {
+    num: 60000,
+    den: 1001,
+};
+
+fn image(color_format: ColorFormat, stride: u32) -> ImageBuf {
+    let len = (stride * RESOLUTION.height) as usize;
+    let data: Vec<u8> = (0..len).map(|x| (x * 7) as u8).collect();
+
+    Image::new(
+        RESOLUTION.width,
+        RESOLUTION.height,
+        stride,
+        color_format,
+        data.into(),
+    )
+}
+
+fn cursor() -> CursorState {
+    let data: Vec<u8> = (0..2 * 2 * 4).collect();
+
+    CursorState {
+        visible: true,
+        pos_x: 3,
+        pos_y: 1,
+        shape: Some(CursorShape {
+            image: Image::new(2, 2, 8, ColorFormat::Bgra8888, data.into()),
+            xor: true,
+            hotspot_x: 0.5,
+            hotspot_y: 1.0,
+        }),
+    }
+}
+
+/// Header, then a record of a frame with no cursor and `len` bytes claimed
+fn truncated_file(len: u32) -> Vec<u8> {
+    let writer = ReplayWriter::new(Vec::new(), &RESOLUTION, &REFRESH_RATE).unwrap();
+    let mut file = writer.into_inner();
+
+    file.extend_from_slice(&0u64.to_le_bytes());
+    file.push(VideoCodec::Jpeg.0 as u8);
+    file.extend_from_slice(&RESOLUTION.width.to_le_bytes());
+    file.extend_from_slice(&RESOLUTION.height.to_le_bytes());
+    file.push(0);
+    file.extend_from_slice(&len.to_le_bytes());
+    file.extend_from_slice(&[0; 16]);
+    file
+}
+
+#[test]
+fn frames_survive_round_trip() {
+    // padded rows are written tightly packed
+    let bgra = image(ColorFormat::Bgra8888, RESOLUTION.width * 4 + 4);
+    let rgb = image(ColorFormat::Rgb24, RESOLUTION.width * 3);
+    let jpeg = [0xFF, 0xD8, 1, 2, 3, 0xFF, 0xD9];
+
+    let mut writer = ReplayWriter::new(Vec::new(), &RESOLUTION, &REFRESH_RATE).unwrap();
+    writer
+        .write_image(Duration::ZERO, Some(&cursor()), bgra.as_data_ref())
+        .unwrap();
+    writer
+        .write_image(Duration::from_millis(16), None, rgb.as_data_ref())
+        .unwrap();
+    writer
+        .write_jpeg(Duration::from_millis(33), None, 5, 3, &jpeg)
+        .unwrap();
+    writer.flush().unwrap();
+
+    let mut reader = ReplayReader::new(Cursor::new(writer.into_inner())).unwrap();
+    assert_eq!(reader.resolution(), &RESOLUTION);
+    assert_eq!(reader.refresh_rate(), &REFRESH_RATE);
+
+    let frame = reader.next_frame().unwrap().unwrap();
+    assert_eq!(frame.timestamp, Duration::ZERO);
+    assert_eq!(frame.codec, VideoCodec::Bgra8888);
+    assert_eq!((frame.width, frame.height), (5, 3));
+
+    let decoded = frame.decode().unwrap();
+    assert_eq!(decoded.stride, RESOLUTION.width * 4);
+    for y in 0..RESOLUTION.height as usize {
+        let row = |img: &ImageBuf| img.data[y * img.stride as usize..][..20].to_vec();
+        assert_eq!(row(&decoded), row(&bgra), "row {y}");
+    }
+
+    let cursor = frame.cursor.unwrap();
+    assert!(cursor.visible);
+    assert_eq!((cursor.pos_x, cursor.pos_y), (3, 1));
+    let shape = cursor.shape.unwrap();
+    let expected = self::cursor().shape.unwrap();
+    assert!(shape.xor);
+    assert_eq!((shape.hotspot_x, shape.hotspot_y), (0.5, 1.0));
+    assert_eq!((shape.image.width, shape.image.height), (2, 2));
+    assert_eq!(&shape.image.data[..], &expected.image.data[..]);
+
+    let frame = reader.next_frame().unwrap().unwrap();
+    assert_eq!(frame.timestamp, Duration::from_millis(16));
+    assert_eq!(frame.codec, VideoCodec::Rgb24);
+    assert!(frame.cursor.is_none());
+    assert_eq!(&frame.decode().unwrap().data[..], &rgb.data[..]);
+
+    let frame = reader.next_frame().unwrap().unwrap();
+    assert_eq!(frame.timestamp, Duration::from_millis(33));
+    assert_eq!(frame.codec, VideoCodec::Jpeg);
+    assert_eq!(frame.data, jpeg);
+
+    assert!(reader.next_frame().unwrap().is_none());
+}
+
+#[test]
+fn truncated_frame_is_an_error() {
+    let file = truncated_file(17);
+    let mut reader = ReplayReader::new(Cursor::new(file)).unwrap();
+
+    assert!(reader.next_frame().is_err());
+}
+
+#[test]
+fn oversized_frame_is_rejected() {
+    // more than any codec takes for 5x3, which is not allocated
+    let file = truncated_file(u32::MAX);
+    let mut reader = ReplayReader::new(Cursor::new(file)).unwrap();
+
+    assert!(reader.next_frame().is_err());
+}
+
+#[test]
+fn oversized_resolution_is_rejected() {
+    let resolution = Resolution {
+        width: 1 << 20,
+        height: 1 << 20,
+    };
+    let writer = ReplayWriter::new(Vec::new(), &resolution, &REFRESH_RATE).unwrap();
+
+    assert!(ReplayReader::new(Cursor::new(writer.into_inner())).is_err());
+}
+
+#[test]
+fn pipeline_records_the_desktop() {
+    let dir = std::env::temp_dir().join(format!("twilight-record-{}", std::process::id()));
+    let defaults = ServerConfig::default();
+    let config = ServerConfig {
+        desktop_capture_method: Some(DesktopCaptureMethod::Synthetic),
+        synthetic: SyntheticCaptureConfig {
+            resolution: Resolution {
+                width: 64,
+                height: 48,
+            },
+            ..defaults.synthetic
+        },
+        replay: ReplayCaptureConfig {
+            record_dir: Some(dir.clone()),
+            record_jpeg_quality: Some(90),
+            ..defaults.replay
+        },
+        ..defaults
+    };
+
+    let output =
+        capture_pipeline(&config, "synthetic", Some(DesktopCodec::Jpeg), None, None).unwrap();
+    for _ in 0..3 {
+        output.rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    }
+    output.shutdown();
+
+    let path = std::fs::read_dir(&dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let file = std::fs::read(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let mut reader = ReplayReader::new(Cursor::new(file)).unwrap();
+    assert_eq!(
+        (reader.resolution().width, reader.resolution().height),
+        (64, 48)
+    );
+
+    let mut frames = 0;
+    let mut last = Duration::ZERO;
+    while let Some(frame) = reader.next_frame().unwrap() {
+        assert_eq!(frame.codec, VideoCodec::Jpeg);
+        assert!(frame.timestamp >= last);
+        last = frame.timestamp;
+
+        let decoded = frame.decode().unwrap();
+        assert_eq!((decoded.width, decoded.height), (64, 48));
+        frames += 1;
+    }
+    // every frame that reached the encoder was recorded first
+    assert!(frames >= 3, "{frames} frames");
+}